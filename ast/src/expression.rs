@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
-use crate::statement::BlockStatement;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone)]
+use crate::statement::{without_locations, BlockStatement};
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Expression {
     NullLiteral,
     StringLiteral(String),
@@ -49,13 +51,70 @@ pub enum Expression {
         left: Box<Expression>,
         index: Box<Expression>,
     },
+
+    /// `try { body } catch (name) { handler } finally { cleanup }`, at least one of `catch` and `finally` is present
+    TryExpression {
+        body: BlockStatement,
+        catch: Option<(String, BlockStatement)>,
+        finally: Option<BlockStatement>,
+    },
+}
+
+/// `string` between double quotes, with the characters the lexer unescapes escaped again
+pub fn quote(string: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in string.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl Expression {
+    /// Copy of the expression with the locations of the statements in its blocks set to the default
+    pub fn without_locations(&self) -> Expression {
+        let boxed = |expression: &Expression| Box::new(expression.without_locations());
+        let all = |expressions: &[Expression]| expressions.iter().map(Expression::without_locations).collect();
+        return match self {
+            Expression::NullLiteral
+            | Expression::StringLiteral(_)
+            | Expression::IntegerLiteral(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::Identifier(_) => self.clone(),
+            Expression::ArrayLiteral(elements) => Expression::ArrayLiteral(all(elements)),
+            Expression::HashLiteral(pairs) => Expression::HashLiteral(pairs.iter().map(|(key, value)| (key.without_locations(), value.without_locations())).collect()),
+            Expression::PrefixExpression { operator, right } => Expression::PrefixExpression { operator: operator.clone(), right: boxed(right) },
+            Expression::InfixExpression { left, operator, right } => Expression::InfixExpression { left: boxed(left), operator: operator.clone(), right: boxed(right) },
+            Expression::GroupedExpression { expression } => Expression::GroupedExpression { expression: boxed(expression) },
+            Expression::IfExpression { condition, consequence, alternative } => Expression::IfExpression {
+                condition: boxed(condition),
+                consequence: without_locations(consequence),
+                alternative: alternative.as_deref().map(without_locations),
+            },
+            Expression::FunctionLiteral { parameters, body } => Expression::FunctionLiteral { parameters: all(parameters), body: without_locations(body) },
+            Expression::CallExpression { function, arguments } => Expression::CallExpression { function: boxed(function), arguments: all(arguments) },
+            Expression::IndexExpression { left, index } => Expression::IndexExpression { left: boxed(left), index: boxed(index) },
+            Expression::TryExpression { body, catch, finally } => Expression::TryExpression {
+                body: without_locations(body),
+                catch: catch.as_ref().map(|(parameter, handler)| (parameter.clone(), without_locations(handler))),
+                finally: finally.as_deref().map(without_locations),
+            },
+        };
+    }
 }
 
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         return match self {
             Expression::NullLiteral => write!(f, "null"),
-            Expression::StringLiteral(string) => write!(f, "\"{}\"", string),
+            Expression::StringLiteral(string) => write!(f, "{}", quote(string)),
             Expression::IntegerLiteral(int) => write!(f, "{}", int),
             Expression::BooleanLiteral(boolean) => write!(f, "{}", boolean),
             Expression::Identifier(identifier) => write!(f, "{}", identifier),
@@ -72,7 +131,14 @@ impl Display for Expression {
             Expression::IfExpression { condition, consequence, alternative } => {
                 let mut result = String::new();
                 result.push_str("if ");
-                result.push_str(&condition.to_string());
+                match condition.as_ref() {
+                    // These are already printed between parentheses
+                    Expression::PrefixExpression { .. }
+                    | Expression::InfixExpression { .. }
+                    | Expression::IndexExpression { .. }
+                    | Expression::GroupedExpression { .. } => result.push_str(&condition.to_string()),
+                    _ => result.push_str(&format!("({})", condition)),
+                }
                 result.push_str(" { ");
                 consequence.iter().for_each(|statement| {
                     result.push_str(&statement.to_string());
@@ -108,6 +174,29 @@ impl Display for Expression {
                 return write!(f, "{}", result);
             }
             Expression::IndexExpression { left, index } => write!(f, "({}[{}])", left, index),
+            Expression::TryExpression { body, catch, finally } => {
+                let mut result = String::new();
+                result.push_str("try { ");
+                body.iter().for_each(|statement| {
+                    result.push_str(&statement.to_string());
+                });
+                result.push_str(" }");
+                if let Some((parameter, handler)) = catch {
+                    result.push_str(&format!(" catch ({}) {{ ", parameter));
+                    handler.iter().for_each(|statement| {
+                        result.push_str(&statement.to_string());
+                    });
+                    result.push_str(" }");
+                }
+                if let Some(finally) = finally {
+                    result.push_str(" finally { ");
+                    finally.iter().for_each(|statement| {
+                        result.push_str(&statement.to_string());
+                    });
+                    result.push_str(" }");
+                }
+                return write!(f, "{}", result);
+            }
             Expression::HashLiteral(pairs) => {
                 let mut result = String::new();
                 result.push_str("{");
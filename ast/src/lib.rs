@@ -1,3 +1,5 @@
 pub mod program;
 pub mod expression;
-pub mod statement;
\ No newline at end of file
+pub mod statement;
+pub mod location;
+pub mod tree;
@@ -0,0 +1,31 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Position of a statement in the source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub line: u32,
+    pub column: u32,
+    /// Source the statement was parsed from, `MAIN_SOURCE` for the program being run
+    pub source: u32,
+}
+
+/// Source of the program given to the interpreter, other sources such as modules are numbered from 1
+pub const MAIN_SOURCE: u32 = 0;
+
+impl Location {
+    pub fn new(line: u32, column: u32) -> Self {
+        return Self { line, column, source: MAIN_SOURCE };
+    }
+
+    pub fn in_source(line: u32, column: u32, source: u32) -> Self {
+        return Self { line, column, source };
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 use crate::statement::BlockStatement;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Program {
     pub statements: BlockStatement,
 }
@@ -27,6 +29,7 @@ impl Display for Program {
 #[cfg(test)]
 mod tests {
     use crate::expression::Expression;
+    use crate::location::Location;
     use crate::statement::Statement;
 
     use super::*;
@@ -38,11 +41,13 @@ mod tests {
                 Statement::LetStatement {
                     identifier: "myVar".to_string(),
                     value: Expression::IntegerLiteral(5),
+                    location: Location::default(),
                 },
                 Statement::ReturnStatement {
                     value: Expression::IntegerLiteral(10),
+                    location: Location::default(),
                 },
-                Statement::ExpressionStatement(Expression::IntegerLiteral(5)),
+                Statement::ExpressionStatement(Expression::IntegerLiteral(5), Location::default()),
             ],
         };
 
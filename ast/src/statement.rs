@@ -1,21 +1,44 @@
 use std::fmt::Display;
 
-use crate::expression::Expression;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Clone)]
+use crate::expression::{quote, Expression};
+use crate::location::Location;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Statement {
     EmptyStatement,
 
     LetStatement {
         identifier: String,
         value: Expression,
+        location: Location,
     },
 
     ReturnStatement {
         value: Expression,
+        location: Location,
+    },
+
+    ExpressionStatement(Expression, Location),
+
+    /// `throw value;`
+    ThrowStatement {
+        value: Expression,
+        location: Location,
     },
 
-    ExpressionStatement(Expression),
+    /// `import "path";` binds every export, `import { a, b } from "path";` only the given ones
+    ImportStatement {
+        path: String,
+        names: Option<Vec<String>>,
+        location: Location,
+    },
+
+    /// `export let name = value;`
+    ExportStatement {
+        statement: Box<Statement>,
+    },
 }
 
 pub type BlockStatement = Vec<Statement>;
@@ -24,16 +47,14 @@ impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         return match self {
             Statement::EmptyStatement => write!(f, ""),
-            Statement::LetStatement { identifier, value } => write!(f, "let {} = {};", identifier, value),
-            Statement::ReturnStatement { value } => write!(f, "return {};", value),
-            Statement::ExpressionStatement(expr) => {
-                if matches!(expr, Expression::IfExpression { .. } | Expression::FunctionLiteral { .. }) {
-                    // If the expression is an if expression or a function, we don't want to add a semicolon
-                    write!(f, "{}", expr)
-                } else {
-                    write!(f, "{};", expr)
-                }
-            }
+            Statement::LetStatement { identifier, value, .. } => write!(f, "let {} = {};", identifier, value),
+            Statement::ReturnStatement { value, .. } => write!(f, "return {};", value),
+            Statement::ThrowStatement { value, .. } => write!(f, "throw {};", value),
+            Statement::ImportStatement { path, names: None, .. } => write!(f, "import {};", quote(path)),
+            Statement::ImportStatement { path, names: Some(names), .. } => write!(f, "import {{ {} }} from {};", names.join(", "), quote(path)),
+            Statement::ExportStatement { statement } => write!(f, "export {}", statement),
+            // Always ended by a `;`, otherwise a statement starting with `(` or `[` would continue an `if` or a `fn` before it
+            Statement::ExpressionStatement(expr, _) => write!(f, "{};", expr),
         };
     }
 }
@@ -42,4 +63,35 @@ impl Statement {
     pub fn to_string(&self) -> String {
         return format!("{}", self);
     }
+
+    pub fn location(&self) -> Option<Location> {
+        return match self {
+            Statement::LetStatement { location, .. }
+            | Statement::ReturnStatement { location, .. }
+            | Statement::ThrowStatement { location, .. }
+            | Statement::ImportStatement { location, .. }
+            | Statement::ExpressionStatement(_, location) => Some(*location),
+            Statement::ExportStatement { statement } => statement.location(),
+            Statement::EmptyStatement => None,
+        };
+    }
+    /// Copy of the statement with every location, nested ones included, set to the default,
+    /// to compare trees parsed from different sources
+    pub fn without_locations(&self) -> Statement {
+        let location = Location::default();
+        return match self {
+            Statement::EmptyStatement => Statement::EmptyStatement,
+            Statement::LetStatement { identifier, value, .. } => Statement::LetStatement { identifier: identifier.clone(), value: value.without_locations(), location },
+            Statement::ReturnStatement { value, .. } => Statement::ReturnStatement { value: value.without_locations(), location },
+            Statement::ExpressionStatement(value, _) => Statement::ExpressionStatement(value.without_locations(), location),
+            Statement::ThrowStatement { value, .. } => Statement::ThrowStatement { value: value.without_locations(), location },
+            Statement::ImportStatement { path, names, .. } => Statement::ImportStatement { path: path.clone(), names: names.clone(), location },
+            Statement::ExportStatement { statement } => Statement::ExportStatement { statement: Box::new(statement.without_locations()) },
+        };
+    }
+}
+
+/// Copy of `block` with every location set to the default, see `Statement::without_locations`
+pub fn without_locations(block: &[Statement]) -> BlockStatement {
+    return block.iter().map(Statement::without_locations).collect();
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+use crate::expression::Expression;
+use crate::program::Program;
+use crate::statement::{BlockStatement, Statement};
+
+/// Renders a program as an indented tree, one node per line with its children below it.
+/// Statements show the line and column they start at.
+pub fn tree(program: &Program) -> String {
+    let mut output = String::from("Program\n");
+    block(&mut output, &program.statements, 1);
+    output
+}
+
+fn line(output: &mut String, depth: usize, label: &str) {
+    output.push_str(&"  ".repeat(depth));
+    output.push_str(label);
+    output.push('\n');
+}
+
+fn block(output: &mut String, statements: &BlockStatement, depth: usize) {
+    for statement in statements {
+        self::statement(output, statement, depth);
+    }
+}
+
+fn statement(output: &mut String, statement: &Statement, depth: usize) {
+    let position = statement.location().map(|location| format!(" @ {}:{}", location.line, location.column)).unwrap_or_default();
+
+    match statement {
+        Statement::EmptyStatement => line(output, depth, "Empty"),
+        Statement::LetStatement { identifier, value, .. } => {
+            line(output, depth, &format!("Let {}{}", identifier, position));
+            expression(output, value, depth + 1);
+        }
+        Statement::ReturnStatement { value, .. } => {
+            line(output, depth, &format!("Return{}", position));
+            expression(output, value, depth + 1);
+        }
+        Statement::ThrowStatement { value, .. } => {
+            line(output, depth, &format!("Throw{}", position));
+            expression(output, value, depth + 1);
+        }
+        Statement::ExpressionStatement(value, _) => {
+            line(output, depth, &format!("Expression{}", position));
+            expression(output, value, depth + 1);
+        }
+        Statement::ImportStatement { path, names: None, .. } => line(output, depth, &format!("Import {:?}{}", path, position)),
+        Statement::ImportStatement { path, names: Some(names), .. } => {
+            line(output, depth, &format!("Import {:?} {{ {} }}{}", path, names.join(", "), position))
+        }
+        Statement::ExportStatement { statement } => {
+            line(output, depth, "Export");
+            self::statement(output, statement, depth + 1);
+        }
+    }
+}
+
+fn expression(output: &mut String, expression: &Expression, depth: usize) {
+    match expression {
+        Expression::NullLiteral => line(output, depth, "Null"),
+        Expression::StringLiteral(string) => line(output, depth, &format!("String {:?}", string)),
+        Expression::IntegerLiteral(integer) => line(output, depth, &format!("Integer {}", integer)),
+        Expression::BooleanLiteral(boolean) => line(output, depth, &format!("Boolean {}", boolean)),
+        Expression::Identifier(identifier) => line(output, depth, &format!("Identifier {}", identifier)),
+        Expression::ArrayLiteral(elements) => {
+            line(output, depth, "Array");
+            elements.iter().for_each(|element| self::expression(output, element, depth + 1));
+        }
+        Expression::HashLiteral(pairs) => {
+            line(output, depth, "Hash");
+            for (key, value) in pairs {
+                line(output, depth + 1, "Pair");
+                self::expression(output, key, depth + 2);
+                self::expression(output, value, depth + 2);
+            }
+        }
+        Expression::PrefixExpression { operator, right } => {
+            line(output, depth, &format!("Prefix {}", operator));
+            self::expression(output, right, depth + 1);
+        }
+        Expression::InfixExpression { left, operator, right } => {
+            line(output, depth, &format!("Infix {}", operator));
+            self::expression(output, left, depth + 1);
+            self::expression(output, right, depth + 1);
+        }
+        Expression::GroupedExpression { expression } => {
+            line(output, depth, "Grouped");
+            self::expression(output, expression, depth + 1);
+        }
+        Expression::IfExpression { condition, consequence, alternative } => {
+            line(output, depth, "If");
+            self::expression(output, condition, depth + 1);
+            line(output, depth + 1, "Then");
+            block(output, consequence, depth + 2);
+            if let Some(alternative) = alternative {
+                line(output, depth + 1, "Else");
+                block(output, alternative, depth + 2);
+            }
+        }
+        Expression::FunctionLiteral { parameters, body } => {
+            let parameters = parameters.iter().map(|parameter| parameter.to_string()).collect::<Vec<String>>();
+            line(output, depth, &format!("Function ({})", parameters.join(", ")));
+            block(output, body, depth + 1);
+        }
+        Expression::CallExpression { function, arguments } => {
+            line(output, depth, "Call");
+            self::expression(output, function, depth + 1);
+            if !arguments.is_empty() {
+                line(output, depth + 1, "Arguments");
+                arguments.iter().for_each(|argument| self::expression(output, argument, depth + 2));
+            }
+        }
+        Expression::IndexExpression { left, index } => {
+            line(output, depth, "Index");
+            self::expression(output, left, depth + 1);
+            self::expression(output, index, depth + 1);
+        }
+        Expression::TryExpression { body, catch, finally } => {
+            line(output, depth, "Try");
+            block(output, body, depth + 1);
+            if let Some((parameter, handler)) = catch {
+                line(output, depth + 1, &format!("Catch ({})", parameter));
+                block(output, handler, depth + 2);
+            }
+            if let Some(finally) = finally {
+                line(output, depth + 1, "Finally");
+                block(output, finally, depth + 2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::location::Location;
+
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        let program = Program {
+            statements: vec![
+                Statement::LetStatement {
+                    identifier: "add".to_string(),
+                    value: Expression::FunctionLiteral {
+                        parameters: vec![Expression::Identifier("a".to_string()), Expression::Identifier("b".to_string())],
+                        body: vec![Statement::ReturnStatement {
+                            value: Expression::InfixExpression {
+                                left: Box::new(Expression::Identifier("a".to_string())),
+                                operator: "+".to_string(),
+                                right: Box::new(Expression::Identifier("b".to_string())),
+                            },
+                            location: Location::new(1, 22),
+                        }],
+                    },
+                    location: Location::new(1, 1),
+                },
+                Statement::ExpressionStatement(Expression::CallExpression {
+                    function: Box::new(Expression::Identifier("add".to_string())),
+                    arguments: vec![Expression::IntegerLiteral(1), Expression::StringLiteral("two".to_string())],
+                }, Location::new(2, 1)),
+            ],
+        };
+
+        assert_eq!(tree(&program), "Program
+  Let add @ 1:1
+    Function (a, b)
+      Return @ 1:22
+        Infix +
+          Identifier a
+          Identifier b
+  Expression @ 2:1
+    Call
+      Identifier add
+      Arguments
+        Integer 1
+        String \"two\"
+");
+
+        let json = serde_json::to_value(&program).unwrap();
+        assert_eq!(json["statements"][0]["LetStatement"]["location"]["column"], 1);
+        let parsed: Program = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.statements, program.statements);
+    }
+}
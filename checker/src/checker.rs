@@ -0,0 +1,535 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use ast::expression::Expression;
+use ast::location::Location;
+use ast::program::Program;
+use ast::statement::{BlockStatement, Statement};
+use evaluator::builtins::{get_builtin, get_builtin_arity};
+
+/// Names the evaluator handles itself instead of looking them up
+static SPECIAL_FORMS: [&str; 2] = ["quote", "unquote"];
+/// Top level bindings with this prefix are called by the test runner
+const TEST_PREFIX: &str = "test_";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A mistake found without running the program
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum Lint {
+    #[error("Unknown identifier: {identifier}")]
+    UnknownIdentifier {
+        identifier: String,
+    },
+
+    #[error("Wrong number of arguments for `{function}`: expected {expected}, got {actual}")]
+    WrongNumberOfArguments {
+        function: String,
+        expected: String,
+        actual: usize,
+    },
+
+    #[error("{name} is a built-in function")]
+    BuiltInFunction {
+        name: String,
+    },
+
+    #[error("{name} is hidden by the built-in function of the same name")]
+    HiddenByBuiltIn {
+        name: String,
+    },
+
+    #[error("{name} is never used")]
+    UnusedBinding {
+        name: String,
+    },
+
+    #[error("Unreachable code after `{statement}`")]
+    UnreachableCode {
+        statement: String,
+    },
+}
+
+impl Lint {
+    /// Errors stop the program when it reaches them, warnings point at code that does not do anything
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::UnknownIdentifier { .. } | Lint::WrongNumberOfArguments { .. } | Lint::BuiltInFunction { .. } => Severity::Error,
+            Lint::HiddenByBuiltIn { .. } | Lint::UnusedBinding { .. } | Lint::UnreachableCode { .. } => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    /// Location of the statement the lint was found in
+    pub location: Location,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        self.lint.severity()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}: {}", self.location.line, self.location.column, self.severity(), self.lint)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BindingKind {
+    Let,
+    Import,
+    Parameter,
+    Catch,
+}
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    kind: BindingKind,
+    location: Location,
+    used: bool,
+    exported: bool,
+}
+
+/// Bindings of the program or of a function body, blocks do not have their own scope
+#[derive(Debug, Default)]
+struct Scope {
+    bindings: Vec<Binding>,
+    /// Names bound anywhere in the scope, functions can use the ones bound after they are created
+    declared: HashSet<String>,
+    /// Names used by functions before they are bound
+    used_later: HashSet<String>,
+    /// Set by an `import` whose exports are unknown, any name may be bound after it
+    open: bool,
+}
+
+/// Names exported by the module at a path, `None` when they are unknown
+type ExportsResolver<'a> = Box<dyn Fn(&str) -> Option<Vec<String>> + 'a>;
+
+/// Resolves the scopes of a program to find mistakes without running it
+pub struct Checker<'a> {
+    globals: HashSet<String>,
+    exports: ExportsResolver<'a>,
+    scopes: Vec<Scope>,
+    location: Location,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Adds the names bound by `let`, `import` and `catch` outside of function literals
+fn declared_names(statements: &BlockStatement, names: &mut HashSet<String>) {
+    statements.iter().for_each(|statement| declared_in_statement(statement, names));
+}
+
+fn declared_in_statement(statement: &Statement, names: &mut HashSet<String>) {
+    match statement {
+        Statement::LetStatement { identifier, value, .. } => {
+            names.insert(identifier.clone());
+            declared_in_expression(value, names);
+        }
+        Statement::ExportStatement { statement } => declared_in_statement(statement, names),
+        Statement::ImportStatement { names: Some(imported), .. } => names.extend(imported.iter().cloned()),
+        Statement::ReturnStatement { value, .. } | Statement::ThrowStatement { value, .. } | Statement::ExpressionStatement(value, _) => {
+            declared_in_expression(value, names)
+        }
+        Statement::ImportStatement { names: None, .. } | Statement::EmptyStatement => {}
+    }
+}
+
+fn declared_in_expression(expression: &Expression, names: &mut HashSet<String>) {
+    match expression {
+        Expression::PrefixExpression { right, .. } => declared_in_expression(right, names),
+        Expression::InfixExpression { left, right, .. } => {
+            declared_in_expression(left, names);
+            declared_in_expression(right, names);
+        }
+        Expression::GroupedExpression { expression } => declared_in_expression(expression, names),
+        Expression::IfExpression { condition, consequence, alternative } => {
+            declared_in_expression(condition, names);
+            declared_names(consequence, names);
+            if let Some(alternative) = alternative {
+                declared_names(alternative, names);
+            }
+        }
+        Expression::TryExpression { body, catch, finally } => {
+            declared_names(body, names);
+            if let Some((_, handler)) = catch {
+                declared_names(handler, names);
+            }
+            if let Some(finally) = finally {
+                declared_names(finally, names);
+            }
+        }
+        Expression::CallExpression { function, arguments } => {
+            declared_in_expression(function, names);
+            arguments.iter().for_each(|argument| declared_in_expression(argument, names));
+        }
+        Expression::IndexExpression { left, index } => {
+            declared_in_expression(left, names);
+            declared_in_expression(index, names);
+        }
+        Expression::ArrayLiteral(elements) => elements.iter().for_each(|element| declared_in_expression(element, names)),
+        Expression::HashLiteral(pairs) => pairs.iter().for_each(|(key, value)| {
+            declared_in_expression(key, names);
+            declared_in_expression(value, names);
+        }),
+        Expression::FunctionLiteral { .. } | Expression::Identifier(_) | Expression::NullLiteral | Expression::StringLiteral(_)
+        | Expression::IntegerLiteral(_) | Expression::BooleanLiteral(_) => {}
+    }
+}
+
+/// Expected number of arguments, as written in the error message
+fn arity(minimum: usize, maximum: Option<usize>) -> String {
+    match maximum {
+        Some(maximum) if maximum == minimum => minimum.to_string(),
+        Some(maximum) => format!("{} to {}", minimum, maximum),
+        None => format!("at least {}", minimum),
+    }
+}
+
+impl<'a> Checker<'a> {
+    /// `globals` are the names bound before the program runs, like the prelude
+    pub fn new(globals: Vec<String>) -> Self {
+        return Self {
+            globals: globals.into_iter().collect(),
+            exports: Box::new(|_| None),
+            scopes: vec![],
+            location: Location::default(),
+            diagnostics: vec![],
+        };
+    }
+
+    /// Resolves the names bound by `import "path";`, without it such an import may bind any name
+    pub fn with_exports(mut self, exports: impl Fn(&str) -> Option<Vec<String>> + 'a) -> Self {
+        self.exports = Box::new(exports);
+        self
+    }
+
+    /// Diagnostics of a program, in the order of the source
+    pub fn check(mut self, program: &Program) -> Vec<Diagnostic> {
+        self.enter_scope(&program.statements, &[]);
+        self.block(&program.statements);
+        self.exit_scope();
+
+        self.diagnostics.sort_by_key(|diagnostic| (diagnostic.location.line, diagnostic.location.column));
+        self.diagnostics
+    }
+
+    fn report(&mut self, lint: Lint, location: Location) {
+        self.diagnostics.push(Diagnostic { lint, location });
+    }
+
+    fn enter_scope(&mut self, statements: &BlockStatement, parameters: &[Expression]) {
+        let mut scope = Scope::default();
+        declared_names(statements, &mut scope.declared);
+        self.scopes.push(scope);
+
+        for parameter in parameters {
+            self.bind(&parameter.to_string(), BindingKind::Parameter, false);
+        }
+    }
+
+    fn exit_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        let top_level = self.scopes.is_empty();
+        for binding in scope.bindings {
+            let test = top_level && binding.name.starts_with(TEST_PREFIX);
+            let reported = matches!(binding.kind, BindingKind::Let | BindingKind::Import) && !test;
+            if reported && !binding.used && !binding.exported && !binding.name.starts_with('_') {
+                self.report(Lint::UnusedBinding { name: binding.name }, binding.location);
+            }
+        }
+    }
+
+    fn bind(&mut self, name: &str, kind: BindingKind, exported: bool) {
+        if get_builtin(name).is_some() {
+            if kind == BindingKind::Let {
+                // The evaluator refuses the binding
+                self.report(Lint::BuiltInFunction { name: name.to_string() }, self.location);
+                return;
+            }
+            self.report(Lint::HiddenByBuiltIn { name: name.to_string() }, self.location);
+        }
+
+        let scope = self.scopes.last_mut().unwrap();
+        let used = scope.used_later.remove(name) || kind == BindingKind::Parameter;
+        scope.bindings.push(Binding { name: name.to_string(), kind, location: self.location, used, exported });
+    }
+
+    fn resolve(&mut self, name: &str) {
+        if get_builtin(name).is_some() || SPECIAL_FORMS.contains(&name) {
+            return;
+        }
+
+        let innermost = self.scopes.len() - 1;
+        for (depth, scope) in self.scopes.iter_mut().enumerate().rev() {
+            if let Some(binding) = scope.bindings.iter_mut().rev().find(|binding| binding.name == name) {
+                binding.used = true;
+                return;
+            }
+            // Code runs in order, only the functions created before a binding can use it
+            if depth != innermost && scope.declared.contains(name) {
+                scope.used_later.insert(name.to_string());
+                return;
+            }
+        }
+
+        if self.globals.contains(name) || self.scopes.iter().any(|scope| scope.open) {
+            return;
+        }
+        self.report(Lint::UnknownIdentifier { identifier: name.to_string() }, self.location);
+    }
+
+    fn block(&mut self, statements: &BlockStatement) {
+        let mut terminated: Option<&str> = None;
+        let mut reported = false;
+        for statement in statements {
+            if let Statement::EmptyStatement = statement {
+                continue;
+            }
+            self.location = statement.location().unwrap_or(self.location);
+
+            // Only the first unreachable statement of a block is reported
+            if let (Some(keyword), false) = (terminated, reported) {
+                self.report(Lint::UnreachableCode { statement: keyword.to_string() }, self.location);
+                reported = true;
+            }
+            if terminated.is_none() {
+                terminated = match statement {
+                    Statement::ReturnStatement { .. } => Some("return"),
+                    Statement::ThrowStatement { .. } => Some("throw"),
+                    _ => None,
+                };
+            }
+
+            self.statement(statement, false);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, exported: bool) {
+        let location = statement.location().unwrap_or(self.location);
+        self.location = location;
+
+        match statement {
+            Statement::EmptyStatement => {}
+            Statement::LetStatement { identifier, value, .. } => {
+                self.expression(value);
+                self.location = location;
+                self.bind(identifier, BindingKind::Let, exported);
+            }
+            Statement::ReturnStatement { value, .. } | Statement::ThrowStatement { value, .. } | Statement::ExpressionStatement(value, _) => {
+                self.expression(value)
+            }
+            Statement::ImportStatement { names: Some(names), .. } => {
+                for name in names {
+                    self.bind(name, BindingKind::Import, false);
+                }
+            }
+            Statement::ImportStatement { path, names: None, .. } => match (self.exports)(path) {
+                Some(names) => {
+                    for name in names {
+                        // Every export is bound, using only some of them is expected
+                        self.bind(&name, BindingKind::Parameter, false);
+                    }
+                }
+                None => self.scopes.last_mut().unwrap().open = true,
+            },
+            Statement::ExportStatement { statement } => self.statement(statement, true),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::NullLiteral | Expression::StringLiteral(_) | Expression::IntegerLiteral(_) | Expression::BooleanLiteral(_) => {}
+            Expression::Identifier(name) => self.resolve(name),
+            Expression::PrefixExpression { right, .. } => self.expression(right),
+            Expression::InfixExpression { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::GroupedExpression { expression } => self.expression(expression),
+            Expression::ArrayLiteral(elements) => elements.iter().for_each(|element| self.expression(element)),
+            Expression::HashLiteral(pairs) => pairs.iter().for_each(|(key, value)| {
+                self.expression(key);
+                self.expression(value);
+            }),
+            Expression::IndexExpression { left, index } => {
+                self.expression(left);
+                self.expression(index);
+            }
+            Expression::IfExpression { condition, consequence, alternative } => {
+                self.expression(condition);
+                self.block(consequence);
+                if let Some(alternative) = alternative {
+                    self.block(alternative);
+                }
+            }
+            Expression::TryExpression { body, catch, finally } => {
+                let location = self.location;
+                self.block(body);
+                if let Some((parameter, handler)) = catch {
+                    self.location = location;
+                    // The parameter is only bound in the handler
+                    let index = self.scopes.last().unwrap().bindings.len();
+                    self.bind(parameter, BindingKind::Catch, false);
+                    self.block(handler);
+                    self.scopes.last_mut().unwrap().bindings.remove(index);
+                }
+                if let Some(finally) = finally {
+                    self.block(finally);
+                }
+            }
+            Expression::FunctionLiteral { parameters, body } => {
+                self.enter_scope(body, parameters);
+                self.block(body);
+                self.exit_scope();
+            }
+            Expression::CallExpression { function, arguments } => {
+                if let Expression::Identifier(name) = function.as_ref() {
+                    let expected = match name.as_str() {
+                        "quote" => Some((1, Some(1))),
+                        _ => get_builtin_arity(name),
+                    };
+                    if let Some((minimum, maximum)) = expected {
+                        if arguments.len() < minimum || maximum.map(|maximum| arguments.len() > maximum).unwrap_or(false) {
+                            let lint = Lint::WrongNumberOfArguments { function: name.clone(), expected: arity(minimum, maximum), actual: arguments.len() };
+                            self.report(lint, self.location);
+                        }
+                    }
+                    // Quoted code is not evaluated
+                    if name == "quote" {
+                        return;
+                    }
+                }
+
+                self.expression(function);
+                arguments.iter().for_each(|argument| self.expression(argument));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evaluator::prelude::prelude_names;
+    use lexer::lexer::Lexer;
+    use parser::parser::Parser;
+
+    use super::*;
+
+    fn check(source: &str) -> Vec<(u32, Lint)> {
+        check_with(Checker::new(prelude_names()), source)
+    }
+
+    fn check_with(checker: Checker, source: &str) -> Vec<(u32, Lint)> {
+        let program = Parser::new(Lexer::new(source.to_string())).unwrap().parse_program().unwrap();
+        checker.check(&program).into_iter().map(|diagnostic| (diagnostic.location.line, diagnostic.lint)).collect()
+    }
+
+    fn unknown(identifier: &str) -> Lint {
+        Lint::UnknownIdentifier { identifier: identifier.to_string() }
+    }
+
+    #[test]
+    fn test_unknown_identifiers() {
+        let source = "let a = b;
+let f = fn() { a + g() };
+let g = fn() { map([a], identity) };
+f();
+let h = fn(x) {
+    let y = z + x;
+    let z = 1;
+    y + z
+};
+h(1);";
+        assert_eq!(check(source), vec![(1, unknown("b")), (6, unknown("z"))]);
+        assert_eq!(check_with(Checker::new(vec![]), "map([1], identity);"), vec![(1, unknown("map")), (1, unknown("identity"))]);
+        assert_eq!(check("try { 1 } catch (e) { e };\ne;"), vec![(2, unknown("e"))]);
+    }
+
+    #[test]
+    fn test_builtins() {
+        let source = "len(1, 2);
+push([1]);
+print();
+json_stringify(1, true, 3);
+let len = 1;
+let f = fn(first) { first };
+f(1);
+quote(1, 2);";
+
+        let wrong_arguments = |function: &str, expected: &str, actual: usize| Lint::WrongNumberOfArguments {
+            function: function.to_string(),
+            expected: expected.to_string(),
+            actual,
+        };
+        assert_eq!(check(source), vec![
+            (1, wrong_arguments("len", "1", 2)),
+            (2, wrong_arguments("push", "2", 1)),
+            (4, wrong_arguments("json_stringify", "1 to 2", 3)),
+            (5, Lint::BuiltInFunction { name: "len".to_string() }),
+            (6, Lint::HiddenByBuiltIn { name: "first".to_string() }),
+            (8, wrong_arguments("quote", "1", 2)),
+        ]);
+    }
+
+    #[test]
+    fn test_unused_and_unreachable() {
+        let source = "let a = 1;
+let _b = 2;
+export let c = 3;
+import { d } from \"lib\";
+let f = fn(x) {
+    if (x) { return 1; a; }
+    throw 2;
+    x;
+    x
+};
+let g = try { f(true) } catch (e) { 0 };
+let test_f = fn() { let test_inner = 1; assert_eq(f(false), 1) };";
+
+        let unused = |name: &str| Lint::UnusedBinding { name: name.to_string() };
+        let unreachable = |statement: &str| Lint::UnreachableCode { statement: statement.to_string() };
+        assert_eq!(check(source), vec![(4, unused("d")), (6, unreachable("return")), (8, unreachable("throw")), (11, unused("g")), (12, unused("test_inner"))]);
+    }
+
+    #[test]
+    fn test_imports() {
+        let checker = Checker::new(vec![]).with_exports(|path| (path == "lib").then(|| vec!["x".to_string()]));
+        assert_eq!(check_with(checker, "import \"lib\"; x + y;"), vec![(1, unknown("y"))]);
+
+        let checker = Checker::new(vec![]).with_exports(|path| (path == "lib").then(|| vec!["x".to_string()]));
+        assert_eq!(check_with(checker, "y; import \"other\"; x + y;"), vec![(1, unknown("y"))]);
+    }
+
+    #[test]
+    fn test_examples() {
+        let sources = [
+            include_str!("../../examples/4.4-map.monkey"),
+            include_str!("../../examples/4.4-reduce.monkey"),
+        ];
+
+        for source in sources {
+            assert_eq!(check(source), vec![], "{}", source);
+        }
+    }
+}
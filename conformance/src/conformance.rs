@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Extension of the programs of the suite
+const CASE_EXTENSION: &str = "monkey";
+/// Extension of the file holding the expected outcome of a program, next to it
+const EXPECTED_EXTENSION: &str = "expected";
+/// Files run by the test runner rather than on their own
+const TEST_FILE_SUFFIX: &str = "_test.monkey";
+
+/// What running a program produced
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Outcome {
+    /// Everything the program printed
+    pub output: String,
+    /// Value of the program, when it ran to the end
+    pub value: Option<String>,
+    /// Error message and backtrace, when it failed
+    pub error: Option<String>,
+}
+
+impl Outcome {
+    /// Text of an expected file, a `-- name --` header starts each section and empty sections are left out
+    pub fn to_expected(&self) -> String {
+        let mut text = String::new();
+        let sections = [("output", Some(&self.output)), ("value", self.value.as_ref()), ("error", self.error.as_ref())];
+        for (name, content) in sections {
+            match content {
+                Some(content) if !content.is_empty() => {
+                    text.push_str(&format!("-- {} --\n{}", name, content));
+                    if !content.ends_with('\n') {
+                        text.push('\n');
+                    }
+                }
+                _ => {}
+            }
+        }
+        text
+    }
+
+    pub fn from_expected(text: &str) -> Self {
+        let mut outcome = Outcome::default();
+        let mut section: Option<&mut String> = None;
+        let (mut value, mut error) = (None, None);
+
+        for line in text.split_inclusive('\n') {
+            match line.trim_end() {
+                "-- output --" => section = Some(&mut outcome.output),
+                "-- value --" => section = Some(value.insert(String::new())),
+                "-- error --" => section = Some(error.insert(String::new())),
+                _ => {
+                    if let Some(content) = section.as_mut() {
+                        content.push_str(line);
+                    }
+                }
+            }
+        }
+
+        // Values and errors are compared without their final newline, the output keeps it
+        outcome.value = value.map(|value| value.trim_end_matches('\n').to_string());
+        outcome.error = error.map(|error| error.trim_end_matches('\n').to_string());
+        outcome
+    }
+}
+
+/// A way to run Monkey programs, every backend is checked against the same expected files
+pub trait Backend {
+    fn run(&self, file: &Path) -> Result<Outcome, anyhow::Error>;
+}
+
+/// Runs each program with an interpreter executable, like `interpreter -f file.monkey`
+pub struct ProcessBackend {
+    program: PathBuf,
+    arguments: Vec<String>,
+}
+
+impl ProcessBackend {
+    /// `arguments` come before the path of the program to run
+    pub fn new(program: impl Into<PathBuf>, arguments: &[&str]) -> Self {
+        return Self {
+            program: program.into(),
+            arguments: arguments.iter().map(|argument| argument.to_string()).collect(),
+        };
+    }
+}
+
+impl Backend for ProcessBackend {
+    fn run(&self, file: &Path) -> Result<Outcome, anyhow::Error> {
+        let output = Command::new(&self.program).args(&self.arguments).arg(file).output()?;
+        Ok(process_outcome(&String::from_utf8_lossy(&output.stdout), &String::from_utf8_lossy(&output.stderr)))
+    }
+}
+
+/// Reads the outcome from what the interpreter wrote: its value is the last line of stdout,
+/// errors are logged to stderr after a `[time LEVEL target]` prefix
+fn process_outcome(stdout: &str, stderr: &str) -> Outcome {
+    let mut errors = vec![];
+    let mut in_error = false;
+    for line in stderr.lines() {
+        match line.strip_prefix('[').and_then(|line| line.split_once("] ")) {
+            Some((header, message)) => {
+                in_error = header.split_whitespace().nth(1) == Some("ERROR");
+                if in_error {
+                    errors.push(message.to_string());
+                }
+            }
+            // Lines following a log line continue its message
+            None if in_error => errors.push(line.trim().to_string()),
+            None => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return Outcome { output: stdout.to_string(), value: None, error: Some(errors.join("\n")) };
+    }
+
+    let trimmed = stdout.strip_suffix('\n').unwrap_or(stdout);
+    let (output, value) = match trimmed.rsplit_once('\n') {
+        Some((output, value)) => (format!("{}\n", output), value),
+        None => (String::new(), trimmed),
+    };
+    Outcome { output, value: Some(value.to_string()), error: None }
+}
+
+/// Programs found under `roots`, sorted by path
+pub fn cases(roots: &[PathBuf]) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut cases = vec![];
+    let mut directories = roots.to_vec();
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(&directory)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().map(|extension| extension == CASE_EXTENSION).unwrap_or(false) && !name.ends_with(TEST_FILE_SUFFIX) {
+                cases.push(path);
+            }
+        }
+    }
+    cases.sort();
+    Ok(cases)
+}
+
+pub fn expected_path(case: &Path) -> PathBuf {
+    case.with_extension(EXPECTED_EXTENSION)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    Passed,
+    /// The expected file was written from the outcome
+    Blessed,
+    /// Lines of the expected file and of the outcome, the differing ones marked with `-` and `+`
+    Failed(Vec<String>),
+}
+
+/// Runs a program and compares its outcome to its expected file, or writes the file with `bless`
+pub fn check(backend: &dyn Backend, case: &Path, bless: bool) -> Result<Verdict, anyhow::Error> {
+    let actual = backend.run(case)?;
+    let path = expected_path(case);
+
+    if bless {
+        std::fs::write(&path, actual.to_expected())?;
+        return Ok(Verdict::Blessed);
+    }
+
+    let expected = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(_) => return Ok(Verdict::Failed(vec![format!("{} is missing, run with --bless to write it", path.display())])),
+    };
+    if Outcome::from_expected(&expected) == actual {
+        return Ok(Verdict::Passed);
+    }
+    Ok(Verdict::Failed(diff(&expected, &actual.to_expected())))
+}
+
+fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let expected = expected.lines().collect::<Vec<&str>>();
+    let actual = actual.lines().collect::<Vec<&str>>();
+
+    let mut lines = vec![];
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(expected), Some(actual)) if expected == actual => lines.push(format!("  {}", expected)),
+            (expected, actual) => {
+                lines.extend(expected.map(|expected| format!("- {}", expected)));
+                lines.extend(actual.map(|actual| format!("+ {}", actual)));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_format() {
+        let outcome = Outcome { output: "a\nb\n".to_string(), value: Some("[1, 2]".to_string()), error: None };
+        assert_eq!(outcome.to_expected(), "-- output --\na\nb\n-- value --\n[1, 2]\n");
+        assert_eq!(Outcome::from_expected(&outcome.to_expected()), outcome);
+
+        let outcome = Outcome { output: String::new(), value: None, error: Some("Error: x\nat <main>".to_string()) };
+        assert_eq!(outcome.to_expected(), "-- error --\nError: x\nat <main>\n");
+        assert_eq!(Outcome::from_expected(&outcome.to_expected()), outcome);
+    }
+
+    #[test]
+    fn test_process_outcome() {
+        let stderr = "[2023-01-01T00:00:00Z INFO  interpreter] Executing file: a.monkey\n";
+        assert_eq!(process_outcome("hi\nthere\n5\n", stderr), Outcome {
+            output: "hi\nthere\n".to_string(),
+            value: Some("5".to_string()),
+            error: None,
+        });
+
+        let stderr = "[2023-01-01T00:00:00Z ERROR repl::interpreter] Error: Unknown\n[2023-01-01T00:00:00Z ERROR repl::interpreter] Backtrace:\n        at <main> (line 1, column 2)\n";
+        assert_eq!(process_outcome("hi\n", stderr), Outcome {
+            output: "hi\n".to_string(),
+            value: None,
+            error: Some("Error: Unknown\nBacktrace:\nat <main> (line 1, column 2)".to_string()),
+        });
+    }
+}
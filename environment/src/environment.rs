@@ -1,25 +1,28 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::object::ObjectType;
 
+// Functions capture a copy of their environment, sharing the stores keeps these copies cheap.
+// A store is only copied when a shared environment is modified.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Environment {
-    store: HashMap<String, ObjectType>,
-    outer: Option<Box<Environment>>,
+    store: Arc<HashMap<String, ObjectType>>,
+    outer: Option<Arc<Environment>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
         return Self {
-            store: HashMap::new(),
+            store: Arc::new(HashMap::new()),
             outer: None,
         };
     }
 
     pub fn new_enclosed(outer: &Environment) -> Environment {
         return Environment {
-            store: HashMap::new(),
-            outer: Some(Box::new(outer.clone())), // TODO: sad clone
+            store: Arc::new(HashMap::new()),
+            outer: Some(Arc::new(outer.clone())),
         };
     }
 
@@ -34,13 +37,28 @@ impl Environment {
         return None;
     }
 
+    /// Bindings of this scope, without the ones of the outer scopes
+    pub fn store(&self) -> &HashMap<String, ObjectType> {
+        return &self.store;
+    }
+
+    pub fn outer(&self) -> Option<&Environment> {
+        return self.outer.as_deref();
+    }
+
     pub fn set(&mut self, name: &str, value: ObjectType) {
-        self.store.insert(name.to_string(), value.clone());
+        Arc::make_mut(&mut self.store).insert(name.to_string(), value);
+    }
+
+    /// Whether both scopes hold the same bindings because one is an unmodified copy of the other
+    pub fn shares_store(&self, other: &Environment) -> bool {
+        return Arc::ptr_eq(&self.store, &other.store);
     }
 
     pub fn merge(&mut self, other: &Environment) {
-        for (key, value) in &other.store {
-            self.store.insert(key.to_string(), value.clone());
+        let store = Arc::make_mut(&mut self.store);
+        for (key, value) in other.store.iter() {
+            store.insert(key.to_string(), value.clone());
         }
     }
 }
\ No newline at end of file
@@ -1,8 +1,15 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::{Error, SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use ast::expression::Expression;
 use ast::statement::BlockStatement;
-use error::EvaluatorError;
+pub use error::EvaluatorError;
+pub use macros::{FromObject, IntoObject};
 
 use crate::environment::Environment;
 
@@ -15,6 +22,7 @@ pub enum ObjectType {
     Null,
 
     Integer(i64),
+    Float(f64),
     Boolean(bool),
     String(String),
     Return(Box<ObjectType>),
@@ -23,12 +31,14 @@ pub enum ObjectType {
     Quote(Box<Expression>),
 
     Function {
+        /// Name of the `let` binding the function literal was assigned to
+        name: Option<String>,
         parameters: Vec<Expression>,
         body: BlockStatement,
         environment: Environment,
     },
 
-    Builtin(fn(&Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError>),
+    Builtin(fn(&[&mut ObjectType]) -> Result<ObjectType, EvaluatorError>),
 
     Array(Vec<ObjectType>),
 }
@@ -38,6 +48,7 @@ impl PartialEq for ObjectType {
         match (self, other) {
             (ObjectType::Null, ObjectType::Null) => true,
             (ObjectType::Integer(i), ObjectType::Integer(j)) => i == j,
+            (ObjectType::Float(i), ObjectType::Float(j)) => i == j,
             (ObjectType::Boolean(b), ObjectType::Boolean(c)) => b == c,
             (ObjectType::String(s), ObjectType::String(t)) => s == t,
             (ObjectType::Return(obj), other) => obj.as_ref() == other,
@@ -52,12 +63,32 @@ impl PartialEq for ObjectType {
 
 }
 
+impl ObjectType {
+    /// Name of the type of the value, `Return` values are named after the returned value
+    pub fn type_name(&self) -> &'static str {
+        return match self {
+            ObjectType::Null => "Null",
+            ObjectType::Integer(_) => "Integer",
+            ObjectType::Float(_) => "Float",
+            ObjectType::Boolean(_) => "Boolean",
+            ObjectType::String(_) => "String",
+            ObjectType::Return(value) => value.type_name(),
+            ObjectType::Hash(_) => "Hash",
+            ObjectType::Quote(_) => "Quote",
+            ObjectType::Function { .. } => "Function",
+            ObjectType::Builtin(_) => "Builtin",
+            ObjectType::Array(_) => "Array",
+        };
+    }
+}
+
 
 impl Object for ObjectType {
     fn inspect(&self) -> String {
         match self {
             ObjectType::Null => "null".to_string(),
             ObjectType::Integer(i) => format!("{}", i),
+            ObjectType::Float(f) => format!("{:?}", f),
             ObjectType::Boolean(b) => format!("{}", b),
             ObjectType::String(s) => format!("{}", s),
             ObjectType::Return(obj) => obj.inspect(),
@@ -104,4 +135,376 @@ impl Display for ObjectType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.inspect())
     }
-}
\ No newline at end of file
+}
+
+/// Only data objects can be serialized, functions and quotes are reported as errors
+impl Serialize for ObjectType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ObjectType::Null => serializer.serialize_unit(),
+            ObjectType::Integer(i) => serializer.serialize_i64(*i),
+            ObjectType::Float(f) => serializer.serialize_f64(*f),
+            ObjectType::Boolean(b) => serializer.serialize_bool(*b),
+            ObjectType::String(s) => serializer.serialize_str(s),
+            ObjectType::Return(obj) => obj.serialize(serializer),
+            ObjectType::Array(arr) => {
+                let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+                for element in arr {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            ObjectType::Hash(hash) => {
+                let mut map = serializer.serialize_map(Some(hash.len()))?;
+                for (key, value) in hash {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            ObjectType::Function { .. } | ObjectType::Builtin(_) | ObjectType::Quote(_) => {
+                Err(S::Error::custom(format!("`{}` cannot be serialized", self)))
+            }
+        }
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = ObjectType;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("null, a boolean, a number, a string, an array or a map")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(ObjectType::Boolean(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(ObjectType::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        // Integers too large for an i64 lose precision instead of failing
+        Ok(i64::try_from(v).map(ObjectType::Integer).unwrap_or(ObjectType::Float(v as f64)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(ObjectType::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(ObjectType::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(ObjectType::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(ObjectType::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(ObjectType::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        ObjectType::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut arr = Vec::new();
+        while let Some(element) = seq.next_element()? {
+            arr.push(element);
+        }
+        Ok(ObjectType::Array(arr))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut hash = Vec::new();
+        while let Some((key, value)) = map.next_entry()? {
+            hash.push((key, value));
+        }
+        Ok(ObjectType::Hash(hash))
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+/// Conversion from a Rust value to an `ObjectType`, failing for integers out of the range of `i64`
+pub trait IntoObject {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError>;
+}
+
+/// Conversion from an `ObjectType` to a Rust value
+pub trait FromObject: Sized {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError>;
+}
+
+/// Conversion from the arguments given to a builtin to a tuple of Rust values
+pub trait FromArguments: Sized {
+    fn from_arguments(args: &[&mut ObjectType]) -> Result<Self, EvaluatorError>;
+}
+
+/// Returns the value stored under the string key `key` of a hash
+pub fn hash_get(pairs: &Vec<(ObjectType, ObjectType)>, key: &str) -> Option<ObjectType> {
+    for (k, v) in pairs {
+        if let ObjectType::String(k) = k {
+            if k == key {
+                return Some(v.clone());
+            }
+        }
+    }
+    None
+}
+
+fn convert_error(expected: &str, object: &ObjectType) -> EvaluatorError {
+    EvaluatorError::cannot_convert_object(expected, object.to_string().as_str())
+}
+
+impl IntoObject for ObjectType {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(self)
+    }
+}
+
+impl FromObject for ObjectType {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        Ok(object)
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::Null)
+    }
+}
+
+impl FromObject for () {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::Null => Ok(()),
+            _ => Err(convert_error("null", &object)),
+        }
+    }
+}
+
+macro_rules! integer_conversion {
+    ($($ty:ty),*) => {
+        $(
+            impl IntoObject for $ty {
+                fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+                    i64::try_from(self).map(ObjectType::Integer).map_err(|_| EvaluatorError::cannot_convert_object("Integer", &self.to_string()))
+                }
+            }
+
+            impl FromObject for $ty {
+                fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+                    match object {
+                        ObjectType::Integer(i) => <$ty>::try_from(i).map_err(|_| convert_error(stringify!($ty), &object)),
+                        _ => Err(convert_error(stringify!($ty), &object)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_conversion!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::Float(self))
+    }
+}
+
+impl FromObject for f64 {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::Float(f) => Ok(f),
+            ObjectType::Integer(i) => Ok(i as f64),
+            _ => Err(convert_error("f64", &object)),
+        }
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::Boolean(self))
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::Boolean(b) => Ok(b),
+            _ => Err(convert_error("bool", &object)),
+        }
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::String(self))
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::String(self.to_string()))
+    }
+}
+
+impl FromObject for String {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::String(s) => Ok(s),
+            _ => Err(convert_error("String", &object)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        match self {
+            Some(value) => value.into_object(),
+            None => Ok(ObjectType::Null),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::Null => Ok(None),
+            _ => Ok(Some(T::from_object(object)?)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::Array(self.into_iter().map(|v| v.into_object()).collect::<Result<_, _>>()?))
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::Array(arr) => arr.into_iter().map(T::from_object).collect(),
+            _ => Err(convert_error("Vec", &object)),
+        }
+    }
+}
+
+impl<K: IntoObject, V: IntoObject> IntoObject for HashMap<K, V> {
+    fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+        Ok(ObjectType::Hash(self.into_iter().map(|(k, v)| Ok((k.into_object()?, v.into_object()?))).collect::<Result<_, EvaluatorError>>()?))
+    }
+}
+
+impl<K: FromObject + Eq + Hash, V: FromObject> FromObject for HashMap<K, V> {
+    fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+        match object {
+            ObjectType::Hash(hash) => hash.into_iter()
+                .map(|(k, v)| Ok((K::from_object(k)?, V::from_object(v)?)))
+                .collect(),
+            _ => Err(convert_error("HashMap", &object)),
+        }
+    }
+}
+
+macro_rules! tuple_conversion {
+    ($len:expr => $($name:ident),+) => {
+        impl<$($name: IntoObject),+> IntoObject for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn into_object(self) -> Result<ObjectType, EvaluatorError> {
+                let ($($name,)+) = self;
+                Ok(ObjectType::Array(vec![$($name.into_object()?),+]))
+            }
+        }
+
+        impl<$($name: FromObject),+> FromObject for ($($name,)+) {
+            fn from_object(object: ObjectType) -> Result<Self, EvaluatorError> {
+                match object {
+                    ObjectType::Array(arr) if arr.len() == $len => {
+                        let mut iter = arr.into_iter();
+                        Ok(($($name::from_object(iter.next().unwrap())?,)+))
+                    }
+                    _ => Err(convert_error(concat!("tuple of ", stringify!($len)), &object)),
+                }
+            }
+        }
+
+        impl<$($name: FromObject),+> FromArguments for ($($name,)+) {
+            fn from_arguments(args: &[&mut ObjectType]) -> Result<Self, EvaluatorError> {
+                if args.len() != $len {
+                    return Err(EvaluatorError::wrong_number_of_arguments($len, args.len()));
+                }
+                let mut iter = args.iter();
+                Ok(($($name::from_object((*iter.next().unwrap()).clone())?,)+))
+            }
+        }
+    };
+}
+
+tuple_conversion!(1 => A);
+tuple_conversion!(2 => A, B);
+tuple_conversion!(3 => A, B, C);
+tuple_conversion!(4 => A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_conversion() {
+        assert_eq!(5i64.into_object(), Ok(ObjectType::Integer(5)));
+        assert_eq!(true.into_object(), Ok(ObjectType::Boolean(true)));
+        assert_eq!("foo".into_object(), Ok(ObjectType::String("foo".to_string())));
+        assert_eq!(None::<i64>.into_object(), Ok(ObjectType::Null));
+
+        assert_eq!(i64::from_object(ObjectType::Integer(5)), Ok(5));
+        assert_eq!(Option::<bool>::from_object(ObjectType::Null), Ok(None));
+        assert_eq!(u8::from_object(ObjectType::Integer(300)), Err(EvaluatorError::cannot_convert_object("u8", "300")));
+        assert_eq!(String::from_object(ObjectType::Integer(1)), Err(EvaluatorError::cannot_convert_object("String", "1")));
+
+        // Integers wider than `i64` are checked both ways
+        assert_eq!(u64::MAX.into_object(), Err(EvaluatorError::cannot_convert_object("Integer", "18446744073709551615")));
+        assert_eq!(usize::MAX.into_object(), Err(EvaluatorError::cannot_convert_object("Integer", &usize::MAX.to_string())));
+        assert_eq!(vec![1, u64::MAX].into_object(), Err(EvaluatorError::cannot_convert_object("Integer", "18446744073709551615")));
+        assert_eq!(u64::from_object(ObjectType::Integer(i64::MAX)), Ok(i64::MAX as u64));
+        assert_eq!(u64::from_object(ObjectType::Integer(-1)), Err(EvaluatorError::cannot_convert_object("u64", "-1")));
+    }
+
+    #[test]
+    fn test_collection_conversion() {
+        let array = vec![1, 2, 3].into_object().unwrap();
+        assert_eq!(array, ObjectType::Array(vec![ObjectType::Integer(1), ObjectType::Integer(2), ObjectType::Integer(3)]));
+        assert_eq!(Vec::<i32>::from_object(array), Ok(vec![1, 2, 3]));
+
+        let tuple = (1, "one".to_string()).into_object().unwrap();
+        assert_eq!(tuple, ObjectType::Array(vec![ObjectType::Integer(1), ObjectType::String("one".to_string())]));
+        assert_eq!(<(i64, String)>::from_object(tuple), Ok((1, "one".to_string())));
+
+        let map = HashMap::from([("a".to_string(), 1)]).into_object().unwrap();
+        assert_eq!(map, ObjectType::Hash(vec![(ObjectType::String("a".to_string()), ObjectType::Integer(1))]));
+        assert_eq!(HashMap::<String, i64>::from_object(map), Ok(HashMap::from([("a".to_string(), 1)])));
+    }
+
+    #[test]
+    fn test_arguments_conversion() {
+        let mut first = ObjectType::Integer(1);
+        let mut second = ObjectType::String("two".to_string());
+        let args = vec![&mut first, &mut second];
+
+        assert_eq!(<(i64, String)>::from_arguments(&args), Ok((1, "two".to_string())));
+        assert_eq!(<(i64,)>::from_arguments(&args), Err(EvaluatorError::wrong_number_of_arguments(1, 2)));
+    }
+}
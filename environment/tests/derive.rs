@@ -0,0 +1,27 @@
+use environment::object::{EvaluatorError, FromObject, IntoObject, ObjectType};
+
+#[derive(Debug, PartialEq, IntoObject, FromObject)]
+struct Point {
+    x: i64,
+    y: i64,
+    label: Option<String>,
+}
+
+#[test]
+fn test_derive_conversion() {
+    let point = Point { x: 1, y: 2, label: None };
+    let object = Point { x: 1, y: 2, label: None }.into_object().unwrap();
+
+    assert_eq!(object.to_string(), "{x: 1, y: 2, label: null}");
+    assert_eq!(Point::from_object(object), Ok(point));
+
+    let partial = ObjectType::Hash(vec![
+        (ObjectType::String("x".to_string()), ObjectType::Integer(3)),
+        (ObjectType::String("y".to_string()), ObjectType::Integer(4)),
+    ]);
+    assert_eq!(Point::from_object(partial), Ok(Point { x: 3, y: 4, label: None }));
+
+    let missing = ObjectType::Hash(vec![(ObjectType::String("x".to_string()), ObjectType::Integer(3))]);
+    assert_eq!(Point::from_object(missing), Err(EvaluatorError::no_such_key("y".to_string())));
+    assert_eq!(Point::from_object(ObjectType::Integer(1)), Err(EvaluatorError::cannot_convert_object("Point", "1")));
+}
@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-#[derive(Error, Debug, PartialEq)]
+#[derive(Error, Debug, PartialEq, Clone)]
 pub enum EvaluatorError {
     #[error("Operator not supported: {actual}")]
     OperatorNotSupported {
@@ -17,7 +17,8 @@ pub enum EvaluatorError {
     #[error("Wrong number of arguments for `{function}`: expected {expected}, got {actual}")]
     WrongNumberOfArguments {
         function: String,
-        expected: usize,
+        /// A number, or a range like `1 to 2`
+        expected: String,
         actual: usize,
     },
 
@@ -57,6 +58,28 @@ pub enum EvaluatorError {
         column: u32,
     },
 
+    #[error("Integer literal {actual} is too large at line {line}, column {column}")]
+    IntegerTooLarge {
+        actual: String,
+        line: u32,
+        column: u32,
+    },
+
+    #[error("Expression nested more than {limit} levels deep at line {line}, column {column}")]
+    NestingTooDeep {
+        limit: usize,
+        line: u32,
+        column: u32,
+    },
+
+    #[error("Division by zero")]
+    DivisionByZero,
+
+    #[error("Integer overflow in {expression}")]
+    IntegerOverflow {
+        expression: String,
+    },
+
     #[error("{index} is out of bounds for array of size {size}")]
     IndexOutOfBounds {
         index: i64,
@@ -78,6 +101,90 @@ pub enum EvaluatorError {
         actual: String,
     },
 
+    #[error("Cannot convert object `{actual}` to {expected}")]
+    CannotConvertObject {
+        expected: String,
+        actual: String,
+    },
+
+    #[error("JSON error: {message}")]
+    JsonError {
+        message: String,
+    },
+
+    #[error("Maximum call depth of {limit} exceeded")]
+    CallDepthExceeded {
+        limit: usize,
+    },
+
+    #[error("Maximum number of evaluation steps ({limit}) exceeded")]
+    StepLimitExceeded {
+        limit: u64,
+    },
+
+    #[error("Collection of size {size} exceeds the maximum size of {limit}")]
+    CollectionTooLarge {
+        size: usize,
+        limit: usize,
+    },
+
+    #[error("Evaluation interrupted")]
+    Interrupted,
+
+    #[error("Evaluation timed out after {timeout_ms}ms")]
+    Timeout {
+        timeout_ms: u64,
+    },
+
+    #[error("Module {path} not found")]
+    ModuleNotFound {
+        path: String,
+    },
+
+    #[error("Cyclic import: {cycle}")]
+    CyclicImport {
+        cycle: String,
+    },
+
+    #[error("{name} is not exported by {module}")]
+    NotExported {
+        name: String,
+        module: String,
+    },
+
+    #[error("Cannot load module {path}: {message}")]
+    ModuleError {
+        path: String,
+        message: String,
+    },
+
+    #[error("Uncaught exception: {value}")]
+    Thrown {
+        value: String,
+    },
+
+    #[error("Assertion failed: {message}")]
+    AssertionFailed {
+        message: String,
+    },
+
+    #[error("Assertion failed: expected {expected}, got {actual}")]
+    AssertionNotEqual {
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Could not write the output: {message}")]
+    OutputError {
+        message: String,
+    },
+
+    /// Reading commands from or writing to the terminal of the debugger failed
+    #[error("Debugger input/output error: {message}")]
+    IoError {
+        message: String,
+    },
+
     #[error("Unknown error")]
     UnknownError,
 }
@@ -106,7 +213,7 @@ impl EvaluatorError {
     pub fn wrong_number_of_arguments2(function: &str, expected: usize, actual: usize) -> EvaluatorError {
         EvaluatorError::WrongNumberOfArguments {
             function: function.to_string(),
-            expected,
+            expected: expected.to_string(),
             actual,
         }
 
@@ -114,7 +221,16 @@ impl EvaluatorError {
     pub fn wrong_number_of_arguments(expected: usize, actual: usize) -> EvaluatorError {
         EvaluatorError::WrongNumberOfArguments {
             function: "unknown".to_string(),
-            expected,
+            expected: expected.to_string(),
+            actual,
+        }
+    }
+
+    /// For a function taking from `minimum` to `maximum` arguments
+    pub fn wrong_number_of_arguments_between(function: &str, minimum: usize, maximum: usize, actual: usize) -> EvaluatorError {
+        EvaluatorError::WrongNumberOfArguments {
+            function: function.to_string(),
+            expected: format!("{} to {}", minimum, maximum),
             actual,
         }
     }
@@ -146,6 +262,14 @@ impl EvaluatorError {
         }
     }
 
+    pub fn integer_too_large(actual: String, line: u32, column: u32) -> EvaluatorError {
+        EvaluatorError::IntegerTooLarge {
+            actual,
+            line,
+            column,
+        }
+    }
+
     pub fn expected_token(expected: &str, actual: &str, line: u32, column: u32) -> EvaluatorError {
         EvaluatorError::UnexpectedToken {
             expected: expected.to_string(),
@@ -155,6 +279,24 @@ impl EvaluatorError {
         }
     }
 
+    pub fn nesting_too_deep(limit: usize, line: u32, column: u32) -> EvaluatorError {
+        EvaluatorError::NestingTooDeep {
+            limit,
+            line,
+            column,
+        }
+    }
+
+    pub fn division_by_zero() -> EvaluatorError {
+        EvaluatorError::DivisionByZero
+    }
+
+    pub fn integer_overflow(expression: String) -> EvaluatorError {
+        EvaluatorError::IntegerOverflow {
+            expression,
+        }
+    }
+
     pub fn index_out_of_bounds(index: i64, size: usize) -> EvaluatorError {
         EvaluatorError::IndexOutOfBounds {
             index,
@@ -186,7 +328,158 @@ impl EvaluatorError {
         }
     }
 
+    pub fn cannot_convert_object(expected: &str, actual: &str) -> EvaluatorError {
+        EvaluatorError::CannotConvertObject {
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }
+    }
+
+    pub fn json_error(message: String) -> EvaluatorError {
+        EvaluatorError::JsonError {
+            message,
+        }
+    }
+
+    pub fn call_depth_exceeded(limit: usize) -> EvaluatorError {
+        EvaluatorError::CallDepthExceeded {
+            limit,
+        }
+    }
+
+    pub fn step_limit_exceeded(limit: u64) -> EvaluatorError {
+        EvaluatorError::StepLimitExceeded {
+            limit,
+        }
+    }
+
+    pub fn collection_too_large(size: usize, limit: usize) -> EvaluatorError {
+        EvaluatorError::CollectionTooLarge {
+            size,
+            limit,
+        }
+    }
+
+    pub fn interrupted() -> EvaluatorError {
+        EvaluatorError::Interrupted
+    }
+
+    pub fn timeout(timeout_ms: u64) -> EvaluatorError {
+        EvaluatorError::Timeout {
+            timeout_ms,
+        }
+    }
+
+    pub fn module_not_found(path: &str) -> EvaluatorError {
+        EvaluatorError::ModuleNotFound {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn cyclic_import(cycle: String) -> EvaluatorError {
+        EvaluatorError::CyclicImport {
+            cycle,
+        }
+    }
+
+    pub fn not_exported(name: &str, module: &str) -> EvaluatorError {
+        EvaluatorError::NotExported {
+            name: name.to_string(),
+            module: module.to_string(),
+        }
+    }
+
+    pub fn module_error(path: &str, message: String) -> EvaluatorError {
+        EvaluatorError::ModuleError {
+            path: path.to_string(),
+            message,
+        }
+    }
+
+    pub fn thrown(value: String) -> EvaluatorError {
+        EvaluatorError::Thrown {
+            value,
+        }
+    }
+
+    pub fn assertion_failed(message: String) -> EvaluatorError {
+        EvaluatorError::AssertionFailed {
+            message,
+        }
+    }
+
+    pub fn assertion_not_equal(expected: String, actual: String) -> EvaluatorError {
+        EvaluatorError::AssertionNotEqual {
+            expected,
+            actual,
+        }
+    }
+
+    pub fn output_error(message: String) -> EvaluatorError {
+        EvaluatorError::OutputError {
+            message,
+        }
+    }
+
+    pub fn io_error(message: String) -> EvaluatorError {
+        EvaluatorError::IoError {
+            message,
+        }
+    }
+
     pub fn unknown_error() -> EvaluatorError {
         EvaluatorError::UnknownError
     }
+
+    /// Name of the variant, exposed to scripts as the `kind` of caught errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EvaluatorError::OperatorNotSupported { .. } => "OperatorNotSupported",
+            EvaluatorError::TypeMismatch { .. } => "TypeMismatch",
+            EvaluatorError::WrongNumberOfArguments { .. } => "WrongNumberOfArguments",
+            EvaluatorError::MissingArgument { .. } => "MissingArgument",
+            EvaluatorError::ArgumentTypeNotSupported { .. } => "ArgumentTypeNotSupported",
+            EvaluatorError::BuiltInFunction { .. } => "BuiltInFunction",
+            EvaluatorError::ReservedKeyword { .. } => "ReservedKeyword",
+            EvaluatorError::UnknownIdentifier { .. } => "UnknownIdentifier",
+            EvaluatorError::UnexpectedToken { .. } => "UnexpectedToken",
+            EvaluatorError::UnfinishedString { .. } => "UnfinishedString",
+            EvaluatorError::IntegerTooLarge { .. } => "IntegerTooLarge",
+            EvaluatorError::NestingTooDeep { .. } => "NestingTooDeep",
+            EvaluatorError::DivisionByZero => "DivisionByZero",
+            EvaluatorError::IntegerOverflow { .. } => "IntegerOverflow",
+            EvaluatorError::IndexOutOfBounds { .. } => "IndexOutOfBounds",
+            EvaluatorError::NoSuchKey { .. } => "NoSuchKey",
+            EvaluatorError::KeyNotSupported { .. } => "KeyNotSupported",
+            EvaluatorError::CannotConvertObjectToExpression { .. } => "CannotConvertObjectToExpression",
+            EvaluatorError::CannotConvertObject { .. } => "CannotConvertObject",
+            EvaluatorError::JsonError { .. } => "JsonError",
+            EvaluatorError::CallDepthExceeded { .. } => "CallDepthExceeded",
+            EvaluatorError::StepLimitExceeded { .. } => "StepLimitExceeded",
+            EvaluatorError::CollectionTooLarge { .. } => "CollectionTooLarge",
+            EvaluatorError::Interrupted => "Interrupted",
+            EvaluatorError::Timeout { .. } => "Timeout",
+            EvaluatorError::ModuleNotFound { .. } => "ModuleNotFound",
+            EvaluatorError::CyclicImport { .. } => "CyclicImport",
+            EvaluatorError::NotExported { .. } => "NotExported",
+            EvaluatorError::ModuleError { .. } => "ModuleError",
+            EvaluatorError::Thrown { .. } => "Thrown",
+            EvaluatorError::AssertionFailed { .. } => "AssertionFailed",
+            EvaluatorError::AssertionNotEqual { .. } => "AssertionNotEqual",
+            EvaluatorError::OutputError { .. } => "OutputError",
+            EvaluatorError::IoError { .. } => "IoError",
+            EvaluatorError::UnknownError => "UnknownError",
+        }
+    }
+
+    /// Resource limits, interruptions and a lost debugger terminal stop the whole program, scripts cannot catch them
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self,
+            EvaluatorError::CallDepthExceeded { .. }
+            | EvaluatorError::StepLimitExceeded { .. }
+            | EvaluatorError::CollectionTooLarge { .. }
+            | EvaluatorError::Interrupted
+            | EvaluatorError::Timeout { .. }
+            | EvaluatorError::IoError { .. })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+use std::fmt::Display;
+
+use ast::location::Location;
+
+use crate::module::Modules;
+
+/// Name shown for the statements that are not in a function
+pub const MAIN_FRAME: &str = "<main>";
+
+/// Name shown for the functions that were not bound with `let`
+pub const ANONYMOUS_FUNCTION: &str = "<anonymous>";
+
+/// A function call being evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Name of the called function
+    pub function: String,
+    /// Location of the statement that made the call
+    pub call_site: Location,
+}
+
+/// Function of a backtrace with the location being evaluated in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktraceFrame {
+    pub function: String,
+    /// Name of the module or prelude file, `None` for the main program
+    pub source: Option<String>,
+    pub location: Location,
+}
+
+/// Functions being evaluated when an error happened, the innermost first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Backtrace {
+    pub frames: Vec<BacktraceFrame>,
+}
+
+impl Backtrace {
+    /// Builds the backtrace of an error raised at `location` while `call_stack` is being evaluated
+    pub fn new(call_stack: &[Frame], location: Location, modules: &Modules) -> Self {
+        let frame = |function: &str, location: Location| BacktraceFrame {
+            function: function.to_string(),
+            source: modules.source_name(location.source).map(|name| name.to_string()),
+            location,
+        };
+
+        let mut frames = vec![];
+        let mut location = location;
+
+        for call in call_stack.iter().rev() {
+            frames.push(frame(&call.function, location));
+            location = call.call_site;
+        }
+        frames.push(frame(MAIN_FRAME, location));
+
+        return Self { frames };
+    }
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self.frames.iter()
+            .map(|frame| match &frame.source {
+                Some(source) => format!("    at {} ({}, {})", frame.function, source, frame.location),
+                None => format!("    at {} ({})", frame.function, frame.location),
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let mut modules = Modules::default();
+        let library = modules.add_source("lib.monkey".to_string());
+        let call_stack = vec![
+            Frame { function: "outer".to_string(), call_site: Location::new(9, 6) },
+            Frame { function: "inner".to_string(), call_site: Location::new(5, 10) },
+        ];
+
+        let backtrace = Backtrace::new(&call_stack, Location::in_source(2, 8, library), &modules);
+        assert_eq!(backtrace.to_string(), "    at inner (lib.monkey, line 2, column 8)\n    at outer (line 5, column 10)\n    at <main> (line 9, column 6)");
+    }
+}
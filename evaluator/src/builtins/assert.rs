@@ -0,0 +1,59 @@
+use environment::object::{Object, ObjectType};
+use error::EvaluatorError;
+
+/// assert(condition, message = "expected true, got {condition}")
+pub fn assert(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvaluatorError::wrong_number_of_arguments2("assert", 1, args.len()));
+    }
+    let condition = &args[0];
+    if !matches!(condition, ObjectType::Boolean(false) | ObjectType::Null) {
+        return Ok(ObjectType::Null);
+    }
+
+    let message = match args.get(1) {
+        Some(ObjectType::String(message)) => message.clone(),
+        Some(message) => message.inspect(),
+        None => format!("expected true, got {}", condition.inspect()),
+    };
+    Err(EvaluatorError::assertion_failed(message))
+}
+
+/// assert_eq(actual, expected)
+pub fn assert_eq(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    if args.len() != 2 {
+        return Err(EvaluatorError::wrong_number_of_arguments2("assert_eq", 2, args.len()));
+    }
+    if args[0] == args[1] {
+        return Ok(ObjectType::Null);
+    }
+    Err(EvaluatorError::assertion_not_equal(args[1].inspect(), args[0].inspect()))
+}
+
+/// assert_error(function, kind), only reached when the builtin is not called by its name since it has to call `function`
+pub fn assert_error(_args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    Err(EvaluatorError::assertion_failed("assert_error can only be called by its name".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assertions() {
+        assert_eq!(assert(&vec![&mut ObjectType::Boolean(true)]), Ok(ObjectType::Null));
+        assert_eq!(assert(&vec![&mut ObjectType::Integer(0)]), Ok(ObjectType::Null));
+        assert_eq!(assert(&vec![&mut ObjectType::Null]), Err(EvaluatorError::assertion_failed("expected true, got null".to_string())));
+        assert_eq!(
+            assert(&vec![&mut ObjectType::Boolean(false), &mut ObjectType::String("empty list".to_string())]),
+            Err(EvaluatorError::assertion_failed("empty list".to_string()))
+        );
+
+        let mut array = ObjectType::Array(vec![ObjectType::Integer(1)]);
+        assert_eq!(assert_eq(&vec![&mut array.clone(), &mut array]), Ok(ObjectType::Null));
+        assert_eq!(
+            assert_eq(&vec![&mut ObjectType::Integer(1), &mut array]),
+            Err(EvaluatorError::assertion_not_equal("[1]".to_string(), "1".to_string()))
+        );
+    }
+}
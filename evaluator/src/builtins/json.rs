@@ -0,0 +1,36 @@
+use environment::object::ObjectType;
+use error::EvaluatorError;
+
+pub fn json_parse(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    if args.len() != 1 {
+        return Err(EvaluatorError::wrong_number_of_arguments(1, args.len()));
+    }
+    let first = &args[0];
+    match first {
+        ObjectType::String(s) => serde_json::from_str(s).map_err(|e| EvaluatorError::json_error(e.to_string())),
+        _ => Err(EvaluatorError::argument_type_not_supported("json_parse", first.to_string().as_str())),
+    }
+}
+
+/// json_stringify(value, pretty = false)
+pub fn json_stringify(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(EvaluatorError::wrong_number_of_arguments_between("json_stringify", 1, 2, args.len()));
+    }
+    let pretty = match args.get(1) {
+        None => false,
+        Some(ObjectType::Boolean(pretty)) => *pretty,
+        Some(other) => return Err(EvaluatorError::argument_type_not_supported("json_stringify", other.to_string().as_str())),
+    };
+
+    let value: &ObjectType = args[0];
+    let result = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+
+    result
+        .map(ObjectType::String)
+        .map_err(|e| EvaluatorError::json_error(e.to_string()))
+}
@@ -1,10 +1,12 @@
 use environment::object::ObjectType;
 
+use crate::builtins::assert::{assert, assert_eq, assert_error};
 use crate::builtins::first::first;
+use crate::builtins::json::{json_parse, json_stringify};
 use crate::builtins::last::last;
 use crate::builtins::len::len;
 use crate::builtins::pop::pop;
-use crate::builtins::print::{print, println};
+use crate::builtins::print::{eprint, eprintln, print, println};
 use crate::builtins::push::push;
 use crate::builtins::rest::rest;
 
@@ -15,6 +17,8 @@ pub mod push;
 pub mod pop;
 pub mod rest;
 pub mod print;
+pub mod json;
+pub mod assert;
 
 pub fn get_builtin(name: &str) -> Option<ObjectType> {
     for (key, value) in BUILTINS.iter() {
@@ -26,7 +30,7 @@ pub fn get_builtin(name: &str) -> Option<ObjectType> {
 }
 
 // map string to function
-static BUILTINS: [(&str, ObjectType); 8] = [
+static BUILTINS: [(&str, ObjectType); 15] = [
     ("len", ObjectType::Builtin(len)),
     ("first", ObjectType::Builtin(first)),
     ("last", ObjectType::Builtin(last)),
@@ -35,4 +39,43 @@ static BUILTINS: [(&str, ObjectType); 8] = [
     ("rest", ObjectType::Builtin(rest)),
     ("print", ObjectType::Builtin(print)),
     ("println", ObjectType::Builtin(println)),
+    ("eprint", ObjectType::Builtin(eprint)),
+    ("eprintln", ObjectType::Builtin(eprintln)),
+    ("json_parse", ObjectType::Builtin(json_parse)),
+    ("json_stringify", ObjectType::Builtin(json_stringify)),
+    ("assert", ObjectType::Builtin(assert)),
+    ("assert_eq", ObjectType::Builtin(assert_eq)),
+    ("assert_error", ObjectType::Builtin(assert_error)),
+];
+
+/// Signature and description of a builtin, for the editor tooling
+pub fn get_builtin_signature(name: &str) -> Option<(&'static str, &'static str)> {
+    SIGNATURES.iter().find(|(key, ..)| key == &name).map(|(_, signature, description, ..)| (*signature, *description))
+}
+
+/// Minimum and maximum number of arguments of a builtin, `None` when it takes any number of arguments
+pub fn get_builtin_arity(name: &str) -> Option<(usize, Option<usize>)> {
+    SIGNATURES.iter().find(|(key, ..)| key == &name).map(|(.., minimum, maximum)| (*minimum, *maximum))
+}
+
+pub fn builtin_names() -> impl Iterator<Item=&'static str> {
+    BUILTINS.iter().map(|(name, _)| *name)
+}
+
+static SIGNATURES: [(&str, &str, &str, usize, Option<usize>); 15] = [
+    ("len", "len(value)", "Returns the length of a string or an array", 1, Some(1)),
+    ("first", "first(array)", "Returns the first element of an array", 1, Some(1)),
+    ("last", "last(array)", "Returns the last element of an array", 1, Some(1)),
+    ("push", "push(array, value)", "Returns a copy of the array with the value added at the end", 2, Some(2)),
+    ("pop", "pop(array)", "Removes the last element of an array and returns it", 1, Some(1)),
+    ("rest", "rest(array)", "Returns all elements of an array except the first", 1, Some(1)),
+    ("print", "print(values...)", "Prints the values to the console", 0, None),
+    ("println", "println(values...)", "Prints each value to the console followed by a newline", 0, None),
+    ("eprint", "eprint(values...)", "Prints the values to the error output", 0, None),
+    ("eprintln", "eprintln(values...)", "Prints each value to the error output followed by a newline", 0, None),
+    ("json_parse", "json_parse(string)", "Parses a JSON string, objects become hashes and numbers become integers or floats", 1, Some(1)),
+    ("json_stringify", "json_stringify(value, pretty)", "Converts a value to a JSON string, `pretty` is optional", 1, Some(2)),
+    ("assert", "assert(condition, message)", "Fails when the condition is false or null, `message` is optional", 1, Some(2)),
+    ("assert_eq", "assert_eq(actual, expected)", "Fails when the values are not equal", 2, Some(2)),
+    ("assert_error", "assert_error(function, kind)", "Calls the function and fails unless it raises an error, of the given `kind` if there is one. Returns the error", 1, Some(2)),
 ];
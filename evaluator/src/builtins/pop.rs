@@ -1,7 +1,7 @@
 use environment::object::ObjectType;
 use error::EvaluatorError;
 
-pub fn pop(args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
+pub fn pop(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
     if args.len() != 1 {
         return Err(EvaluatorError::wrong_number_of_arguments(1, args.len()));
     }
@@ -13,10 +13,6 @@ pub fn pop(args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
 }
 
 fn pop_array(arr: &mut Vec<ObjectType>) -> Result<ObjectType, EvaluatorError> {
-    if arr.len() > 0 {
-        Ok(arr.pop().unwrap())
-    } else {
-        Ok(ObjectType::Null)
-    }
+    Ok(arr.pop().unwrap_or(ObjectType::Null))
 }
 
@@ -1,15 +1,36 @@
 use environment::object::ObjectType;
 use error::EvaluatorError;
 
-pub fn print(args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
-    args.iter().for_each(|arg| print!("{}", arg));
+use crate::output::write;
+
+pub fn print(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    for arg in args {
+        write(&format!("{}", arg), false)?;
+    }
+
+    Ok(ObjectType::Null)
+}
+
+pub fn println(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    for arg in args {
+        write(&format!("{}\n", arg), false)?;
+    }
 
     Ok(ObjectType::Null)
 }
 
-pub fn println(args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
-    args.iter().for_each(|arg| println!("{}", arg));
+pub fn eprint(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    for arg in args {
+        write(&format!("{}", arg), true)?;
+    }
 
     Ok(ObjectType::Null)
 }
 
+pub fn eprintln(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
+    for arg in args {
+        write(&format!("{}\n", arg), true)?;
+    }
+
+    Ok(ObjectType::Null)
+}
@@ -1,10 +1,13 @@
 use environment::object::ObjectType;
 use error::EvaluatorError;
 
-pub fn push(args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
+pub fn push(args: &[&mut ObjectType]) -> Result<ObjectType, EvaluatorError> {
     if args.len() == 1 {
         return Err(EvaluatorError::missing_argument(2));
     }
+    if args.len() != 2 {
+        return Err(EvaluatorError::wrong_number_of_arguments(2, args.len()));
+    }
     let first = &mut args[0].to_owned();
     match first {
         ObjectType::Array(arr) => push_array(arr, args[1].clone()),
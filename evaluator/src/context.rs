@@ -0,0 +1,252 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ast::location::Location;
+use environment::object::ObjectType;
+use error::EvaluatorError;
+
+use ast::statement::Statement;
+use environment::environment::Environment;
+
+use crate::backtrace::{Backtrace, Frame};
+use crate::hook::Hook;
+use crate::module::Modules;
+use crate::output::Output;
+
+/// Default maximum call depth, each call uses about 20KB of stack in debug builds
+/// so evaluations should run on a thread with a stack of at least `STACK_SIZE`, `evaluator::with_evaluation_stack` spawns one
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Stack size needed to reach `DEFAULT_MAX_DEPTH` with some headroom
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Number of steps between two checks of the deadline, reading the clock on every step is too slow
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Limits enforced while evaluating a program, `None` means unlimited
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    /// Maximum number of nested function calls
+    pub max_depth: Option<usize>,
+    /// Maximum number of evaluated expressions
+    pub max_steps: Option<u64>,
+    /// Maximum length of strings, arrays and hashes
+    pub max_collection_size: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        return Self {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_steps: None,
+            max_collection_size: None,
+        };
+    }
+}
+
+/// Shared flag used to stop a running evaluation from another thread or a signal handler
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Asks the evaluation to stop, it returns `EvaluatorError::Interrupted` at its next step
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.interrupted.store(false, Ordering::Relaxed);
+    }
+}
+
+/// State shared by the whole evaluation of a program
+#[derive(Debug, Default)]
+pub struct Context {
+    pub limits: Limits,
+    pub modules: Modules,
+    /// Location of the statement being evaluated
+    pub location: Location,
+    /// Value given to `throw`, kept until it is caught
+    thrown: Option<ObjectType>,
+    call_stack: Vec<Frame>,
+    hook: Option<Box<dyn Hook>>,
+    /// Backtrace of the last error, with the error it belongs to
+    backtrace: Option<(EvaluatorError, Backtrace)>,
+    steps: u64,
+    interrupt: InterruptHandle,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    /// Where the printing builtins write
+    pub output: Output,
+}
+
+impl Context {
+    pub fn new(limits: Limits) -> Self {
+        return Self {
+            limits,
+            ..Self::default()
+        };
+    }
+
+    /// Stops the evaluation when `handle` is interrupted
+    pub fn with_interrupt(mut self, handle: InterruptHandle) -> Self {
+        self.interrupt = handle;
+        self
+    }
+
+    /// Stops the evaluation once `timeout` has elapsed, starting now
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// Writes what the program prints to `output` instead of the standard streams
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Starts the timeout again from now, the time spent loading the prelude does not count
+    pub(crate) fn restart_deadline(&mut self) {
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Calls `hook` before each statement
+    pub fn with_hook(mut self, hook: Box<dyn Hook>) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    /// Records the location of the statement about to be evaluated and gives it to the hook
+    pub fn enter_statement(&mut self, environment: &Environment, statement: &Statement) -> Result<(), EvaluatorError> {
+        if let Some(location) = statement.location() {
+            self.location = location;
+        }
+
+        // The hook is taken out while it runs so it can read the context
+        if let Some(mut hook) = self.hook.take() {
+            let result = hook.before_statement(self, environment, statement);
+            self.hook = Some(hook);
+            return result;
+        }
+        Ok(())
+    }
+
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.call_stack.len()
+    }
+
+    /// Calls being evaluated, the innermost last
+    pub fn call_stack(&self) -> &[Frame] {
+        &self.call_stack
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn step(&mut self) -> Result<(), EvaluatorError> {
+        self.steps += 1;
+        if let Some(limit) = self.limits.max_steps {
+            if self.steps > limit {
+                return Err(EvaluatorError::step_limit_exceeded(limit));
+            }
+        }
+        if self.interrupt.is_interrupted() {
+            return Err(EvaluatorError::interrupted());
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.timeout) {
+            if self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(EvaluatorError::timeout(timeout.as_millis() as u64));
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps `value` for the `catch` that handles the returned error, the error only has its representation
+    pub fn throw(&mut self, value: ObjectType) -> EvaluatorError {
+        let error = EvaluatorError::thrown(value.to_string());
+        self.thrown = Some(value);
+        error
+    }
+
+    pub fn take_thrown(&mut self) -> Option<ObjectType> {
+        self.thrown.take()
+    }
+
+    /// Pushes a call to `function` made by the statement being evaluated
+    pub fn enter_call(&mut self, function: String) -> Result<(), EvaluatorError> {
+        if let Some(limit) = self.limits.max_depth {
+            if self.call_stack.len() >= limit {
+                return Err(EvaluatorError::call_depth_exceeded(limit));
+            }
+        }
+        self.call_stack.push(Frame { function, call_site: self.location });
+        Ok(())
+    }
+
+    /// Replaces the innermost call by a call it made in tail position, the caller stays the same
+    pub fn replace_call(&mut self, function: String) {
+        if let Some(frame) = self.call_stack.last_mut() {
+            frame.function = function;
+        }
+    }
+
+    pub fn exit_call(&mut self) {
+        self.call_stack.pop();
+    }
+
+    /// Records where `error` happened, only the first call for an error is kept as the stack unwinds
+    pub fn capture_backtrace(&mut self, error: &EvaluatorError) {
+        if matches!(&self.backtrace, Some((captured, _)) if captured == error) {
+            return;
+        }
+
+        self.backtrace = Some((error.clone(), Backtrace::new(&self.call_stack, self.location, &self.modules)));
+    }
+
+    /// Backtrace of `error`, if it was raised during this evaluation
+    pub fn backtrace(&self, error: &EvaluatorError) -> Option<&Backtrace> {
+        match &self.backtrace {
+            Some((captured, backtrace)) if captured == error => Some(backtrace),
+            _ => None,
+        }
+    }
+
+    /// Forgets the backtrace of an error that was caught
+    pub fn clear_backtrace(&mut self) {
+        self.backtrace = None;
+    }
+
+    /// Returns the object back if it is not larger than the allowed collection size
+    pub fn check_size(&self, object: ObjectType) -> Result<ObjectType, EvaluatorError> {
+        if let Some(limit) = self.limits.max_collection_size {
+            let size = match &object {
+                ObjectType::String(s) => s.len(),
+                ObjectType::Array(arr) => arr.len(),
+                ObjectType::Hash(hash) => hash.len(),
+                _ => 0,
+            };
+            if size > limit {
+                return Err(EvaluatorError::collection_too_large(size, limit));
+            }
+        }
+        Ok(object)
+    }
+}
@@ -3,15 +3,36 @@ use ast::expression::Expression;
 use ast::program::Program;
 use ast::statement::{BlockStatement, Statement};
 use environment::environment::Environment;
-use environment::object::ObjectType;
+use environment::object::{IntoObject, Object, ObjectType};
 use error::EvaluatorError;
 
+use crate::backtrace::ANONYMOUS_FUNCTION;
 use crate::builtins::get_builtin;
+use crate::context::{Context, STACK_SIZE};
+use crate::module::import;
 use crate::converter::convert_object_to_expression;
 use crate::modify::modify;
 
 pub fn eval(program: &Program, environment: &mut Environment) -> Result<ObjectType, EvaluatorError> {
-    let result = eval_block_statement(environment, &program.statements)?;
+    with_evaluation_stack(|| eval_with_context(program, environment, &mut Context::default()))
+}
+
+/// Runs `run` on a thread with a stack of `STACK_SIZE` and waits for it, a panic is raised again on the caller.
+/// The default call depth needs more stack than the threads of the caller may have.
+pub fn with_evaluation_stack<T: Send>(run: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|scope| {
+        let evaluation = std::thread::Builder::new().stack_size(STACK_SIZE)
+            .spawn_scoped(scope, run)
+            .expect("the evaluation thread starts");
+        evaluation.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+/// Evaluates `program`, the backtrace of a returned error is available with `Context::backtrace`.
+/// Runs on the calling thread, which needs a stack of `STACK_SIZE` for the default call depth,
+/// see `with_evaluation_stack`.
+pub fn eval_with_context(program: &Program, environment: &mut Environment, context: &mut Context) -> Result<ObjectType, EvaluatorError> {
+    let result = eval_block_statement(context, environment, &program.statements).inspect_err(|error| context.capture_backtrace(error))?;
 
     if let ObjectType::Return(obj) = result {
         return Ok(*obj);
@@ -21,28 +42,40 @@ pub fn eval(program: &Program, environment: &mut Environment) -> Result<ObjectTy
 }
 
 
-pub fn eval_node(environment: &mut Environment, node: &Statement) -> Result<ObjectType, EvaluatorError> {
+pub fn eval_node(context: &mut Context, environment: &mut Environment, node: &Statement) -> Result<ObjectType, EvaluatorError> {
     return match node {
-        Statement::ExpressionStatement(expr) => eval_expression(environment, expr),
-        Statement::ReturnStatement { value } => {
-            let evaluated = eval_expression(environment, value)?;
+        Statement::ExpressionStatement(expr, _) => eval_expression(context, environment, expr),
+        Statement::ReturnStatement { value, .. } => {
+            let evaluated = eval_expression(context, environment, value)?;
             return Ok(ObjectType::Return(Box::new(evaluated)));
         }
-        Statement::LetStatement { value, identifier } => {
+        Statement::ThrowStatement { value, .. } => {
+            let evaluated = eval_expression(context, environment, value)?;
+            return Err(context.throw(evaluated));
+        }
+        Statement::LetStatement { value, identifier, .. } => {
             // Check builtins
             if get_builtin(identifier).is_some() {
                 return Err(EvaluatorError::built_in_function(identifier.as_str()));
             }
 
-            let evaluated = eval_expression(environment, value)?;
+            let mut evaluated = eval_expression(context, environment, value)?;
+            if let (Expression::FunctionLiteral { .. }, ObjectType::Function { name, .. }) = (value, &mut evaluated) {
+                *name = Some(identifier.clone());
+            }
+
             environment.set(identifier, evaluated);
             return Ok(ObjectType::Null);
         }
+        Statement::ExportStatement { statement } => eval_node(context, environment, statement),
+        Statement::ImportStatement { path, names, .. } => import(context, environment, path, names),
         _ => Err(EvaluatorError::operator_not_supported(node.to_string())),
     };
 }
 
-fn eval_expression(environment: &mut Environment, expr: &Expression) -> Result<ObjectType, EvaluatorError> {
+fn eval_expression(context: &mut Context, environment: &mut Environment, expr: &Expression) -> Result<ObjectType, EvaluatorError> {
+    context.step()?;
+
     return match expr {
         Expression::IntegerLiteral(value) => Ok(ObjectType::Integer(*value)),
         Expression::BooleanLiteral(value) => {
@@ -53,9 +86,13 @@ fn eval_expression(environment: &mut Environment, expr: &Expression) -> Result<O
             }
         }
         Expression::StringLiteral(value) => Ok(ObjectType::String(value.clone())), // TODO: remove clone
-        Expression::PrefixExpression { operator, right } => eval_prefix_expression(operator, &eval_expression(environment, right)?),
-        Expression::InfixExpression { left, operator, right } => eval_infix_expression(operator, &eval_expression(environment, left)?, &eval_expression(environment, right)?),
-        Expression::IfExpression { condition, consequence, alternative } => eval_if_expression(environment, condition, consequence, alternative),
+        Expression::PrefixExpression { operator, right } => eval_prefix_expression(operator, &eval_expression(context, environment, right)?),
+        Expression::InfixExpression { left, operator, right } => {
+            let evaluated = eval_infix_expression(operator, &eval_expression(context, environment, left)?, &eval_expression(context, environment, right)?)?;
+            context.check_size(evaluated)
+        }
+        Expression::IfExpression { condition, consequence, alternative } => eval_if_expression(context, environment, condition, consequence, alternative),
+        Expression::TryExpression { body, catch, finally } => eval_try_expression(context, environment, body, catch, finally),
         Expression::Identifier(identifier) => {
             // Check builtin functions
             let builtin = get_builtin(identifier);
@@ -70,6 +107,7 @@ fn eval_expression(environment: &mut Environment, expr: &Expression) -> Result<O
             Err(EvaluatorError::unknown_identifier(identifier))
         }
         Expression::FunctionLiteral { parameters, body } => Ok(ObjectType::Function {
+            name: None,
             parameters: parameters.clone(), // TODO: remove clone
             body: body.clone(), // TODO: remove clone
             environment: environment.clone(), // TODO: remove clone
@@ -81,25 +119,18 @@ fn eval_expression(environment: &mut Environment, expr: &Expression) -> Result<O
                     if arguments.len() != 1 {
                         return Err(EvaluatorError::wrong_number_of_arguments2("quote", 1, arguments.len()));
                     }
-                    return eval_quote_expression(environment,&arguments[0]);
+                    return eval_quote_expression(context, environment, &arguments[0]);
+                }
+                if identifier == "assert_error" {
+                    return eval_assert_error(context, environment, arguments);
                 }
             }
 
 
-            let evaluated = eval_expression(environment, function)?;
-
-            let mut evaluated_arguments = vec![];
-
-            let arguments = arguments.iter();
-
-            for argument in arguments {
-                let evaluated = eval_expression(environment, argument)?;
-                evaluated_arguments.push(evaluated);
-            }
-
+            let (evaluated, mut evaluated_arguments) = eval_call_expression(context, environment, function, arguments)?;
             let evaluated_arguments = evaluated_arguments.iter_mut().collect();
 
-            return apply_function(environment, &evaluated, &evaluated_arguments);
+            return apply_function(context, environment, &evaluated, &evaluated_arguments);
         }
         Expression::ArrayLiteral(elements) => {
             let mut result = vec![];
@@ -107,42 +138,44 @@ fn eval_expression(environment: &mut Environment, expr: &Expression) -> Result<O
             let elements = elements.iter();
 
             for element in elements {
-                result.push(eval_expression(environment, element)?);
+                result.push(eval_expression(context, environment, element)?);
             }
 
-            Ok(ObjectType::Array(result))
+            context.check_size(ObjectType::Array(result))
         }
         Expression::IndexExpression { left, index } => {
-            let left = eval_expression(environment, left)?;
-            let index = eval_expression(environment, index)?;
+            let left = eval_expression(context, environment, left)?;
+            let index = eval_expression(context, environment, index)?;
 
             return eval_index_expression(&left, &index);
         }
         Expression::HashLiteral(pairs) => {
             let mut result = vec![];
             for (key, value) in pairs {
-                let key = eval_expression(environment, key)?;
+                let key = eval_expression(context, environment, key)?;
 
                 // Not all keys are allowed
                 if matches!(key, ObjectType::Array(_) | ObjectType::Hash(_) | ObjectType::Function { .. }) {
                     return Err(EvaluatorError::key_not_supported(key.to_string()));
                 }
 
-                let value = eval_expression(environment, value)?;
+                let value = eval_expression(context, environment, value)?;
                 result.push((key, value));
             }
-            return Ok(ObjectType::Hash(result));
+            return context.check_size(ObjectType::Hash(result));
         }
         _ => Err(EvaluatorError::operator_not_supported(expr.to_string())),
     };
 }
 
-fn eval_block_statement(environment: &mut Environment, statements: &BlockStatement) -> Result<ObjectType, EvaluatorError> {
+fn eval_block_statement(context: &mut Context, environment: &mut Environment, statements: &BlockStatement) -> Result<ObjectType, EvaluatorError> {
     let iter = statements.iter();
     let mut result = ObjectType::Null;
 
     for statement in iter {
-        let evaluated = eval_node(environment, statement);
+        context.enter_statement(environment, statement)?;
+
+        let evaluated = eval_node(context, environment, statement);
         if let Err(error) = evaluated {
             return Err(error);
         }
@@ -170,6 +203,15 @@ fn eval_infix_expression(operator: &str, left: &ObjectType, right: &ObjectType)
         (ObjectType::Integer(left_value), ObjectType::Integer(right_value)) => {
             eval_integer_infix_expression(operator, left_value, right_value)
         }
+        (ObjectType::Float(left_value), ObjectType::Float(right_value)) => {
+            eval_float_infix_expression(operator, left_value, right_value)
+        }
+        (ObjectType::Integer(left_value), ObjectType::Float(right_value)) => {
+            eval_float_infix_expression(operator, &(*left_value as f64), right_value)
+        }
+        (ObjectType::Float(left_value), ObjectType::Integer(right_value)) => {
+            eval_float_infix_expression(operator, left_value, &(*right_value as f64))
+        }
         (ObjectType::Boolean(left_value), ObjectType::Boolean(right_value)) => {
             eval_boolean_infix_expression(operator, left_value, right_value)
         }
@@ -181,11 +223,29 @@ fn eval_infix_expression(operator: &str, left: &ObjectType, right: &ObjectType)
 }
 
 fn eval_integer_infix_expression(operator: &str, left: &i64, right: &i64) -> Result<ObjectType, EvaluatorError> {
+    let overflow = || EvaluatorError::integer_overflow(format!("{} {} {}", left, operator, right));
+    match operator {
+        "+" => left.checked_add(*right).map(ObjectType::Integer).ok_or_else(overflow),
+        "-" => left.checked_sub(*right).map(ObjectType::Integer).ok_or_else(overflow),
+        "*" => left.checked_mul(*right).map(ObjectType::Integer).ok_or_else(overflow),
+        "/" if *right == 0 => Err(EvaluatorError::division_by_zero()),
+        "/" => left.checked_div(*right).map(ObjectType::Integer).ok_or_else(overflow),
+        "<" => Ok(ObjectType::Boolean(left < right)),
+        "<=" => Ok(ObjectType::Boolean(left <= right)),
+        ">" => Ok(ObjectType::Boolean(left > right)),
+        ">=" => Ok(ObjectType::Boolean(left >= right)),
+        "==" => Ok(ObjectType::Boolean(left == right)),
+        "!=" => Ok(ObjectType::Boolean(left != right)),
+        _ => Err(EvaluatorError::operator_not_supported(operator.to_string())),
+    }
+}
+
+fn eval_float_infix_expression(operator: &str, left: &f64, right: &f64) -> Result<ObjectType, EvaluatorError> {
     match operator {
-        "+" => Ok(ObjectType::Integer(left + right)),
-        "-" => Ok(ObjectType::Integer(left - right)),
-        "*" => Ok(ObjectType::Integer(left * right)),
-        "/" => Ok(ObjectType::Integer(left / right)),
+        "+" => Ok(ObjectType::Float(left + right)),
+        "-" => Ok(ObjectType::Float(left - right)),
+        "*" => Ok(ObjectType::Float(left * right)),
+        "/" => Ok(ObjectType::Float(left / right)),
         "<" => Ok(ObjectType::Boolean(left < right)),
         "<=" => Ok(ObjectType::Boolean(left <= right)),
         ">" => Ok(ObjectType::Boolean(left > right)),
@@ -207,6 +267,8 @@ fn eval_boolean_infix_expression(operator: &str, left: &bool, right: &bool) -> R
 fn eval_string_infix_expression(operator: &str, left: &String, right: &String) -> Result<ObjectType, EvaluatorError> {
     match operator {
         "+" => Ok(ObjectType::String(format!("{}{}", left, right))),
+        "==" => Ok(ObjectType::Boolean(left == right)),
+        "!=" => Ok(ObjectType::Boolean(left != right)),
         _ => Err(EvaluatorError::type_missmatch(left.to_string().as_str(), operator, right.to_string().as_str())),
     }
 }
@@ -227,6 +289,7 @@ fn eval_bang_operator_expression(right: &ObjectType) -> Result<ObjectType, Evalu
                 Ok(ObjectType::Boolean(false))
             }
         }
+        ObjectType::Float(value) => Ok(ObjectType::Boolean(*value == 0.0)),
         ObjectType::Null => Ok(ObjectType::Boolean(true)),
         _ => Err(EvaluatorError::operator_not_supported(right.to_string())),
     }
@@ -234,46 +297,267 @@ fn eval_bang_operator_expression(right: &ObjectType) -> Result<ObjectType, Evalu
 
 fn eval_minus_prefix_operator_expression(right: &ObjectType) -> Result<ObjectType, EvaluatorError> {
     match right {
-        ObjectType::Integer(value) => Ok(ObjectType::Integer(-*value)),
+        ObjectType::Integer(value) => value.checked_neg().map(ObjectType::Integer).ok_or_else(|| EvaluatorError::integer_overflow(format!("-{}", value))),
+        ObjectType::Float(value) => Ok(ObjectType::Float(-*value)),
         _ => Err(EvaluatorError::operator_not_supported(right.to_string())),
     }
 }
 
-fn eval_if_expression(environment: &mut Environment, condition: &Expression, consequence: &BlockStatement, alternative: &Option<BlockStatement>) -> Result<ObjectType, EvaluatorError> {
-    if is_truthy(&eval_expression(environment, condition)?) {
-        return eval_block_statement(environment, consequence);
+fn eval_if_expression(context: &mut Context, environment: &mut Environment, condition: &Expression, consequence: &BlockStatement, alternative: &Option<BlockStatement>) -> Result<ObjectType, EvaluatorError> {
+    if is_truthy(&eval_expression(context, environment, condition)?) {
+        return eval_block_statement(context, environment, consequence);
     }
 
     if alternative.is_some() {
-        return eval_block_statement(environment, alternative.as_ref().unwrap());
+        return eval_block_statement(context, environment, alternative.as_ref().unwrap());
     }
 
     Ok(ObjectType::Null)
 }
 
-fn apply_function(outer_environment: &Environment, function: &ObjectType, args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
-    if let ObjectType::Function { parameters, body, environment } = function {
-        let mut enclosing_environment = Environment::new_enclosed(outer_environment);
-        enclosing_environment.merge(environment); // TODO: this is a hack, we should not clone but use references !!!
+/// Value bound by `catch` for the errors raised by the interpreter
+#[derive(IntoObject)]
+struct ErrorObject {
+    kind: String,
+    message: String,
+    location: LocationObject,
+}
 
-        if parameters.len() != args.len() {
-            return Err(EvaluatorError::wrong_number_of_arguments(parameters.len(), args.len()));
+#[derive(IntoObject)]
+struct LocationObject {
+    line: u32,
+    column: u32,
+}
+
+/// Value of a caught error, the thrown value or an error object for the errors raised by the interpreter
+fn caught_value(context: &mut Context, error: &EvaluatorError) -> ObjectType {
+    let value = match (error, context.take_thrown()) {
+        (EvaluatorError::Thrown { .. }, Some(thrown)) => thrown,
+        _ => ErrorObject {
+            kind: error.kind().to_string(),
+            message: error.to_string(),
+            location: LocationObject { line: context.location.line, column: context.location.column },
+        }.into_object().expect("strings and u32 always convert"),
+    };
+
+    context.clear_backtrace();
+    value
+}
+
+/// `assert_error(function, kind)` calls `function` and returns the error it raises, like `catch` would bind it
+fn eval_assert_error(context: &mut Context, environment: &mut Environment, arguments: &[Expression]) -> Result<ObjectType, EvaluatorError> {
+    if arguments.is_empty() || arguments.len() > 2 {
+        return Err(EvaluatorError::wrong_number_of_arguments2("assert_error", 1, arguments.len()));
+    }
+    let function = eval_expression(context, environment, &arguments[0])?;
+    let kind = match arguments.get(1).map(|kind| eval_expression(context, environment, kind)).transpose()? {
+        None => None,
+        Some(ObjectType::String(kind)) => Some(kind),
+        Some(kind) => return Err(EvaluatorError::argument_type_not_supported("assert_error", &kind.to_string())),
+    };
+
+    let location = context.location;
+    let error = match apply_function(context, environment, &function, &vec![]) {
+        Ok(value) => return Err(EvaluatorError::assertion_failed(format!("expected an error, got {}", value.inspect()))),
+        Err(error) if !error.is_catchable() => return Err(error),
+        Err(error) => error,
+    };
+    let value = caught_value(context, &error);
+    context.location = location;
+
+    match kind {
+        Some(kind) if kind != error.kind() => Err(EvaluatorError::assertion_failed(format!("expected an error of kind {}, got {}", kind, error))),
+        _ => Ok(value),
+    }
+}
+
+fn eval_try_expression(context: &mut Context, environment: &mut Environment, body: &BlockStatement, catch: &Option<(String, BlockStatement)>, finally: &Option<BlockStatement>) -> Result<ObjectType, EvaluatorError> {
+    let result = match (eval_block_statement(context, environment, body), catch) {
+        (Err(error), Some((parameter, handler))) if error.is_catchable() => {
+            // The parameter only exists in the handler, the other bindings of the handler are kept like those of the body
+            let value = caught_value(context, &error);
+            let mut handler_environment = Environment::new_enclosed(environment);
+            handler_environment.set(parameter, value);
+            let result = eval_block_statement(context, &mut handler_environment, handler);
+            for (name, value) in handler_environment.store() {
+                if name != parameter {
+                    environment.set(name, value.clone());
+                }
+            }
+            result
         }
+        (result, _) => result,
+    };
 
-        for (name, value) in parameters.iter().zip(args.iter()) {
-            enclosing_environment.set(name.to_string().as_str(), (*value).clone()); // TODO: remove clone
+    if let Some(finally) = finally {
+        // An error or a `return` in `finally` replaces the result of the body
+        let cleanup = eval_block_statement(context, environment, finally)?;
+        if let ObjectType::Return(_) = cleanup {
+            return Ok(cleanup);
         }
+    }
 
-        return eval_block_statement(&mut enclosing_environment, body);
+    result
+}
+
+/// Result of evaluating code in tail position of a function body
+enum Tail {
+    Value(ObjectType),
+    /// The function returns the result of this call, evaluated by the caller without growing the stack
+    Call(ObjectType, Vec<ObjectType>),
+}
+
+fn eval_call_expression(context: &mut Context, environment: &mut Environment, function: &Expression, arguments: &[Expression]) -> Result<(ObjectType, Vec<ObjectType>), EvaluatorError> {
+    let evaluated = eval_expression(context, environment, function)?;
+
+    let mut evaluated_arguments = vec![];
+
+    let arguments = arguments.iter();
+
+    for argument in arguments {
+        let evaluated = eval_expression(context, environment, argument)?;
+        evaluated_arguments.push(evaluated);
+    }
+
+    Ok((evaluated, evaluated_arguments))
+}
+
+/// Evaluates a function body, calls in tail position (last statement, `return`, through `if`/`else`) are not evaluated
+/// but returned as `Tail::Call`. When `tail` is false only `return` statements are in tail position.
+fn eval_tail_block_statement(context: &mut Context, environment: &mut Environment, statements: &BlockStatement, tail: bool) -> Result<Tail, EvaluatorError> {
+    let mut result = ObjectType::Null;
+
+    for (index, statement) in statements.iter().enumerate() {
+        let is_tail = tail && index == statements.len() - 1;
+        context.enter_statement(environment, statement)?;
+
+        let evaluated = match statement {
+            Statement::ReturnStatement { value, .. } => {
+                return match eval_tail_expression(context, environment, value, true)? {
+                    Tail::Value(ObjectType::Return(obj)) => Ok(Tail::Value(ObjectType::Return(obj))),
+                    Tail::Value(obj) => Ok(Tail::Value(ObjectType::Return(Box::new(obj)))),
+                    call => Ok(call),
+                };
+            }
+            Statement::ExpressionStatement(expr, _) => eval_tail_expression(context, environment, expr, is_tail)?,
+            _ => Tail::Value(eval_node(context, environment, statement)?),
+        };
+
+        match evaluated {
+            Tail::Value(ObjectType::Return(obj)) => return Ok(Tail::Value(ObjectType::Return(obj))),
+            Tail::Value(obj) => result = obj,
+            call => return Ok(call),
+        }
+    }
+
+    Ok(Tail::Value(result))
+}
+
+fn eval_tail_expression(context: &mut Context, environment: &mut Environment, expr: &Expression, tail: bool) -> Result<Tail, EvaluatorError> {
+    match expr {
+        Expression::CallExpression { function, arguments } if tail && !is_special_form(function) => {
+            context.step()?;
+            let (function, arguments) = eval_call_expression(context, environment, function, arguments)?;
+            Ok(Tail::Call(function, arguments))
+        }
+        Expression::IfExpression { condition, consequence, alternative } => {
+            context.step()?;
+            if is_truthy(&eval_expression(context, environment, condition)?) {
+                return eval_tail_block_statement(context, environment, consequence, tail);
+            }
+
+            if let Some(alternative) = alternative {
+                return eval_tail_block_statement(context, environment, alternative, tail);
+            }
+
+            Ok(Tail::Value(ObjectType::Null))
+        }
+        _ => Ok(Tail::Value(eval_expression(context, environment, expr)?)),
+    }
+}
+
+/// Calls the evaluator makes itself, they are never replaced by a tail call
+fn is_special_form(function: &Expression) -> bool {
+    matches!(function, Expression::Identifier(identifier) if identifier == "quote" || identifier == "assert_error")
+}
+
+fn apply_function(context: &mut Context, outer_environment: &Environment, function: &ObjectType, args: &Vec<&mut ObjectType>) -> Result<ObjectType, EvaluatorError> {
+    if let ObjectType::Function { .. } = function {
+        context.enter_call(function_name(function))?;
+        let location = context.location;
+        let result = apply_tail_calls(context, outer_environment, function.clone(), args.iter().map(|arg| (*arg).clone()).collect());
+        if let Err(error) = &result {
+            context.capture_backtrace(error);
+        }
+        context.exit_call();
+
+        // Errors keep the location inside the function, the caller carries on from the calling statement
+        if result.is_ok() {
+            context.location = location;
+        }
+
+        return result;
     }
 
     if let ObjectType::Builtin(function) = function {
-        return function(args); // TODO: remove clone
+        // Builtins do not see the context, the printing ones write to its output
+        let result = crate::output::install(&context.output, || function(args))?;
+        return context.check_size(result);
     }
 
     Err(EvaluatorError::operator_not_supported(function.to_string()))
 }
 
+fn function_name(function: &ObjectType) -> String {
+    match function {
+        ObjectType::Function { name: Some(name), .. } => name.clone(),
+        _ => ANONYMOUS_FUNCTION.to_string(),
+    }
+}
+
+/// Calls `function` then every call it makes in tail position in a loop, all of them counting as a single call.
+/// The calls share one frame, the bindings of a replaced call stay visible to the next one as if it was still on the stack.
+fn apply_tail_calls(context: &mut Context, outer_environment: &Environment, mut function: ObjectType, mut args: Vec<ObjectType>) -> Result<ObjectType, EvaluatorError> {
+    let mut frame = Environment::new_enclosed(outer_environment);
+    // Function environment last copied into the frame, calling the same function again only rebinds the parameters
+    let mut copied: Option<Environment> = None;
+
+    loop {
+        let (parameters, body, environment) = match &function {
+            ObjectType::Function { parameters, body, environment, .. } => (parameters, body, environment),
+            _ => {
+                let mut args = args;
+                return apply_function(context, &frame, &function, &args.iter_mut().collect());
+            }
+        };
+
+        if !copied.as_ref().is_some_and(|copied| copied.shares_store(environment)) {
+            frame.merge(environment); // TODO: this is a hack, we should not clone but use references !!!
+            copied = Some(environment.clone());
+        }
+
+        if parameters.len() != args.len() {
+            return Err(EvaluatorError::wrong_number_of_arguments(parameters.len(), args.len()));
+        }
+
+        for (name, value) in parameters.iter().zip(args) {
+            frame.set(name.to_string().as_str(), value);
+        }
+
+        match eval_tail_block_statement(context, &mut frame, body, true)? {
+            Tail::Value(ObjectType::Return(obj)) => return Ok(*obj),
+            Tail::Value(obj) => return Ok(obj),
+            Tail::Call(next_function, next_args) => {
+                if let ObjectType::Function { .. } = next_function {
+                    context.replace_call(function_name(&next_function));
+                }
+                function = next_function;
+                args = next_args;
+            }
+        }
+    }
+}
+
 fn eval_index_expression(left: &ObjectType, index: &ObjectType) -> Result<ObjectType, EvaluatorError> {
     match (left, index) {
         (ObjectType::Array(elements), ObjectType::Integer(index)) => {
@@ -288,15 +572,12 @@ fn eval_index_expression(left: &ObjectType, index: &ObjectType) -> Result<Object
             Ok(elements[*index as usize].clone()) // TODO: remove clone
         }
         (ObjectType::String(value), ObjectType::Integer(index)) => {
-            let max_index = value.len() as i64;
-            if *index < 0 {
-                return Err(EvaluatorError::index_out_of_bounds(*index, max_index as usize));
+            // Strings are indexed by character, not by byte
+            let character = usize::try_from(*index).ok().and_then(|index| value.chars().nth(index));
+            match character {
+                Some(character) => Ok(ObjectType::String(character.to_string())),
+                None => Err(EvaluatorError::index_out_of_bounds(*index, value.chars().count())),
             }
-            if *index >= max_index {
-                return Err(EvaluatorError::index_out_of_bounds(*index, max_index as usize));
-            }
-
-            Ok(ObjectType::String(value.chars().nth(*index as usize).unwrap().to_string()))
         }
         (ObjectType::Hash(pairs), index) => {
             for (key, value) in pairs {
@@ -311,12 +592,13 @@ fn eval_index_expression(left: &ObjectType, index: &ObjectType) -> Result<Object
     }
 }
 
-fn eval_quote_expression(environment: &mut Environment, quote: &Expression) -> Result<ObjectType, EvaluatorError> {
-    let unquoted_quote = eval_unquote_calls(environment, quote.clone())?;
+fn eval_quote_expression(context: &mut Context, environment: &mut Environment, quote: &Expression) -> Result<ObjectType, EvaluatorError> {
+    let unquoted_quote = eval_unquote_calls(context, environment, quote.clone())?;
     return Ok(ObjectType::Quote(Box::new(unquoted_quote)));
 }
 
-fn eval_unquote_calls(environment: &mut Environment, mut quote: Expression) -> Result<Expression, EvaluatorError> {
+fn eval_unquote_calls(context: &mut Context, environment: &mut Environment, mut quote: Expression) -> Result<Expression, EvaluatorError> {
+    let limits = context.limits.clone();
     modify(Some(&mut quote), |expression| {
         if let Expression::CallExpression { function, arguments } = expression {
             if let Expression::Identifier(name) = function.as_ref() {
@@ -326,14 +608,14 @@ fn eval_unquote_calls(environment: &mut Environment, mut quote: Expression) -> R
                     }
 
                     // TODO: use environment
-                    let object = eval_expression(&mut Environment::new(), &arguments[0])?;
+                    let object = eval_expression(&mut Context::new(limits.clone()), &mut Environment::new(), &arguments[0])?;
                     *expression = convert_object_to_expression(object)?;
                 }
             }
         }
 
         Ok(())
-    }).unwrap();
+    })?;
 
     Ok(quote)
 }
@@ -354,6 +636,10 @@ mod tests {
     use lexer::lexer::Lexer;
     use parser::parser::Parser;
 
+    use std::time::Duration;
+
+    use crate::context::{InterruptHandle, Limits};
+
     use super::*;
 
     fn test_eval(input: String) -> Result<ObjectType, EvaluatorError> {
@@ -372,6 +658,14 @@ mod tests {
         eval(&program, &mut env)
     }
 
+    fn test_eval_with_limits(input: String, limits: Limits) -> Result<ObjectType, EvaluatorError> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer)?;
+        let program = parser.parse_program().unwrap();
+
+        eval_with_context(&program, &mut Environment::new(), &mut Context::new(limits))
+    }
+
     fn run_test_suite(tests: &Vec<(&str, Result<ObjectType, EvaluatorError>)>) {
         tests.iter().for_each(|(input, result)| {
             let evaluated = test_eval(input.to_string());
@@ -468,6 +762,20 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_string_comparison() {
+        let tests = vec![
+            (r#""a" == "a""#, true),
+            (r#""a" == "b""#, false),
+            (r#""a" != "b""#, true),
+        ];
+
+        tests.iter().for_each(|(input, result)| {
+            let evaluated = test_eval(input.to_string()).unwrap();
+            assert_eq!(evaluated, ObjectType::Boolean(*result));
+        })
+    }
+
     #[test]
     fn test_bang_operator() {
         std::env::set_var("RUST_LOG", "trace");
@@ -528,6 +836,8 @@ mod tests {
             ("let a = 5 * 5; a;", Ok(ObjectType::Integer(25))),
             ("let a = 5; let b = a; b;", Ok(ObjectType::Integer(5))),
             ("let a = 5; let b = a; let c = a + b + 5; c;", Ok(ObjectType::Integer(15))),
+            // `from` is only a keyword in imports
+            ("let from = 1; from", Ok(ObjectType::Integer(1))),
         ];
 
         run_test_suite(&tests);
@@ -634,6 +944,24 @@ mod tests {
 
     }
 
+    /// Inputs minimised from crashes found by the fuzz targets
+    #[test]
+    fn test_fuzz_regressions() {
+        let tests = vec![
+            ("1 / 0", Err(EvaluatorError::division_by_zero())),
+            ("9223372036854775807 + 1", Err(EvaluatorError::integer_overflow("9223372036854775807 + 1".to_string()))),
+            ("(-9223372036854775807 - 1) / -1", Err(EvaluatorError::integer_overflow("-9223372036854775808 / -1".to_string()))),
+            ("-(-9223372036854775807 - 1)", Err(EvaluatorError::integer_overflow("--9223372036854775808".to_string()))),
+            ("push()", Err(EvaluatorError::wrong_number_of_arguments(2, 0))),
+            (r#""é"[1]"#, Err(EvaluatorError::index_out_of_bounds(1, 1))),
+            (r#""aé"[1]"#, Ok(ObjectType::String("é".to_string()))),
+            (r#"len("é")"#, Ok(ObjectType::Integer(1))),
+            ("quote(unquote(foo))", Err(EvaluatorError::unknown_identifier("foo"))),
+        ];
+
+        run_test_suite(&tests);
+    }
+
     #[test]
     fn test_array() {
         std::env::set_var("RUST_LOG", "trace");
@@ -673,6 +1001,219 @@ mod tests {
         run_test_suite(&tests);
     }
 
+    #[test]
+    fn test_float() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let tests = vec![
+            (r#"json_parse("1.5") * 2"#, Ok(ObjectType::Float(3.0))),
+            (r#"json_parse("1.5") + 1"#, Ok(ObjectType::Float(2.5))),
+            (r#"-json_parse("1.5")"#, Ok(ObjectType::Float(-1.5))),
+            (r#"json_parse("1.5") < 2"#, Ok(ObjectType::Boolean(true))),
+        ];
+
+        run_test_suite(&tests);
+    }
+
+    #[test]
+    fn test_json() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let tests = vec![
+            (r#"json_parse("null")"#, Ok(ObjectType::Null)),
+            (r#"json_parse("[1, true, \"a\"]")"#, Ok(ObjectType::Array(vec![ObjectType::Integer(1), ObjectType::Boolean(true), ObjectType::String("a".to_string())]))),
+            (r#"json_parse("{\"a\": {\"b\": 2}}")["a"]["b"]"#, Ok(ObjectType::Integer(2))),
+            (r#"json_parse(1)"#, Err(EvaluatorError::argument_type_not_supported("json_parse", "1"))),
+            (r#"json_parse("[1,")"#, Err(EvaluatorError::json_error("EOF while parsing a value at line 1 column 3".to_string()))),
+            (r#"json_stringify({"a": [1, 2], "b": first([])})"#, Ok(ObjectType::String(r#"{"a":[1,2],"b":null}"#.to_string()))),
+            (r#"json_stringify([1], true)"#, Ok(ObjectType::String("[\n  1\n]".to_string()))),
+            (r#"json_stringify({1: "one"})"#, Ok(ObjectType::String(r#"{"1":"one"}"#.to_string()))),
+            (r#"json_stringify(fn(x) { x })"#, Err(EvaluatorError::json_error("`fn(x) {\nx;\n\t}` cannot be serialized".to_string()))),
+            (r#"json_stringify([len])"#, Err(EvaluatorError::json_error("`builtin function` cannot be serialized".to_string()))),
+            (r#"json_stringify(1, true, 3)"#, Err(EvaluatorError::wrong_number_of_arguments_between("json_stringify", 1, 2, 3))),
+        ];
+
+        run_test_suite(&tests);
+    }
+
+    #[test]
+    fn test_limits() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let recursion = "let f = fn(x) { 1 + f(x) }; f(1)";
+
+        let depth = Limits { max_depth: Some(50), ..Limits::default() };
+        assert_eq!(test_eval_with_limits(recursion.to_string(), depth.clone()), Err(EvaluatorError::call_depth_exceeded(50)));
+        assert_eq!(test_eval_with_limits("let f = fn(x) { if (x > 0) { f(x - 1) } else { x } }; f(49)".to_string(), depth), Ok(ObjectType::Integer(0)));
+
+        let steps = Limits { max_steps: Some(10), ..Limits::default() };
+        assert_eq!(test_eval_with_limits(recursion.to_string(), steps.clone()), Err(EvaluatorError::step_limit_exceeded(10)));
+        assert_eq!(test_eval_with_limits("1 + 2".to_string(), steps), Ok(ObjectType::Integer(3)));
+
+        let size = Limits { max_collection_size: Some(3), ..Limits::default() };
+        assert_eq!(test_eval_with_limits("[1, 2, 3]".to_string(), size.clone()), Ok(ObjectType::Array(vec![ObjectType::Integer(1), ObjectType::Integer(2), ObjectType::Integer(3)])));
+        assert_eq!(test_eval_with_limits("[1, 2, 3, 4]".to_string(), size.clone()), Err(EvaluatorError::collection_too_large(4, 3)));
+        assert_eq!(test_eval_with_limits("push([1, 2, 3], 4)".to_string(), size.clone()), Err(EvaluatorError::collection_too_large(4, 3)));
+        assert_eq!(test_eval_with_limits(r#""ab" + "cd""#.to_string(), size.clone()), Err(EvaluatorError::collection_too_large(4, 3)));
+        assert_eq!(test_eval_with_limits(r#"{1: 1, 2: 2, 3: 3, 4: 4}"#.to_string(), size), Err(EvaluatorError::collection_too_large(4, 3)));
+    }
+
+    #[test]
+    fn test_default_limits_do_not_overflow() {
+        // `eval` gives itself the stack it needs, the caller has the 2MB of a spawned thread
+        let handle = std::thread::spawn(|| {
+            let deep = test_eval("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(990)".to_string());
+            let unbounded = test_eval("let f = fn(x) { 1 + f(x) }; f(1)".to_string());
+            (deep, unbounded)
+        });
+
+        let (deep, unbounded) = handle.join().unwrap();
+        assert_eq!(deep, Ok(ObjectType::Integer(990)));
+        assert_eq!(unbounded, Err(EvaluatorError::call_depth_exceeded(1000)));
+    }
+
+    #[test]
+    fn test_interrupt_and_timeout() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let input = "let f = fn(x) { if (x > 0) { f(x - 1) } else { 0 } }; f(30); f(30); f(30); f(30); f(30); f(30); f(30);";
+        let program = Parser::new(Lexer::new(input.to_string())).unwrap().parse_program().unwrap();
+
+        let handle = InterruptHandle::new();
+        handle.interrupt();
+        let mut context = Context::default().with_interrupt(handle.clone());
+        assert_eq!(eval_with_context(&program, &mut Environment::new(), &mut context), Err(EvaluatorError::interrupted()));
+
+        handle.reset();
+        let mut context = Context::default().with_interrupt(handle);
+        assert_eq!(eval_with_context(&program, &mut Environment::new(), &mut context), Ok(ObjectType::Integer(0)));
+
+        let mut context = Context::default().with_timeout(Duration::ZERO);
+        assert_eq!(eval_with_context(&program, &mut Environment::new(), &mut context), Err(EvaluatorError::timeout(0)));
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let depth = Limits { max_depth: Some(10), ..Limits::default() };
+        let tests = vec![
+            // last statement, through if/else
+            ("let sum = fn(n, acc) { if (n == 0) { acc } else { sum(n - 1, acc + n) } }; sum(10000, 0)", Ok(ObjectType::Integer(50005000))),
+            // return statements
+            ("let sum = fn(n, acc) { if (n == 0) { return acc; } return sum(n - 1, acc + n); }; sum(10000, 0)", Ok(ObjectType::Integer(50005000))),
+            // mutual recursion
+            ("let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } }; let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } }; even(10001)", Ok(ObjectType::Boolean(false))),
+            // functions defined in the caller are still visible
+            ("let outer = fn(n) { let iter = fn(n) { if (n == 0) { 0 } else { iter(n - 1) } }; iter(n) }; outer(100)", Ok(ObjectType::Integer(0))),
+            // builtins in tail position
+            ("let f = fn(arr) { len(arr) }; f([1, 2])", Ok(ObjectType::Integer(2))),
+            // not in tail position
+            ("let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(20)", Err(EvaluatorError::call_depth_exceeded(10))),
+        ];
+
+        tests.iter().for_each(|(input, result)| {
+            assert_eq!(&test_eval_with_limits(input.to_string(), depth.clone()), result);
+        });
+    }
+
+    #[test]
+    fn test_long_tail_call_loop() {
+        // Each call of the loop would copy the many globals if its frame was not reused, taking minutes
+        let globals = (0..1000).map(|index| format!("let g{} = {};", index, index)).collect::<String>();
+        let input = format!("{} let count = fn(n, acc) {{ if (n == 0) {{ acc }} else {{ count(n - 1, acc + g1) }} }}; count(100000, 0)", globals);
+        let program = Parser::new(Lexer::new(input)).unwrap().parse_program().unwrap();
+
+        let mut context = Context::default().with_timeout(Duration::from_secs(20));
+        assert_eq!(eval_with_context(&program, &mut Environment::new(), &mut context), Ok(ObjectType::Integer(100000)));
+    }
+
+    #[test]
+    fn test_return_stays_in_function() {
+        let tests = vec![
+            ("let f = fn() { return 1; }; f(); 5", Ok(ObjectType::Integer(5))),
+            ("let f = fn() { if (true) { return 1; } 2 }; f() + 10", Ok(ObjectType::Integer(11))),
+        ];
+
+        run_test_suite(&tests);
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let tests = vec![
+            (r#"try { throw "oops"; } catch (e) { e }"#, Ok(ObjectType::String("oops".to_string()))),
+            ("try { 1 } catch (e) { 2 }", Ok(ObjectType::Integer(1))),
+            ("let f = fn() { throw {\"code\": 4}; }; try { f() } catch (e) { e[\"code\"] }", Ok(ObjectType::Integer(4))),
+            ("try { [1][5] } catch (e) { e[\"kind\"] }", Ok(ObjectType::String("IndexOutOfBounds".to_string()))),
+            ("try { [1][5] } catch (e) { e[\"message\"] }", Ok(ObjectType::String("5 is out of bounds for array of size 1".to_string()))),
+            ("let a = 1;\nlet f = fn() {\n  a[0]\n};\ntry { f() } catch (e) { e[\"location\"][\"line\"] }", Ok(ObjectType::Integer(3))),
+            ("try { try { throw 1; } catch (e) { throw e + 1; } } catch (e) { e }", Ok(ObjectType::Integer(2))),
+            ("let a = 0; try { throw 1; } catch (e) { let a = 1; } finally { let a = a + 10; }; a", Ok(ObjectType::Integer(11))),
+            ("let a = 0; try { try { throw 1; } finally { let a = 10; } } catch (e) { a + e }", Ok(ObjectType::Integer(11))),
+            ("let e = 5; let r = try { throw 1; } catch (e) { e }; [e, r]", Ok(ObjectType::Array(vec![ObjectType::Integer(5), ObjectType::Integer(1)]))),
+            ("try { throw 1; } catch (e) { 2 }; e", Err(EvaluatorError::unknown_identifier("e"))),
+            ("let f = fn() { try { return 1; } finally { 2 } }; f() + 10", Ok(ObjectType::Integer(11))),
+            ("let f = fn() { try { 1 } finally { return 2; } }; f()", Ok(ObjectType::Integer(2))),
+            ("throw 1 + 1;", Err(EvaluatorError::thrown("2".to_string()))),
+            ("try { throw 1; } finally { 2 }", Err(EvaluatorError::thrown("1".to_string()))),
+            ("try { 1 } catch (e) { 2 } finally { unknown }", Err(EvaluatorError::unknown_identifier("unknown"))),
+        ];
+
+        run_test_suite(&tests);
+
+        // Limits stop the program even inside a `try`
+        let limits = Limits { max_steps: Some(100), ..Limits::default() };
+        let input = "let f = fn(x) { f(x + 1) }; try { f(0) } catch (e) { 0 }";
+        assert_eq!(test_eval_with_limits(input.to_string(), limits), Err(EvaluatorError::step_limit_exceeded(100)));
+    }
+
+    #[test]
+    fn test_assertions() {
+        let tests = vec![
+            ("assert(1 < 2); assert_eq([1, \"a\"], [1, \"a\"]); 3", Ok(ObjectType::Integer(3))),
+            ("assert(1 > 2, \"not greater\")", Err(EvaluatorError::assertion_failed("not greater".to_string()))),
+            ("assert_eq(1 + 1, 3)", Err(EvaluatorError::assertion_not_equal("3".to_string(), "2".to_string()))),
+            ("assert_error(fn() { [1][5] })[\"kind\"]", Ok(ObjectType::String("IndexOutOfBounds".to_string()))),
+            ("assert_error(fn() { throw 4; }, \"Thrown\") + 1", Ok(ObjectType::Integer(5))),
+            ("let f = fn() { assert_error(fn() { 1 }) }; f()", Err(EvaluatorError::assertion_failed("expected an error, got 1".to_string()))),
+            (
+                "assert_error(fn() { unknown }, \"IndexOutOfBounds\")",
+                Err(EvaluatorError::assertion_failed("expected an error of kind IndexOutOfBounds, got Unknown identifier: unknown".to_string())),
+            ),
+            ("try { assert_eq(1, 2) } catch (e) { e[\"kind\"] }", Ok(ObjectType::String("AssertionNotEqual".to_string()))),
+        ];
+
+        run_test_suite(&tests);
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let backtrace = |input: &str| {
+            let program = Parser::new(Lexer::new(input.to_string())).unwrap().parse_program().unwrap();
+            let mut context = Context::default();
+            let error = eval_with_context(&program, &mut Environment::new(), &mut context).unwrap_err();
+            assert_eq!(context.depth(), 0);
+
+            context.backtrace(&error).map(|backtrace| {
+                backtrace.frames.iter().map(|frame| (frame.function.clone(), frame.location.line)).collect::<Vec<_>>()
+            })
+        };
+        let frames = |frames: &[(&str, u32)]| Some(frames.iter().map(|(function, line)| (function.to_string(), *line)).collect::<Vec<_>>());
+
+        assert_eq!(backtrace("1 + a"), frames(&[("<main>", 1)]));
+        assert_eq!(backtrace("let inner = fn() {\n  a\n};\nlet outer = fn() {\n  inner() + 1\n};\n\nouter()"), frames(&[("inner", 2), ("outer", 5), ("<main>", 8)]));
+        assert_eq!(backtrace("let f = fn(g) {\n  g() + 1\n};\nf(fn() { a })"), frames(&[("<anonymous>", 4), ("f", 2), ("<main>", 4)]));
+        // A call in tail position replaces its caller
+        assert_eq!(backtrace("let fail = fn() {\n  a\n};\nlet f = fn() {\n  fail()\n};\nf()"), frames(&[("fail", 2), ("<main>", 7)]));
+        // Only the error that escaped has a backtrace
+        assert_eq!(backtrace("let f = fn() { a };\ntry { f() } catch (e) { 1 };\n1 + b"), frames(&[("<main>", 3)]));
+    }
+
     #[test]
     fn test_quote() {
         std::env::set_var("RUST_LOG", "trace");
@@ -0,0 +1,92 @@
+use ast::statement::Statement;
+use environment::environment::Environment;
+use environment::object::ObjectType;
+use error::EvaluatorError;
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::context::Context;
+use crate::evaluator::eval_with_context;
+
+/// Called by the evaluator before each statement, debuggers use it to pause the program
+pub trait Hook {
+    /// `context` gives the location of `statement` and the call stack, an error stops the evaluation
+    fn before_statement(&mut self, context: &Context, environment: &Environment, statement: &Statement) -> Result<(), EvaluatorError>;
+}
+
+impl std::fmt::Debug for dyn Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Hook")
+    }
+}
+
+/// Evaluates `input` with the bindings visible in a paused frame, without modifying them.
+/// The evaluation does not call the hook and uses the limits and the output of `context`.
+pub fn eval_in_frame(context: &Context, environment: &Environment, input: &str) -> Result<ObjectType, EvaluatorError> {
+    let mut parser = Parser::new(Lexer::new(input.to_string()))?;
+    let program = parser.parse_program().map_err(|mut errors| errors.remove(0))?;
+
+    let mut frame_context = Context::new(context.limits.clone());
+    frame_context.modules = context.modules.clone();
+    frame_context.output = context.output.clone();
+
+    eval_with_context(&program, &mut environment.clone(), &mut frame_context)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Records the line and call depth of every statement, and stops at `stop_at`
+    struct Recorder {
+        lines: Rc<RefCell<Vec<(u32, usize)>>>,
+        stop_at: Option<u32>,
+    }
+
+    impl Hook for Recorder {
+        fn before_statement(&mut self, context: &Context, _environment: &Environment, _statement: &Statement) -> Result<(), EvaluatorError> {
+            self.lines.borrow_mut().push((context.location.line, context.depth()));
+            if Some(context.location.line) == self.stop_at {
+                return Err(EvaluatorError::interrupted());
+            }
+            Ok(())
+        }
+    }
+
+    fn run(input: &str, stop_at: Option<u32>) -> (Result<ObjectType, EvaluatorError>, Vec<(u32, usize)>) {
+        let lines = Rc::new(RefCell::new(vec![]));
+        let program = Parser::new(Lexer::new(input.to_string())).unwrap().parse_program().unwrap();
+        let mut context = Context::default().with_hook(Box::new(Recorder { lines: lines.clone(), stop_at }));
+
+        let result = eval_with_context(&program, &mut Environment::new(), &mut context);
+        (result, lines.take())
+    }
+
+    #[test]
+    fn test_hook() {
+        let input = "let f = fn(x) {\n  x + 1\n};\nlet a = f(1);\nf(a)";
+
+        let (result, lines) = run(input, None);
+        assert_eq!(result, Ok(ObjectType::Integer(3)));
+        assert_eq!(lines, vec![(1, 0), (4, 0), (2, 1), (5, 0), (2, 1)]);
+
+        let (result, lines) = run(input, Some(2));
+        assert_eq!(result, Err(EvaluatorError::interrupted()));
+        assert_eq!(lines, vec![(1, 0), (4, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn test_eval_in_frame() {
+        let mut environment = Environment::new();
+        environment.set("a", ObjectType::Integer(2));
+        let context = Context::default();
+
+        assert_eq!(eval_in_frame(&context, &environment, "a * 3"), Ok(ObjectType::Integer(6)));
+        assert_eq!(eval_in_frame(&context, &environment, "let a = 5; a"), Ok(ObjectType::Integer(5)));
+        assert_eq!(environment.get("a"), Some(&ObjectType::Integer(2)));
+        assert_eq!(eval_in_frame(&context, &environment, "b"), Err(EvaluatorError::unknown_identifier("b")));
+    }
+}
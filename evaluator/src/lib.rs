@@ -1,4 +1,10 @@
 pub mod evaluator;
 pub mod builtins;
 pub mod modify;
-pub mod converter;
\ No newline at end of file
+pub mod converter;
+pub mod context;
+pub mod backtrace;
+pub mod module;
+pub mod prelude;
+pub mod hook;
+pub mod output;
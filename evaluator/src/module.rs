@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use ast::location::MAIN_SOURCE;
+use ast::program::Program;
+use ast::statement::Statement;
+use environment::environment::Environment;
+use environment::object::ObjectType;
+use error::EvaluatorError;
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::context::Context;
+use crate::evaluator::eval_with_context;
+use crate::prelude::prelude_environment;
+
+/// Exported bindings of an evaluated module
+pub type Exports = Vec<(String, ObjectType)>;
+
+/// Resolves, loads and caches the modules imported by a program
+#[derive(Debug, Default, Clone)]
+pub struct Modules {
+    /// Directories searched when a module is not found next to the importing file
+    pub search_paths: Vec<PathBuf>,
+    /// Files being evaluated, the last one is the importing file
+    loading: Vec<PathBuf>,
+    cache: HashMap<PathBuf, Exports>,
+    /// Bindings of the prelude, every module starts with them
+    pub prelude: Exports,
+    /// Names of the sources parsed after the main program, see `Location::source`
+    sources: Vec<String>,
+}
+
+impl Modules {
+    pub fn new(search_paths: Vec<PathBuf>) -> Self {
+        return Self {
+            search_paths,
+            ..Self::default()
+        };
+    }
+
+    /// Sets the file of the main program, imports are resolved relative to it
+    pub fn set_main_file(&mut self, file: PathBuf) {
+        let file = file.canonicalize().unwrap_or(file);
+        self.loading.clear();
+        self.loading.push(file);
+    }
+
+    /// Registers a source to parse, its statements are located with the returned number
+    pub fn add_source(&mut self, name: String) -> u32 {
+        self.sources.push(name);
+        self.sources.len() as u32
+    }
+
+    /// Name of a source registered with `add_source`, `None` for the main program
+    pub fn source_name(&self, source: u32) -> Option<&str> {
+        if source == MAIN_SOURCE {
+            return None;
+        }
+        self.sources.get(source as usize - 1).map(|name| name.as_str())
+    }
+
+    /// Names a module exports, read without evaluating it. `None` when the module cannot be found or parsed
+    pub fn exported_names(&self, path: &str) -> Option<Vec<String>> {
+        let file = self.resolve(path).ok()?;
+        let input = std::fs::read_to_string(file).ok()?;
+        let program = Parser::new(Lexer::new(input)).ok()?.parse_program().ok()?;
+        Some(exported_identifiers(&program))
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, EvaluatorError> {
+        let requested = Path::new(path);
+        if requested.is_absolute() {
+            return requested.canonicalize().map_err(|_| EvaluatorError::module_not_found(path));
+        }
+
+        let importing_directory = match self.loading.last() {
+            Some(file) => file.parent().map(|parent| parent.to_path_buf()).unwrap_or_default(),
+            None => PathBuf::from("."),
+        };
+
+        let candidates = std::iter::once(&importing_directory).chain(self.search_paths.iter());
+        for directory in candidates {
+            let candidate = directory.join(requested);
+            if candidate.is_file() {
+                return candidate.canonicalize().map_err(|e| EvaluatorError::module_error(path, e.to_string()));
+            }
+        }
+
+        Err(EvaluatorError::module_not_found(path))
+    }
+}
+
+/// Evaluates the module at `path` once and binds its exports, or only `names`, into `environment`
+pub fn import(context: &mut Context, environment: &mut Environment, path: &str, names: &Option<Vec<String>>) -> Result<ObjectType, EvaluatorError> {
+    let file = context.modules.resolve(path)?;
+    let exports = load(context, &file)?;
+
+    match names {
+        None => {
+            for (name, value) in exports {
+                environment.set(&name, value);
+            }
+        }
+        Some(names) => {
+            for name in names {
+                let value = exports.iter().find(|(export, _)| export == name);
+                match value {
+                    Some((_, value)) => environment.set(name, value.clone()),
+                    None => return Err(EvaluatorError::not_exported(name, path)),
+                }
+            }
+        }
+    }
+
+    Ok(ObjectType::Null)
+}
+
+fn load(context: &mut Context, file: &Path) -> Result<Exports, EvaluatorError> {
+    if let Some(exports) = context.modules.cache.get(file) {
+        return Ok(exports.clone());
+    }
+
+    if let Some(start) = context.modules.loading.iter().position(|loading| loading.as_path() == file) {
+        let cycle = context.modules.loading[start..].iter().map(PathBuf::as_path)
+            .chain(std::iter::once(file))
+            .map(|path| path.display().to_string())
+            .collect::<Vec<String>>()
+            .join(" -> ");
+        return Err(EvaluatorError::cyclic_import(cycle));
+    }
+
+    let display = file.display().to_string();
+    debug!("Loading module {}", display);
+
+    let input = std::fs::read_to_string(file).map_err(|e| EvaluatorError::module_error(&display, e.to_string()))?;
+    let mut parser = Parser::new(Lexer::new(input)).map_err(|e| EvaluatorError::module_error(&display, e.to_string()))?;
+    parser.source = context.modules.add_source(display.clone());
+    let program = parser.parse_program().map_err(|errors| {
+        let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+        EvaluatorError::module_error(&display, messages.join(", "))
+    })?;
+
+    let mut environment = Environment::new_enclosed(&prelude_environment(&context.modules.prelude));
+
+    context.modules.loading.push(file.to_path_buf());
+    let evaluated = eval_with_context(&program, &mut environment, context);
+    context.modules.loading.pop();
+    evaluated?;
+
+    let exports = collect_exports(&program, &environment);
+    context.modules.cache.insert(file.to_path_buf(), exports.clone());
+    Ok(exports)
+}
+
+/// Values of the top level `export let` bindings of an evaluated program
+pub(crate) fn collect_exports(program: &Program, environment: &Environment) -> Exports {
+    let mut exports = vec![];
+    for identifier in exported_identifiers(program) {
+        if let Some(value) = environment.get(&identifier) {
+            exports.push((identifier, value.clone()));
+        }
+    }
+
+    exports
+}
+
+/// Names of the top level `export let` bindings of a program
+pub(crate) fn exported_identifiers(program: &Program) -> Vec<String> {
+    let mut identifiers = vec![];
+    for statement in &program.statements {
+        if let Statement::ExportStatement { statement } = statement {
+            if let Statement::LetStatement { identifier, .. } = statement.as_ref() {
+                identifiers.push(identifier.clone());
+            }
+        }
+    }
+
+    identifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_modules(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("monkey-modules-{}-{}", name, std::process::id()));
+        for (path, content) in files {
+            let path = directory.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        directory
+    }
+
+    fn run(directory: &Path, main: &str, search_paths: Vec<PathBuf>) -> Result<ObjectType, EvaluatorError> {
+        let input = std::fs::read_to_string(directory.join(main)).unwrap();
+        let program = Parser::new(Lexer::new(input)).unwrap().parse_program().unwrap();
+
+        let mut context = Context::default();
+        context.modules = Modules::new(search_paths);
+        context.modules.set_main_file(directory.join(main));
+
+        eval_with_context(&program, &mut Environment::new(), &mut context)
+    }
+
+    #[test]
+    fn test_import() {
+        let directory = write_modules("import", &[
+            ("main.monkey", r#"import "lib/math.monkey"; import { twice } from "lib/math.monkey"; twice(square(3))"#),
+            ("lib/math.monkey", r#"import { double } from "double.monkey"; export let square = fn(x) { x * x }; export let twice = fn(x) { double(x) };"#),
+            ("lib/double.monkey", r#"export let double = fn(x) { x * 2 }; let private = 1;"#),
+            ("private.monkey", r#"import { private } from "lib/double.monkey";"#),
+            ("missing.monkey", r#"import "nope.monkey";"#),
+        ]);
+
+        assert_eq!(run(&directory, "main.monkey", vec![]), Ok(ObjectType::Integer(18)));
+        assert_eq!(run(&directory, "private.monkey", vec![]), Err(EvaluatorError::not_exported("private", "lib/double.monkey")));
+        assert_eq!(run(&directory, "missing.monkey", vec![]), Err(EvaluatorError::module_not_found("nope.monkey")));
+    }
+
+    #[test]
+    fn test_search_path() {
+        let directory = write_modules("search", &[
+            ("app/main.monkey", r#"import "shared.monkey"; value"#),
+            ("vendor/shared.monkey", r#"export let value = 42;"#),
+        ]);
+
+        assert_eq!(run(&directory, "app/main.monkey", vec![]), Err(EvaluatorError::module_not_found("shared.monkey")));
+        assert_eq!(run(&directory, "app/main.monkey", vec![directory.join("vendor")]), Ok(ObjectType::Integer(42)));
+    }
+
+    #[test]
+    fn test_evaluated_once() {
+        let directory = write_modules("once", &[
+            ("main.monkey", r#"import "a.monkey"; import "b.monkey"; a + b"#),
+            ("a.monkey", r#"import { counter } from "counter.monkey"; export let a = counter;"#),
+            ("b.monkey", r#"import { counter } from "counter.monkey"; export let b = counter;"#),
+            ("counter.monkey", r#"export let counter = len([1]);"#),
+        ]);
+
+        let input = std::fs::read_to_string(directory.join("main.monkey")).unwrap();
+        let program = Parser::new(Lexer::new(input)).unwrap().parse_program().unwrap();
+        let mut context = Context::default();
+        context.modules.set_main_file(directory.join("main.monkey"));
+
+        assert_eq!(eval_with_context(&program, &mut Environment::new(), &mut context), Ok(ObjectType::Integer(2)));
+        assert_eq!(context.modules.cache.len(), 3);
+    }
+
+    #[test]
+    fn test_cyclic_import() {
+        let directory = write_modules("cycle", &[
+            ("a.monkey", r#"import "b.monkey";"#),
+            ("b.monkey", r#"import "a.monkey";"#),
+        ]);
+
+        let a = directory.join("a.monkey").canonicalize().unwrap().display().to_string();
+        let b = directory.join("b.monkey").canonicalize().unwrap().display().to_string();
+        assert_eq!(run(&directory, "a.monkey", vec![]), Err(EvaluatorError::cyclic_import(format!("{} -> {} -> {}", a, b, a))));
+    }
+}
@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use error::EvaluatorError;
+
+/// Destination of one of the streams of an `Output`
+#[derive(Clone)]
+enum Stream {
+    Stdout,
+    Stderr,
+    Writer(Arc<Mutex<dyn Write + Send>>),
+}
+
+impl Stream {
+    fn write(&self, text: &str) -> std::io::Result<()> {
+        match self {
+            Stream::Stdout => std::io::stdout().lock().write_all(text.as_bytes()),
+            Stream::Stderr => std::io::stderr().lock().write_all(text.as_bytes()),
+            Stream::Writer(writer) => writer.lock().unwrap().write_all(text.as_bytes()),
+        }
+    }
+}
+
+/// Where a program prints: `print` and `println` write to its output stream, `eprint` and `eprintln` to its error stream.
+/// The standard streams of the process by default.
+#[derive(Clone)]
+pub struct Output {
+    out: Stream,
+    err: Stream,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self { out: Stream::Stdout, err: Stream::Stderr }
+    }
+}
+
+impl std::fmt::Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Output")
+    }
+}
+
+impl Output {
+    /// Writes to `out` and `err`, a `Buffer` keeps what is written
+    pub fn new(out: impl Write + Send + 'static, err: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Stream::Writer(Arc::new(Mutex::new(out))),
+            err: Stream::Writer(Arc::new(Mutex::new(err))),
+        }
+    }
+
+    /// Writes to the standard output and error of the process
+    pub fn stdio() -> Self {
+        Self::default()
+    }
+
+    /// Writes `text` to the output stream
+    pub fn print(&self, text: &str) -> std::io::Result<()> {
+        self.out.write(text)
+    }
+
+    /// Writes `text` to the error stream
+    pub fn eprint(&self, text: &str) -> std::io::Result<()> {
+        self.err.write(text)
+    }
+}
+
+/// In-memory stream, its clones share the same contents
+#[derive(Debug, Clone, Default)]
+pub struct Buffer {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What was written so far
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.lock().unwrap()).into_owned()
+    }
+
+    /// What was written so far, the buffer is emptied
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.bytes.lock().unwrap());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Output of the builtin running on this thread, builtins do not see the context
+    static CURRENT: RefCell<Output> = RefCell::new(Output::default());
+}
+
+/// Runs `f` with the printing builtins called on this thread writing to `output`
+pub(crate) fn install<T>(output: &Output, f: impl FnOnce() -> T) -> T {
+    let outer = CURRENT.with(|current| current.replace(output.clone()));
+    let result = f();
+    CURRENT.with(|current| current.replace(outer));
+    result
+}
+
+/// Writes `text` to the output stream of the running builtin, or to its error stream when `error` is set
+pub(crate) fn write(text: &str, error: bool) -> Result<(), EvaluatorError> {
+    let output = CURRENT.with(|current| current.borrow().clone());
+    let result = if error { output.eprint(text) } else { output.print(text) };
+    result.map_err(|err| EvaluatorError::output_error(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use environment::environment::Environment;
+    use lexer::lexer::Lexer;
+    use parser::parser::Parser;
+
+    use crate::context::Context;
+    use crate::evaluator::eval_with_context;
+
+    use super::*;
+
+    fn run(input: &str) -> (String, String) {
+        let (out, err) = (Buffer::new(), Buffer::new());
+        let program = Parser::new(Lexer::new(input.to_string())).unwrap().parse_program().unwrap();
+        let mut context = Context::default().with_output(Output::new(out.clone(), err.clone()));
+        eval_with_context(&program, &mut Environment::new(), &mut context).unwrap();
+        (out.take(), err.contents())
+    }
+
+    #[test]
+    fn test_output() {
+        assert_eq!(run("print(1, \"a\"); println(true, [2]);"), ("1atrue\n[2]\n".to_string(), "".to_string()));
+        assert_eq!(run("eprint(\"a\"); eprintln(1); println(2);"), ("2\n".to_string(), "a1\n".to_string()));
+        assert_eq!(run("let p = println; let f = fn(x) { p(x) }; f([1]);"), ("[1]\n".to_string(), "".to_string()));
+    }
+
+    #[test]
+    fn test_failing_output() {
+        struct Closed;
+
+        impl Write for Closed {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let program = Parser::new(Lexer::new("println(1);".to_string())).unwrap().parse_program().unwrap();
+        let mut context = Context::default().with_output(Output::new(Closed, Buffer::new()));
+        let result = eval_with_context(&program, &mut Environment::new(), &mut context);
+        assert_eq!(result.unwrap_err().kind(), "OutputError");
+    }
+}
@@ -0,0 +1,155 @@
+use environment::environment::Environment;
+use error::EvaluatorError;
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::context::Context;
+use crate::evaluator::eval_with_context;
+use crate::module::{collect_exports, exported_identifiers, Exports};
+
+// Evaluated in this order, a module can use the exports of the previous ones
+static PRELUDE: [(&str, &str); 3] = [
+    ("prelude/functional.monkey", include_str!("functional.monkey")),
+    ("prelude/collections.monkey", include_str!("collections.monkey")),
+    ("prelude/strings.monkey", include_str!("strings.monkey")),
+];
+
+/// Evaluates the prelude and binds its exports in a scope enclosing `environment`, user definitions shadow them.
+/// Modules imported later in the same context also start in a scope enclosed by the prelude.
+/// The prelude has its own context, it does not use up the limits of `context`.
+pub fn load_prelude(context: &mut Context, environment: &mut Environment) -> Result<(), EvaluatorError> {
+    let mut prelude_context = Context::default();
+    prelude_context.modules = std::mem::take(&mut context.modules);
+    let exports = evaluate_prelude(&mut prelude_context);
+    context.modules = prelude_context.modules;
+    let exports = exports?;
+
+    // Functions copy the bindings of the scope they are defined in, with the prelude in that scope a recursive
+    // user function reusing a prelude name would call the prelude function
+    let mut global = Environment::new_enclosed(&prelude_environment(&exports));
+    global.merge(environment);
+    *environment = global;
+    context.modules.prelude = exports;
+    context.restart_deadline();
+
+    Ok(())
+}
+
+/// Scope binding the exports of the prelude
+pub(crate) fn prelude_environment(exports: &Exports) -> Environment {
+    let mut environment = Environment::new();
+    for (name, value) in exports {
+        environment.set(name, value.clone());
+    }
+    environment
+}
+
+fn evaluate_prelude(context: &mut Context) -> Result<Exports, EvaluatorError> {
+    let mut prelude_environment = Environment::new();
+    let mut exports: Exports = vec![];
+
+    for (name, source) in PRELUDE.iter() {
+        let mut parser = Parser::new(Lexer::new(source.to_string())).map_err(|e| EvaluatorError::module_error(name, e.to_string()))?;
+        parser.source = context.modules.add_source(name.to_string());
+        let program = parser.parse_program().map_err(|errors| {
+            let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+            EvaluatorError::module_error(name, messages.join(", "))
+        })?;
+
+        eval_with_context(&program, &mut prelude_environment, context)?;
+        exports.extend(collect_exports(&program, &prelude_environment));
+    }
+
+    Ok(exports)
+}
+
+/// Names the prelude binds, read without evaluating it
+pub fn prelude_names() -> Vec<String> {
+    let mut names = vec![];
+    for (_, source) in PRELUDE.iter() {
+        let program = Parser::new(Lexer::new(source.to_string())).ok().and_then(|mut parser| parser.parse_program().ok());
+        if let Some(program) = program {
+            names.extend(exported_identifiers(&program));
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ast::program::Program;
+    use environment::object::ObjectType;
+
+    use crate::context::Limits;
+
+    use super::*;
+
+    fn test_eval_with_prelude(input: &str) -> Result<ObjectType, EvaluatorError> {
+        let program: Program = Parser::new(Lexer::new(input.to_string())).unwrap().parse_program().unwrap();
+        let mut context = Context::default();
+        let mut environment = Environment::new();
+        load_prelude(&mut context, &mut environment)?;
+
+        eval_with_context(&program, &mut environment, &mut context)
+    }
+
+    fn array(values: &[i64]) -> ObjectType {
+        ObjectType::Array(values.iter().map(|value| ObjectType::Integer(*value)).collect())
+    }
+
+    #[test]
+    fn test_prelude() {
+        let tests: Vec<(&str, ObjectType)> = vec![
+            ("map([1, 2, 3], fn(x) { x * 2 })", array(&[2, 4, 6])),
+            ("filter(range(0, 6), fn(x) { x > 3 })", array(&[4, 5])),
+            ("reduce([1, 2, 3], 10, fn(x, y) { x + y })", ObjectType::Integer(16)),
+            ("compose(fn(x) { x + 1 }, fn(x) { x * 2 })(5)", ObjectType::Integer(11)),
+            ("identity(7)", ObjectType::Integer(7)),
+            ("any([1, 2, 3], fn(x) { x == 2 })", ObjectType::Boolean(true)),
+            ("all([1, 2, 3], fn(x) { x < 3 })", ObjectType::Boolean(false)),
+            ("sum(range(1, 101))", ObjectType::Integer(5050)),
+            ("contains([1, 2, 3], 4)", ObjectType::Boolean(false)),
+            ("concat([1], [2, 3])", array(&[1, 2, 3])),
+            ("reverse([1, 2, 3])", array(&[3, 2, 1])),
+            ("slice([1, 2, 3, 4], 1, 3)", array(&[2, 3])),
+            ("take([1, 2, 3], 5)", array(&[1, 2, 3])),
+            ("drop([1, 2, 3], 1)", array(&[2, 3])),
+            (r#"join(["a", "b", "c"], ", ")"#, ObjectType::String("a, b, c".to_string())),
+            (r#"join(reverse(chars("abc")), "")"#, ObjectType::String("cba".to_string())),
+            (r#"repeat("ab", 3)"#, ObjectType::String("ababab".to_string())),
+            (r#"starts_with("monkey", "mon")"#, ObjectType::Boolean(true)),
+            (r#"ends_with("monkey", "key")"#, ObjectType::Boolean(true)),
+            (r#"ends_with("key", "monkey")"#, ObjectType::Boolean(false)),
+            // User definitions override the prelude, the other prelude functions keep using their own helpers
+            ("let sum = fn(arr) { 0 }; sum([1, 2])", ObjectType::Integer(0)),
+            ("let reduce = fn(arr, initial, f) { 0 }; sum([1, 2])", ObjectType::Integer(3)),
+            // Including recursive ones, in tail position or not
+            ("let map = fn(x) { if (x == 0) { 0 } else { map(x - 1) } }; map(3)", ObjectType::Integer(0)),
+            ("let sum = fn(n, acc) { if (n == 0) { acc } else { sum(n - 1, acc + n) } }; sum(100, 0)", ObjectType::Integer(5050)),
+            ("let take = fn(n) { if (n == 0) { 0 } else { 1 + take(n - 1) } }; take(5)", ObjectType::Integer(5)),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval_with_prelude(input), Ok(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_prelude_does_not_use_the_limits() {
+        let program: Program = Parser::new(Lexer::new("1".to_string())).unwrap().parse_program().unwrap();
+        let limits = Limits { max_steps: Some(20), ..Limits::default() };
+        let mut context = Context::new(limits).with_timeout(Duration::from_millis(50));
+        let mut environment = Environment::new();
+        load_prelude(&mut context, &mut environment).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+
+        assert_eq!(eval_with_context(&program, &mut environment, &mut context), Ok(ObjectType::Integer(1)));
+    }
+
+    #[test]
+    fn test_private_helpers() {
+        assert_eq!(test_eval_with_prelude("reduce_from"), Err(EvaluatorError::unknown_identifier("reduce_from")));
+    }
+}
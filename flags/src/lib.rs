@@ -1,4 +1,22 @@
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
 
 pub static STOP_AT_FIRST_ERROR: AtomicBool = AtomicBool::new(false);
-pub static PRINT_EVALUATED_RESULT: AtomicBool = AtomicBool::new(false);
\ No newline at end of file
+pub static PRINT_EVALUATED_RESULT: AtomicBool = AtomicBool::new(false);
+pub static NO_PRELUDE: AtomicBool = AtomicBool::new(false);
+
+// Evaluation limits, `None` keeps the default
+pub static MAX_CALL_DEPTH: Lazy<Mutex<Option<usize>>> = Lazy::new(|| Mutex::new(None));
+pub static MAX_STEPS: Lazy<Mutex<Option<u64>>> = Lazy::new(|| Mutex::new(None));
+pub static MAX_COLLECTION_SIZE: Lazy<Mutex<Option<usize>>> = Lazy::new(|| Mutex::new(None));
+// Timeout of the evaluation, 0 means no timeout
+pub static TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+
+// Directories searched for imported modules
+pub static MODULE_PATHS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(vec![]));
+
+// REPL session resumed on start, and the directory the sessions are saved in (default: the XDG data directory)
+pub static SESSION_NAME: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new("default".to_string()));
+pub static SESSION_DIR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
@@ -0,0 +1,668 @@
+use std::collections::{HashMap, VecDeque};
+
+use ast::expression::{quote, Expression};
+use ast::statement::Statement;
+use error::EvaluatorError;
+use lexer::lexer::{Comment, Lexer};
+use lexer::precedence::Precedence;
+use lexer::token::TokenType;
+use parser::parser::Parser;
+
+/// Layout settings of the formatter
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Number of spaces per indentation level
+    pub indent_width: usize,
+    /// Width arrays, hashes and arguments are broken over several lines to fit in
+    pub max_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        return Self {
+            indent_width: 4,
+            max_width: 100,
+        };
+    }
+}
+
+/// Formats a program, comments are kept and the result parses to the same program.
+/// The layout only depends on the program, its comments and its blank lines, so formatting twice changes nothing.
+pub fn format(source: &str, options: &FormatOptions) -> Result<String, Vec<EvaluatorError>> {
+    let mut parser = Parser::new(Lexer::new(source.to_string())).map_err(|error| vec![error])?;
+    let program = parser.parse_program()?;
+
+    let mut printer = Printer {
+        options,
+        lines: source.lines().collect(),
+        comments: parser.lexer.comments.iter().cloned().collect(),
+        tokens: SourceTokens::new(source),
+        next_token: 0,
+        last_line: 0,
+    };
+    Ok(printer.statements(&program.statements, 0, u32::MAX))
+}
+
+/// Tokens of the source, the printer walks them along the program to find the lines of its brackets and comments
+#[derive(Default)]
+struct SourceTokens {
+    kinds: Vec<TokenType>,
+    lines: Vec<u32>,
+    /// Index of the closing bracket of each opening one, a block left open has none
+    closing: HashMap<usize, usize>,
+    /// Commas directly inside each opening bracket
+    commas: HashMap<usize, Vec<usize>>,
+    /// Innermost opening bracket around the comment of each line
+    comment_owners: HashMap<u32, usize>,
+}
+
+impl SourceTokens {
+    fn new(source: &str) -> Self {
+        let mut lexer = Lexer::new(source.to_string());
+        let mut tokens = Self::default();
+        let mut open: Vec<usize> = vec![];
+        let mut next_comment = 0;
+
+        while let Ok(token) = lexer.next_token() {
+            // A comment ends its line, it is inside the brackets left open by the tokens before it
+            while let Some(comment) = lexer.comments.get(next_comment).filter(|comment| comment.line < token.line || token.kind == TokenType::EOF) {
+                if let Some(opening) = open.last() {
+                    tokens.comment_owners.insert(comment.line, *opening);
+                }
+                next_comment += 1;
+            }
+
+            let index = tokens.kinds.len();
+            match token.kind {
+                TokenType::EOF => break,
+                TokenType::LPAREN | TokenType::LBRACKET | TokenType::LBRACE => open.push(index),
+                TokenType::RPAREN | TokenType::RBRACKET | TokenType::RBRACE => {
+                    if let Some(opening) = open.pop() {
+                        tokens.closing.insert(opening, index);
+                    }
+                }
+                TokenType::COMMA => {
+                    if let Some(opening) = open.last() {
+                        tokens.commas.entry(*opening).or_default().push(index);
+                    }
+                }
+                _ => {}
+            }
+            tokens.kinds.push(token.kind);
+            tokens.lines.push(token.line);
+        }
+        tokens
+    }
+}
+
+/// Operators are stored as the representation of their token
+fn operator_precedence(operator: &str) -> u8 {
+    match Lexer::new(operator.to_string()).next_token() {
+        Ok(token) => token.to_precedence().value(),
+        Err(_) => Precedence::LOWEST.value(),
+    }
+}
+
+/// How tightly an expression binds, literals and expressions delimited by keywords or brackets never need parentheses
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::InfixExpression { operator, .. } => operator_precedence(operator),
+        Expression::PrefixExpression { .. } => Precedence::PREFIX.value(),
+        Expression::CallExpression { .. } => Precedence::CALL.value(),
+        Expression::IndexExpression { .. } => Precedence::INDEX.value(),
+        Expression::GroupedExpression { expression } => precedence(expression),
+        _ => u8::MAX,
+    }
+}
+
+/// Column reached after writing `text` from `column`, the lines following the first one are already indented
+fn end_column(column: usize, text: &str) -> usize {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => last.len(),
+        None => column + text.len(),
+    }
+}
+
+/// A statement of a block, its separator is only known once the next one is printed
+struct Printed {
+    /// Comments and blank lines before the statement
+    before: String,
+    text: String,
+    /// Set for the expressions ending with a block, they only need a `;` when the next statement could continue them
+    optional_semicolon: bool,
+    first_line_comments: Vec<String>,
+    last_line_comments: Vec<String>,
+    /// Comments written on their own line in the middle of the statement
+    after: String,
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    lines: Vec<&'a str>,
+    comments: VecDeque<Comment>,
+    tokens: SourceTokens,
+    /// Index of the first token of the source not printed yet
+    next_token: usize,
+    /// Last line of the source printed so far
+    last_line: u32,
+}
+
+/// Comments written inside a list, attached to its elements
+#[derive(Default)]
+struct ListComments {
+    /// Comments on their own line before each element
+    before: Vec<Vec<String>>,
+    /// Comments at the end of the last line of each element
+    trailing: Vec<Vec<String>>,
+    /// Comments after the last element
+    after: Vec<String>,
+}
+
+impl ListComments {
+    fn is_empty(&self) -> bool {
+        self.after.is_empty() && self.before.iter().chain(&self.trailing).all(|comments| comments.is_empty())
+    }
+}
+
+impl Printer<'_> {
+    fn indent(&self, level: usize) -> String {
+        " ".repeat(level * self.options.indent_width)
+    }
+
+    fn is_blank(&self, line: u32) -> bool {
+        line >= 1 && self.lines.get(line as usize - 1).map(|text| text.trim().is_empty()).unwrap_or(false)
+    }
+
+    /// Whether code comes before the comment on its line
+    fn is_trailing(&self, comment: &Comment) -> bool {
+        let line = self.lines.get(comment.line as usize - 1).copied().unwrap_or("");
+        line.chars().take(comment.column as usize - 1).any(|ch| !ch.is_whitespace())
+    }
+
+    /// Moves past the next token of the source matching `matches`, the tokens before it are not printed as such
+    fn take(&mut self, matches: impl Fn(&TokenType) -> bool) -> Option<usize> {
+        let index = (self.next_token..self.tokens.kinds.len()).find(|index| matches(&self.tokens.kinds[*index]))?;
+        self.next_token = index + 1;
+        self.last_line = self.last_line.max(self.tokens.lines[index]);
+        Some(index)
+    }
+
+    /// Moves past the next identifier or literal
+    fn take_leaf(&mut self) {
+        self.take(|kind| matches!(kind, TokenType::IDENT(_) | TokenType::INT(_) | TokenType::STRING(_) | TokenType::TRUE | TokenType::FALSE));
+    }
+
+    fn take_opening(&mut self, kind: TokenType) -> Option<usize> {
+        self.take(|token| *token == kind)
+    }
+
+    /// Line of the bracket closing `opening`, the end of the file for a block left open
+    fn closing_line(&self, opening: Option<usize>) -> u32 {
+        match opening.and_then(|opening| self.tokens.closing.get(&opening)) {
+            Some(closing) => self.tokens.lines[*closing],
+            None => u32::MAX,
+        }
+    }
+
+    /// Moves past the bracket closing `opening`
+    fn take_closing(&mut self, opening: Option<usize>) {
+        match opening.and_then(|opening| self.tokens.closing.get(&opening)) {
+            Some(closing) => self.next_token = self.next_token.max(closing + 1),
+            None => self.next_token = self.tokens.kinds.len(),
+        }
+        self.last_line = self.last_line.max(self.closing_line(opening));
+    }
+
+    /// Takes the comments written directly inside the list opened at `opening`, they stay with the element on their line
+    /// or come before the next one
+    fn list_comments(&mut self, opening: Option<usize>, count: usize) -> ListComments {
+        let (Some(opening), Some(closing)) = (opening, opening.and_then(|opening| self.tokens.closing.get(&opening).copied())) else {
+            return ListComments::default();
+        };
+        let owners = &self.tokens.comment_owners;
+        let (owned, others): (Vec<Comment>, Vec<Comment>) = self.comments.drain(..).partition(|comment| owners.get(&comment.line) == Some(&opening));
+        self.comments = others.into();
+
+        let mut comments = ListComments { before: vec![vec![]; count], trailing: vec![vec![]; count], after: vec![] };
+        if count == 0 {
+            comments.after = owned.into_iter().map(|comment| comment.text).collect();
+            return comments;
+        }
+
+        // Elements are separated by the commas directly inside the list
+        let commas = self.tokens.commas.get(&opening).cloned().unwrap_or_default();
+        let starts = (0..count).map(|index| match index {
+            0 => self.tokens.lines[opening + 1],
+            _ => commas.get(index - 1).map(|comma| self.tokens.lines[comma + 1]).unwrap_or(u32::MAX),
+        }).collect::<Vec<u32>>();
+        let ends = (0..count).map(|index| {
+            let end = commas.get(index).copied().unwrap_or(closing);
+            self.tokens.lines[end - 1]
+        }).collect::<Vec<u32>>();
+
+        for comment in owned {
+            if self.is_trailing(&comment) {
+                if let Some(index) = (0..count).rev().find(|index| ends[*index] == comment.line) {
+                    comments.trailing[index].push(comment.text);
+                    continue;
+                }
+            }
+            match (0..count).find(|index| starts[*index] > comment.line) {
+                Some(index) => comments.before[index].push(comment.text),
+                None => comments.after.push(comment.text),
+            }
+        }
+        comments
+    }
+
+    /// Comments written before `line`, each on its own line
+    fn comments_before(&mut self, line: u32, level: usize, first: &mut bool) -> String {
+        let mut output = String::new();
+        while matches!(self.comments.front(), Some(comment) if comment.line < line) {
+            let comment = self.comments.pop_front().unwrap();
+            if !*first && self.is_blank(comment.line - 1) {
+                output.push('\n');
+            }
+            output.push_str(&format!("{}{}\n", self.indent(level), comment.text));
+            *first = false;
+        }
+        output
+    }
+
+    /// Statements of a block ending at the line `end`, each line is indented and ends with a newline
+    fn statements(&mut self, statements: &[Statement], level: usize, end: u32) -> String {
+        let indent = self.indent(level);
+        let mut printed = vec![];
+        let mut first = true;
+
+        for statement in statements {
+            let line = statement.location().map(|location| location.line).unwrap_or(self.last_line);
+            let mut before = self.comments_before(line, level, &mut first);
+            if !first && self.is_blank(line.saturating_sub(1)) {
+                before.push('\n');
+            }
+            first = false;
+
+            self.last_line = self.last_line.max(line);
+            let (text, optional_semicolon) = self.statement(statement, level, indent.len());
+
+            let mut first_line_comments = vec![];
+            let mut last_line_comments = vec![];
+            let mut after = String::new();
+            while matches!(self.comments.front(), Some(comment) if comment.line <= self.last_line) {
+                let comment = self.comments.pop_front().unwrap();
+                if !self.is_trailing(&comment) {
+                    after.push_str(&format!("{}{}\n", indent, comment.text));
+                } else if comment.line == line && text.contains('\n') {
+                    first_line_comments.push(comment.text);
+                } else {
+                    last_line_comments.push(comment.text);
+                }
+            }
+
+            printed.push(Printed { before, text, optional_semicolon, first_line_comments, last_line_comments, after });
+        }
+
+        let mut output = String::new();
+        for index in 0..printed.len() {
+            let statement = &printed[index];
+            let mut text = statement.text.clone();
+
+            let continued = printed.get(index + 1).map(|next| next.text.starts_with(['(', '[', '-', '+'])).unwrap_or(false);
+            if statement.optional_semicolon && continued {
+                text.push(';');
+            }
+            if !statement.first_line_comments.is_empty() {
+                let (head, tail) = text.split_once('\n').unwrap();
+                text = format!("{} {}\n{}", head, statement.first_line_comments.join(" "), tail);
+            }
+            if !statement.last_line_comments.is_empty() {
+                text = format!("{} {}", text, statement.last_line_comments.join(" "));
+            }
+
+            output.push_str(&format!("{}{}{}\n{}", statement.before, indent, text, statement.after));
+        }
+
+        output.push_str(&self.comments_before(end, level, &mut first));
+        output
+    }
+
+    /// Text of a statement starting at `column`, without its indentation
+    fn statement(&mut self, statement: &Statement, level: usize, column: usize) -> (String, bool) {
+        let text = match statement {
+            Statement::EmptyStatement => String::new(),
+            Statement::LetStatement { identifier, value, .. } => {
+                self.take_leaf();
+                let prefix = format!("let {} = ", identifier);
+                format!("{}{};", prefix, self.expression(value, level, column + prefix.len()))
+            }
+            Statement::ReturnStatement { value, .. } => format!("return {};", self.expression(value, level, column + 7)),
+            Statement::ThrowStatement { value, .. } => format!("throw {};", self.expression(value, level, column + 6)),
+            Statement::ImportStatement { path, names: None, .. } => {
+                self.take_leaf();
+                format!("import {};", quote(path))
+            }
+            Statement::ImportStatement { path, names: Some(names), .. } => {
+                let opening = self.take_opening(TokenType::LBRACE);
+                self.take_closing(opening);
+                // `from` and the path
+                self.take_leaf();
+                self.take_leaf();
+                if names.is_empty() {
+                    format!("import {{}} from {};", quote(path))
+                } else {
+                    format!("import {{ {} }} from {};", names.join(", "), quote(path))
+                }
+            }
+            Statement::ExportStatement { statement } => {
+                let (text, _) = self.statement(statement, level, column + 7);
+                format!("export {}", text)
+            }
+            Statement::ExpressionStatement(expression, _) => {
+                let text = self.expression(expression, level, column);
+                if matches!(expression, Expression::IfExpression { .. } | Expression::FunctionLiteral { .. } | Expression::TryExpression { .. }) {
+                    return (text, true);
+                }
+                format!("{};", text)
+            }
+        };
+        (text, false)
+    }
+
+    fn block(&mut self, statements: &[Statement], level: usize) -> String {
+        let opening = self.take_opening(TokenType::LBRACE);
+        let end = self.closing_line(opening);
+        let body = self.statements(statements, level + 1, end);
+        self.take_closing(opening);
+
+        if body.is_empty() {
+            return "{}".to_string();
+        }
+        format!("{{\n{}{}}}", body, self.indent(level))
+    }
+
+    fn parenthesized(&mut self, expression: &Expression, parentheses: bool, level: usize, column: usize) -> String {
+        if parentheses {
+            return format!("({})", self.expression(expression, level, column + 1));
+        }
+        self.expression(expression, level, column)
+    }
+
+    fn expression(&mut self, expression: &Expression, level: usize, column: usize) -> String {
+        match expression {
+            Expression::NullLiteral => "null".to_string(),
+            Expression::StringLiteral(string) => {
+                self.take_leaf();
+                quote(string)
+            }
+            Expression::IntegerLiteral(integer) => {
+                self.take_leaf();
+                integer.to_string()
+            }
+            Expression::BooleanLiteral(boolean) => {
+                self.take_leaf();
+                boolean.to_string()
+            }
+            Expression::Identifier(identifier) => {
+                self.take_leaf();
+                identifier.clone()
+            }
+            Expression::GroupedExpression { expression } => self.expression(expression, level, column),
+            Expression::PrefixExpression { operator, right } => {
+                let parentheses = precedence(right) < Precedence::PREFIX.value();
+                format!("{}{}", operator, self.parenthesized(right, parentheses, level, column + operator.len()))
+            }
+            Expression::InfixExpression { left, operator, right } => {
+                // Operators are left associative, a right operand of the same precedence needs parentheses
+                let operator_precedence = operator_precedence(operator);
+                let left = self.parenthesized(left, precedence(left) < operator_precedence, level, column);
+                let column = end_column(column, &left) + operator.len() + 2;
+                let right = self.parenthesized(right, precedence(right) <= operator_precedence, level, column);
+                format!("{} {} {}", left, operator, right)
+            }
+            Expression::CallExpression { function, arguments } => {
+                let function = self.parenthesized(function, precedence(function) < Precedence::CALL.value(), level, column);
+                let column = end_column(column, &function);
+                let opening = self.take_opening(TokenType::LPAREN);
+                let (arguments, comments) = self.list(opening, arguments, level, column + 1);
+                format!("{}{}", function, self.layout("(", arguments, comments, ")", level, column))
+            }
+            Expression::IndexExpression { left, index } => {
+                let left = self.parenthesized(left, precedence(left) < Precedence::CALL.value(), level, column);
+                let column = end_column(column, &left);
+                let opening = self.take_opening(TokenType::LBRACKET);
+                let index = self.expression(index, level, column + 1);
+                self.take_closing(opening);
+                format!("{}[{}]", left, index)
+            }
+            Expression::ArrayLiteral(elements) => {
+                let opening = self.take_opening(TokenType::LBRACKET);
+                let (elements, comments) = self.list(opening, elements, level, column + 1);
+                self.layout("[", elements, comments, "]", level, column)
+            }
+            Expression::HashLiteral(pairs) => {
+                let opening = self.take_opening(TokenType::LBRACE);
+                let comments = self.list_comments(opening, pairs.len());
+                let mut entries = vec![];
+                let mut entry_column = column + 1;
+                for (key, value) in pairs {
+                    let key = self.expression(key, level, entry_column);
+                    let value_column = end_column(entry_column, &key) + 2;
+                    let entry = format!("{}: {}", key, self.expression(value, level, value_column));
+                    entry_column = end_column(entry_column, &entry) + 2;
+                    entries.push(entry);
+                }
+                self.take_closing(opening);
+                self.layout("{", entries, comments, "}", level, column)
+            }
+            Expression::IfExpression { condition, consequence, alternative } => {
+                let condition = self.expression(condition, level, column + 4);
+                let mut text = format!("if ({}) {}", condition, self.block(consequence, level));
+                if let Some(alternative) = alternative {
+                    text = format!("{} else {}", text, self.block(alternative, level));
+                }
+                text
+            }
+            Expression::FunctionLiteral { parameters, body } => {
+                parameters.iter().for_each(|_| self.take_leaf());
+                let parameters = parameters.iter().map(|parameter| parameter.to_string()).collect::<Vec<String>>();
+                format!("fn({}) {}", parameters.join(", "), self.block(body, level))
+            }
+            Expression::TryExpression { body, catch, finally } => {
+                let mut text = format!("try {}", self.block(body, level));
+                if let Some((parameter, handler)) = catch {
+                    self.take_leaf();
+                    text = format!("{} catch ({}) {}", text, parameter, self.block(handler, level));
+                }
+                if let Some(finally) = finally {
+                    text = format!("{} finally {}", text, self.block(finally, level));
+                }
+                text
+            }
+        }
+    }
+
+    /// Elements of the list opened at `opening`, each one printed as if the list fit on one line, and its comments
+    fn list(&mut self, opening: Option<usize>, elements: &[Expression], level: usize, column: usize) -> (Vec<String>, ListComments) {
+        // Taken first so that the blocks of the elements do not take them
+        let comments = self.list_comments(opening, elements.len());
+        let mut column = column;
+        let mut printed = vec![];
+        for element in elements {
+            let text = self.expression(element, level, column);
+            column = end_column(column, &text) + 2;
+            printed.push(text);
+        }
+        self.take_closing(opening);
+        (printed, comments)
+    }
+
+    /// Lays out a list on one line if it fits, around its last element if only that one spans several lines,
+    /// or with one element per line, as lists with comments are
+    fn layout(&self, open: &str, elements: Vec<String>, comments: ListComments, close: &str, level: usize, column: usize) -> String {
+        if elements.is_empty() && comments.is_empty() {
+            return format!("{}{}", open, close);
+        }
+
+        let joined = elements.join(", ");
+        let first_line = joined.split('\n').next().unwrap_or("");
+        let multiline = elements.iter().filter(|element| element.contains('\n')).count() + if comments.is_empty() { 0 } else { 2 };
+
+        if multiline == 0 && column + open.len() + joined.len() + close.len() <= self.options.max_width {
+            return format!("{}{}{}", open, joined, close);
+        }
+        if multiline == 1 && elements.last().is_some_and(|element| element.contains('\n')) && column + open.len() + first_line.len() <= self.options.max_width {
+            return format!("{}{}{}", open, joined, close);
+        }
+
+        let indent = self.indent(level + 1);
+        let unit = self.indent(1);
+        let mut lines = vec![];
+        for (index, element) in elements.iter().enumerate() {
+            lines.extend(comments.before[index].iter().map(|comment| format!("{}{}", indent, comment)));
+
+            // Elements were printed at the indentation of the list
+            let element_lines = element.split('\n').enumerate().map(|(index, line)| {
+                if index == 0 || line.is_empty() { line.to_string() } else { format!("{}{}", unit, line) }
+            });
+            let mut text = format!("{}{}", indent, element_lines.collect::<Vec<String>>().join("\n"));
+            if index + 1 < elements.len() {
+                text.push(',');
+            }
+            if !comments.trailing[index].is_empty() {
+                text = format!("{} {}", text, comments.trailing[index].join(" "));
+            }
+            lines.push(text);
+        }
+        lines.extend(comments.after.iter().map(|comment| format!("{}{}", indent, comment)));
+
+        format!("{}\n{}\n{}{}", open, lines.join("\n"), self.indent(level), close)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::program::Program;
+    use ast::statement::without_locations;
+
+    use super::*;
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source.to_string())).unwrap().parse_program().unwrap()
+    }
+
+    /// Formats `source` and checks the result is stable and parses to the same program
+    fn check(source: &str, options: &FormatOptions) -> String {
+        let formatted = format(source, options).unwrap();
+        assert_eq!(without_locations(&parse(&formatted).statements), without_locations(&parse(source).statements), "{}", formatted);
+        assert_eq!(format(&formatted, options).unwrap(), formatted);
+        formatted
+    }
+
+    #[test]
+    fn test_format() {
+        let tests = vec![
+            ("let   x=1+2*3;", "let x = 1 + 2 * 3;\n"),
+            ("let x = (1 + 2) * 3;", "let x = (1 + 2) * 3;\n"),
+            ("let x = 1 - (2 - 3) - 4;", "let x = 1 - (2 - 3) - 4;\n"),
+            ("let x = (1 - 2) - 3;", "let x = 1 - 2 - 3;\n"),
+            ("let x = -(a + b) * -c;", "let x = -(a + b) * -c;\n"),
+            ("let x = (-a)[0] + -a[0] + (f + g)(1) + f(1)[2];", "let x = (-a)[0] + -a[0] + (f + g)(1) + f(1)[2];\n"),
+            ("let x = !(a == b) == (c < d);", "let x = !(a == b) == c < d;\n"),
+            (r#"let s = 'it\'s "quoted"\n';"#, "let s = \"it's \\\"quoted\\\"\\n\";\n"),
+            ("let h = {\"a\": [1, 2], 2: {}};", "let h = {\"a\": [1, 2], 2: {}};\n"),
+            ("import {a,b} from 'lib';import \"all\";export let y = fn() {};", "import { a, b } from \"lib\";\nimport \"all\";\nexport let y = fn() {};\n"),
+            (
+                "let f = fn(x, y) { if (x > y) { return x; } else { y } };",
+                "let f = fn(x, y) {\n    if (x > y) {\n        return x;\n    } else {\n        y;\n    }\n};\n",
+            ),
+            (
+                "let r = try { throw 1; } catch (e) { e } finally { 2 };",
+                "let r = try {\n    throw 1;\n} catch (e) {\n    e;\n} finally {\n    2;\n};\n",
+            ),
+            ("map([1, 2], fn(x) { x * 2 });", "map([1, 2], fn(x) {\n    x * 2;\n});\n"),
+            // A block needs a `;` when the next statement could continue it
+            ("if (a) { 1 }; -b", "if (a) {\n    1;\n};\n-b;\n"),
+            ("if (a) { 1 }; [1]; if (b) { 2 } c", "if (a) {\n    1;\n};\n[1];\nif (b) {\n    2;\n}\nc;\n"),
+        ];
+
+        for (source, expected) in tests {
+            assert_eq!(check(source, &FormatOptions::default()), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "// Header
+
+let a = 1; // one
+let f = fn(x) { // entry
+    // before
+    x
+
+    // end of body
+}; // after f
+
+
+// trailing
+";
+        let expected = "// Header
+
+let a = 1; // one
+let f = fn(x) {
+    // entry
+    // before
+    x;
+
+    // end of body
+}; // after f
+
+// trailing
+";
+        assert_eq!(check(source, &FormatOptions::default()), expected);
+
+        let tests = vec![
+            ("let a = [\n 1, // one\n 2 // two\n];", "let a = [\n    1, // one\n    2 // two\n];\n"),
+            ("f(\n    // first\n    1,\n    2\n    // last\n);", "f(\n    // first\n    1,\n    2\n    // last\n);\n"),
+            ("let h = {\"a\": 1, // a\n \"b\": [2, 3]}; // h", "let h = {\n    \"a\": 1, // a\n    \"b\": [2, 3]\n}; // h\n"),
+            ("let a = [1, [2, // two\n 3]];", "let a = [1, [\n    2, // two\n    3\n]];\n"),
+            ("let a = [ // none\n];", "let a = [\n    // none\n];\n"),
+        ];
+        for (source, expected) in tests {
+            assert_eq!(check(source, &FormatOptions::default()), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let source = "let values = [\"first value\", \"second value\", \"third value\"]; let f = fn() { g(1) };";
+        let options = FormatOptions { indent_width: 2, max_width: 40 };
+
+        let expected = "let values = [\n  \"first value\",\n  \"second value\",\n  \"third value\"\n];\nlet f = fn() {\n  g(1);\n};\n";
+        assert_eq!(check(source, &options), expected);
+        assert!(check(source, &FormatOptions::default()).starts_with("let values = [\"first value\", \"second value\", \"third value\"];\n"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sources = [
+            include_str!("../../examples/3.11-trash.monkey"),
+            include_str!("../../examples/4.4-map.monkey"),
+            include_str!("../../examples/4.4-reduce.monkey"),
+            include_str!("../../evaluator/src/prelude/functional.monkey"),
+            include_str!("../../evaluator/src/prelude/collections.monkey"),
+            include_str!("../../evaluator/src/prelude/strings.monkey"),
+        ];
+
+        for source in sources {
+            let formatted = check(source, &FormatOptions::default());
+            let comments = |text: &str| text.lines().filter(|line| line.contains("//")).count();
+            assert_eq!(comments(&formatted), comments(source), "{}", formatted);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(format("let = 1;", &FormatOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use monkey_fuzz::generator::Source;
+
+// The generated programs are always valid, so the evaluator is reached on every input
+fuzz_target!(|source: Source| {
+    if let Err(errors) = monkey_fuzz::parse(&source.0) {
+        panic!("generated program does not parse: {:?}\n{}", errors, source.0);
+    }
+    monkey_fuzz::evaluate(&source.0);
+});
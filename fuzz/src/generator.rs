@@ -0,0 +1,190 @@
+use std::fmt::{Debug, Formatter};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Expressions nested deeper than this are leaves
+const MAX_DEPTH: usize = 5;
+/// Statements in the program and in each block
+const MAX_STATEMENTS: usize = 8;
+/// Elements of array and hash literals, arguments of calls
+const MAX_ELEMENTS: usize = 4;
+
+/// Names bound by the generated `let`s and parameters
+const NAMES: [&str; 6] = ["a", "b", "c", "f", "g", "value"];
+/// Builtins and prelude functions the programs call, the printing ones are left out to keep the runs quiet
+const FUNCTIONS: [&str; 23] = [
+    "len", "first", "last", "push", "pop", "rest", "json_parse", "json_stringify", "assert", "quote", "unquote",
+    "range", "sum", "concat", "reverse", "slice", "take", "reduce", "map", "filter", "chars", "join", "repeat",
+];
+const INTEGERS: [i64; 8] = [0, 1, 2, -1, 10, 255, i64::MAX, i64::MIN + 1];
+const OPERATORS: [&str; 10] = ["+", "-", "*", "/", "<", "<=", ">", ">=", "==", "!="];
+
+/// The source of a syntactically valid Monkey program, built from the fuzzer input
+pub struct Source(pub String);
+
+impl Debug for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Source {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut generator = Generator { u, depth: 0, in_function: false, bound: vec![] };
+        let mut statements = vec![generator.statement()?];
+        while generator.more(statements.len(), MAX_STATEMENTS)? {
+            statements.push(generator.statement()?);
+        }
+        Ok(Source(statements.join("\n")))
+    }
+}
+
+struct Generator<'a, 'b> {
+    u: &'a mut Unstructured<'b>,
+    depth: usize,
+    /// Whether `return` is allowed
+    in_function: bool,
+    /// Names bound so far, identifiers are mostly picked from them
+    bound: Vec<String>,
+}
+
+impl Generator<'_, '_> {
+    /// Whether to add another item to a list of `length` items, never once the input is used up
+    /// so programs do not end with the same default statement repeated
+    fn more(&mut self, length: usize, max: usize) -> Result<bool> {
+        Ok(length < max && !self.u.is_empty() && self.u.arbitrary()?)
+    }
+
+    fn statement(&mut self) -> Result<String> {
+        let statement = match self.u.int_in_range(0..=9)? {
+            0..=2 => {
+                let name = self.name()?;
+                let value = self.expression()?;
+                self.bound.push(name.clone());
+                format!("let {} = {};", name, value)
+            }
+            3 if self.in_function => format!("return {};", self.expression()?),
+            4 => format!("throw {};", self.expression()?),
+            _ => format!("{};", self.expression()?),
+        };
+        Ok(statement)
+    }
+
+    fn block(&mut self) -> Result<String> {
+        let mut statements = vec![];
+        while self.more(statements.len(), MAX_STATEMENTS / 2)? {
+            statements.push(self.statement()?);
+        }
+        Ok(format!("{{ {} }}", statements.join(" ")))
+    }
+
+    fn expression(&mut self) -> Result<String> {
+        if self.depth >= MAX_DEPTH || self.u.ratio(1, 3)? {
+            return self.leaf();
+        }
+
+        self.depth += 1;
+        let expression = match self.u.int_in_range(0..=9)? {
+            0 => format!("{}({})", self.u.choose(&["!", "-"])?, self.expression()?),
+            1 => {
+                let operator = self.u.choose(&OPERATORS)?;
+                format!("({} {} {})", self.expression()?, operator, self.expression()?)
+            }
+            2 => {
+                let condition = self.expression()?;
+                let consequence = self.block()?;
+                match self.u.arbitrary::<bool>()? {
+                    true => format!("if ({}) {} else {}", condition, consequence, self.block()?),
+                    false => format!("if ({}) {}", condition, consequence),
+                }
+            }
+            3 => self.try_expression()?,
+            4 => self.function()?,
+            5 => {
+                let function = match self.u.arbitrary::<bool>()? {
+                    true => self.u.choose(&FUNCTIONS)?.to_string(),
+                    false => format!("({})", self.expression()?),
+                };
+                format!("{}({})", function, self.elements()?)
+            }
+            6 => format!("[{}]", self.elements()?),
+            7 => {
+                let mut pairs = vec![];
+                while self.more(pairs.len(), MAX_ELEMENTS)? {
+                    pairs.push(format!("{}: {}", self.leaf()?, self.expression()?));
+                }
+                format!("{{{}}}", pairs.join(", "))
+            }
+            8 => format!("({})[{}]", self.expression()?, self.expression()?),
+            _ => self.leaf()?,
+        };
+        self.depth -= 1;
+        Ok(expression)
+    }
+
+    fn try_expression(&mut self) -> Result<String> {
+        let mut expression = format!("try {}", self.block()?);
+        let (catch, finally) = match self.u.int_in_range(0..=2)? {
+            0 => (true, false),
+            1 => (false, true),
+            _ => (true, true),
+        };
+        if catch {
+            let name = self.name()?;
+            self.bound.push(name.clone());
+            expression.push_str(&format!(" catch ({}) {}", name, self.block()?));
+        }
+        if finally {
+            expression.push_str(&format!(" finally {}", self.block()?));
+        }
+        Ok(expression)
+    }
+
+    fn function(&mut self) -> Result<String> {
+        let mut parameters: Vec<String> = vec![];
+        while self.more(parameters.len(), 3)? {
+            let name = self.name()?;
+            if !parameters.contains(&name) {
+                parameters.push(name);
+            }
+        }
+        self.bound.extend(parameters.iter().cloned());
+
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        let body = self.block()?;
+        self.in_function = in_function;
+        Ok(format!("fn({}) {}", parameters.join(", "), body))
+    }
+
+    fn elements(&mut self) -> Result<String> {
+        let mut elements = vec![];
+        while self.more(elements.len(), MAX_ELEMENTS)? {
+            elements.push(self.expression()?);
+        }
+        Ok(elements.join(", "))
+    }
+
+    fn leaf(&mut self) -> Result<String> {
+        let leaf = match self.u.int_in_range(0..=5)? {
+            0 => self.u.choose(&INTEGERS)?.to_string(),
+            1 => self.u.int_in_range(0..=100i64)?.to_string(),
+            2 => self.string()?,
+            3 => self.u.choose(&["true", "false"])?.to_string(),
+            4 if !self.bound.is_empty() => self.u.choose(&self.bound)?.clone(),
+            _ => self.u.choose(&FUNCTIONS)?.to_string(),
+        };
+        Ok(leaf)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let mut parts = vec![];
+        while self.more(parts.len(), 6)? {
+            parts.push(*self.u.choose(&["a", "b", " ", "é", "{", "\\n", "\\\"", "\\\\"])?);
+        }
+        Ok(format!("\"{}\"", parts.concat()))
+    }
+
+    fn name(&mut self) -> Result<String> {
+        Ok(self.u.choose(&NAMES)?.to_string())
+    }
+}
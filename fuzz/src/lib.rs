@@ -0,0 +1,51 @@
+use environment::environment::Environment;
+use error::EvaluatorError;
+use evaluator::context::{Context, Limits};
+use evaluator::evaluator::{eval_with_context, with_evaluation_stack};
+use evaluator::output::Output;
+use evaluator::prelude::load_prelude;
+use lexer::lexer::Lexer;
+use lexer::token::TokenType;
+use parser::parser::Parser;
+
+pub mod generator;
+
+/// Limits keeping each run short, whatever the program does
+fn limits() -> Limits {
+    return Limits {
+        max_depth: Some(64),
+        max_steps: Some(20_000),
+        max_collection_size: Some(1_000),
+    };
+}
+
+/// Reads every token of `source`, up to the end or the first error
+pub fn lex(source: &str) {
+    let mut lexer = Lexer::new(source.to_string());
+    while let Ok(token) = lexer.next_token() {
+        if token.kind == TokenType::EOF {
+            break;
+        }
+    }
+}
+
+pub fn parse(source: &str) -> Result<ast::program::Program, Vec<EvaluatorError>> {
+    Parser::new(Lexer::new(source.to_string())).map_err(|error| vec![error])?.parse_program()
+}
+
+/// Evaluates `source` after the prelude under `limits`, errors are expected, panics are not.
+/// Runs on a thread with the stack the interpreter gives to evaluations, a panic is raised again here.
+pub fn evaluate(source: &str) {
+    let program = match parse(source) {
+        Ok(program) => program,
+        Err(_) => return,
+    };
+
+    with_evaluation_stack(move || {
+        // Printing is not what is fuzzed, writing to the terminal would only slow the runs down
+        let mut context = Context::new(limits()).with_output(Output::new(std::io::sink(), std::io::sink()));
+        let mut environment = Environment::new();
+        load_prelude(&mut context, &mut environment).expect("the prelude loads");
+        let _ = eval_with_context(&program, &mut environment, &mut context);
+    });
+}
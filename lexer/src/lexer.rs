@@ -4,11 +4,21 @@ use error::EvaluatorError;
 
 use crate::token::{Token, TokenType};
 
+/// A `//` comment, skipped like whitespace but kept for the tools that print the source back
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Text of the comment, including the `//`
+    pub text: String,
+    pub line: u32,
+    /// Column of the first `/`
+    pub column: u32,
+}
+
 pub struct Lexer {
     input: String,
-    /// current position in input (points to current char)
+    /// current byte offset in input (points to current char)
     position: usize,
-    /// current reading position in input (after current char)
+    /// current reading byte offset in input (after current char)
     read_position: usize,
     /// current char under examination
     ch: char,
@@ -17,6 +27,9 @@ pub struct Lexer {
     line: u32,
     /// current column
     column: u32,
+
+    /// comments read so far
+    pub comments: Vec<Comment>,
 }
 
 impl Debug for Lexer {
@@ -44,6 +57,7 @@ impl Lexer {
             ch: '\0',
             line: 1,
             column: 1,
+            comments: vec![],
         };
 
         lexer.next_char();
@@ -60,31 +74,37 @@ impl Lexer {
         self.next_char();
         self.line = 1;
         self.column = 1;
+        self.comments.clear();
     }
 
     pub fn next_char(&mut self) -> char {
-        if self.read_position >= self.input.len() {
-            self.ch = '\0';
-        } else {
-            self.ch = self.input.chars().nth(self.read_position).unwrap();
-        }
+        self.ch = self.peek_char();
 
         self.position = self.read_position;
-        self.read_position += 1;
+        self.read_position += self.ch.len_utf8();
         self.column += 1;
         return self.ch;
     }
 
+    /// Byte offset of the character under examination, right after the last token read
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
     pub fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            return '\0';
-        } else {
-            return self.input.chars().nth(self.read_position).unwrap();
-        }
+        return self.input.get(self.read_position..).and_then(|rest| rest.chars().next()).unwrap_or('\0');
     }
 
     fn skip_whitespace(&mut self) {
-        while matches!(self.ch, ' ' | '\t' | '\n' | '\r') {
+        loop {
+            if self.ch == '/' && self.peek_char() == '/' {
+                self.read_comment();
+                continue;
+            }
+            if !matches!(self.ch, ' ' | '\t' | '\n' | '\r') {
+                break;
+            }
+
             let is_newline = self.ch == '\n';
             self.next_char();
             if is_newline {
@@ -94,59 +114,73 @@ impl Lexer {
         }
     }
 
+    /// Reads a comment up to the end of the line, the newline is left to `skip_whitespace`
+    fn read_comment(&mut self) {
+        let start = self.position;
+        let column = self.column;
+        while !matches!(self.ch, '\n' | '\0') {
+            self.next_char();
+        }
+
+        let text = self.input[start..self.position].trim_end().to_string();
+        self.comments.push(Comment { text, line: self.line, column });
+    }
+
     pub fn next_token(&mut self) -> Result<Token, EvaluatorError> {
         // Skip whitespace
         self.skip_whitespace();
 
+        // Tokens are reported at their first character
+        let (line, column) = (self.line, self.column);
         let mut has_read = false;
 
         let token = match self.ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.next_char();
-                    Token::new(TokenType::EQ, self.line, self.column)
+                    Token::new(TokenType::EQ, line, column)
                 } else {
-                    Token::new(TokenType::ASSIGN, self.line, self.column)
+                    Token::new(TokenType::ASSIGN, line, column)
                 }
             }
-            '+' => Token::new(TokenType::PLUS, self.line, self.column),
-            '-' => Token::new(TokenType::MINUS, self.line, self.column),
+            '+' => Token::new(TokenType::PLUS, line, column),
+            '-' => Token::new(TokenType::MINUS, line, column),
             '!' => {
                 if self.peek_char() == '=' {
                     self.next_char();
-                    Token::new(TokenType::NOT_EQ, self.line, self.column)
+                    Token::new(TokenType::NOT_EQ, line, column)
                 } else {
-                    Token::new(TokenType::BANG, self.line, self.column)
+                    Token::new(TokenType::BANG, line, column)
                 }
             }
-            '*' => Token::new(TokenType::ASTERISK, self.line, self.column),
-            '/' => Token::new(TokenType::SLASH, self.line, self.column),
+            '*' => Token::new(TokenType::ASTERISK, line, column),
+            '/' => Token::new(TokenType::SLASH, line, column),
             '<' => {
                 if self.peek_char() == '=' {
                     self.next_char();
-                    Token::new(TokenType::LTE, self.line, self.column)
+                    Token::new(TokenType::LTE, line, column)
                 } else {
-                    Token::new(TokenType::LT, self.line, self.column)
+                    Token::new(TokenType::LT, line, column)
                 }
             }
             '>' => {
                 if self.peek_char() == '=' {
                     self.next_char();
-                    Token::new(TokenType::GTE, self.line, self.column)
+                    Token::new(TokenType::GTE, line, column)
                 } else {
-                    Token::new(TokenType::GT, self.line, self.column)
+                    Token::new(TokenType::GT, line, column)
                 }
             }
-            ',' => Token::new(TokenType::COMMA, self.line, self.column),
-            ';' => Token::new(TokenType::SEMICOLON, self.line, self.column),
-            ':' => Token::new(TokenType::COLON, self.line, self.column),
-            '(' => Token::new(TokenType::LPAREN, self.line, self.column),
-            ')' => Token::new(TokenType::RPAREN, self.line, self.column),
-            '{' => Token::new(TokenType::LBRACE, self.line, self.column),
-            '}' => Token::new(TokenType::RBRACE, self.line, self.column),
-            '[' => Token::new(TokenType::LBRACKET, self.line, self.column),
-            ']' => Token::new(TokenType::RBRACKET, self.line, self.column),
-            '\0' => Token::new(TokenType::EOF, self.line, self.column),
+            ',' => Token::new(TokenType::COMMA, line, column),
+            ';' => Token::new(TokenType::SEMICOLON, line, column),
+            ':' => Token::new(TokenType::COLON, line, column),
+            '(' => Token::new(TokenType::LPAREN, line, column),
+            ')' => Token::new(TokenType::RPAREN, line, column),
+            '{' => Token::new(TokenType::LBRACE, line, column),
+            '}' => Token::new(TokenType::RBRACE, line, column),
+            '[' => Token::new(TokenType::LBRACKET, line, column),
+            ']' => Token::new(TokenType::RBRACKET, line, column),
+            '\0' => Token::new(TokenType::EOF, line, column),
             'a'..='z' | 'A'..='Z' | '_' => {
                 has_read = true;
                 let start = self.position;
@@ -164,10 +198,16 @@ impl Lexer {
                     "if" => TokenType::IF,
                     "else" => TokenType::ELSE,
                     "return" => TokenType::RETURN,
+                    "import" => TokenType::IMPORT,
+                    "export" => TokenType::EXPORT,
+                    "try" => TokenType::TRY,
+                    "catch" => TokenType::CATCH,
+                    "finally" => TokenType::FINALLY,
+                    "throw" => TokenType::THROW,
                     _ => TokenType::IDENT(literal),
                 };
 
-                Token::new(token_type, self.line, self.column)
+                Token::new(token_type, line, column)
             }
             '0'..='9' => {
                 has_read = true;
@@ -177,40 +217,34 @@ impl Lexer {
                 }
                 let literal = self.input[start..self.position].to_string();
 
-                Token::new(TokenType::INT(literal.parse::<i64>().unwrap()), self.line, self.column)
+                match literal.parse::<i64>() {
+                    Ok(value) => Token::new(TokenType::INT(value), line, column),
+                    Err(_) => return Err(EvaluatorError::integer_too_large(literal, line, column)),
+                }
             }
             '"' | '\'' => {
                 let quote = self.ch;
                 has_read = true;
                 let start = self.position + 1;
                 let mut is_escaped = false;
-                while is_escaped || (self.peek_char() != quote && self.peek_char() != '\0') {
-                    is_escaped = false;
-                    self.next_char();
-                    if self.ch == '\\' { // \
-                        is_escaped = true;
-                    }
+                while self.peek_char() != '\0' && (is_escaped || self.peek_char() != quote) {
+                    // A backslash escapes the next character, unless it is itself escaped
+                    let ch = self.next_char();
+                    is_escaped = !is_escaped && ch == '\\';
                 }
                 self.next_char();
-                let literal = self.input[start..self.position].to_string()
-                    // replace escaped characters
-                    .replace("\\n", "\n")
-                    .replace("\\t", "\t")
-                    .replace("\\r", "\r")
-                    .replace("\\\\", "\\")
-                    .replace("\\\"", "\"")
-                    .replace("\\'", "'");
+                let literal = unescape(&self.input[start..self.position]);
 
                 // if not closed, return illegal token
                 if self.ch != quote {
                     // TODO: return error
-                    return Err(EvaluatorError::unfinished_string(literal, self.line, self.column));
+                    return Err(EvaluatorError::unfinished_string(literal, line, column));
                 }
 
                 self.next_char();
-                Token::new(TokenType::STRING(literal), self.line, self.column)
+                Token::new(TokenType::STRING(literal), line, column)
             }
-            v => Token::new(TokenType::ILLEGAL(v), self.line, self.column),
+            v => Token::new(TokenType::ILLEGAL(v), line, column),
         };
 
         // Read next char if not literal or number
@@ -222,6 +256,30 @@ impl Lexer {
     }
 }
 
+/// Replaces the escape sequences of a string literal, unknown sequences are kept as they are
+fn unescape(literal: &str) -> String {
+    let mut result = String::new();
+    let mut chars = literal.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(escaped @ ('\\' | '"' | '\'')) => result.push(escaped),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use crate::token::TokenType;
@@ -419,6 +477,134 @@ mod tests {
         assert_eq!(lexer.next_char(), '\0');
     }
 
+    #[test]
+    fn modules() {
+        let input = r#"import { a, b } from "lib.monkey"; export let c = a;"#;
+
+        let expected_tokens = vec![
+            TokenType::IMPORT,
+            TokenType::LBRACE,
+            TokenType::IDENT("a".to_string()),
+            TokenType::COMMA,
+            TokenType::IDENT("b".to_string()),
+            TokenType::RBRACE,
+            // `from` is only a keyword in imports, the parser recognizes it there
+            TokenType::IDENT("from".to_string()),
+            TokenType::STRING("lib.monkey".to_string()),
+            TokenType::SEMICOLON,
+            TokenType::EXPORT,
+            TokenType::LET,
+            TokenType::IDENT("c".to_string()),
+            TokenType::ASSIGN,
+            TokenType::IDENT("a".to_string()),
+            TokenType::SEMICOLON,
+            TokenType::EOF,
+        ];
+
+        let mut lexer = Lexer::new(input.to_string());
+        for expected_token in expected_tokens {
+            let token = lexer.next_token().unwrap();
+            assert_eq!(token.kind, expected_token);
+        }
+    }
+
+    #[test]
+    fn exceptions() {
+        let input = r#"try { throw "a"; } catch (e) { e } finally { 1 }"#;
+
+        let expected_tokens = vec![
+            TokenType::TRY,
+            TokenType::LBRACE,
+            TokenType::THROW,
+            TokenType::STRING("a".to_string()),
+            TokenType::SEMICOLON,
+            TokenType::RBRACE,
+            TokenType::CATCH,
+            TokenType::LPAREN,
+            TokenType::IDENT("e".to_string()),
+            TokenType::RPAREN,
+            TokenType::LBRACE,
+            TokenType::IDENT("e".to_string()),
+            TokenType::RBRACE,
+            TokenType::FINALLY,
+            TokenType::LBRACE,
+            TokenType::INT(1),
+            TokenType::RBRACE,
+            TokenType::EOF,
+        ];
+
+        let mut lexer = Lexer::new(input.to_string());
+        for expected_token in expected_tokens {
+            let token = lexer.next_token().unwrap();
+            assert_eq!(token.kind, expected_token);
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let input = "// header\nlet a = 1; // one\na / 2 // two";
+
+        let expected_tokens = vec![
+            TokenType::LET,
+            TokenType::IDENT("a".to_string()),
+            TokenType::ASSIGN,
+            TokenType::INT(1),
+            TokenType::SEMICOLON,
+            TokenType::IDENT("a".to_string()),
+            TokenType::SLASH,
+            TokenType::INT(2),
+            TokenType::EOF,
+        ];
+
+        let mut lexer = Lexer::new(input.to_string());
+        for expected_token in expected_tokens {
+            let token = lexer.next_token().unwrap();
+            assert_eq!(token.kind, expected_token);
+        }
+
+        assert_eq!(lexer.comments, vec![
+            Comment { text: "// header".to_string(), line: 1, column: 1 },
+            Comment { text: "// one".to_string(), line: 2, column: 12 },
+            Comment { text: "// two".to_string(), line: 3, column: 7 },
+        ]);
+    }
+
+    #[test]
+    fn escapes() {
+        let tests = vec![
+            (r#""a\\nb""#, "a\\nb"),
+            (r#""a\\""#, "a\\"),
+            (r#""say \"hi\"\n""#, "say \"hi\"\n"),
+            (r#"'it\'s'"#, "it's"),
+        ];
+
+        for (input, expected) in tests {
+            let token = Lexer::new(input.to_string()).next_token().unwrap();
+            assert_eq!(token.kind, TokenType::STRING(expected.to_string()), "{}", input);
+        }
+    }
+
+    /// Inputs minimised from crashes found by the fuzz targets
+    #[test]
+    fn fuzz_regressions() {
+        let tokens = |input: &str| {
+            let mut lexer = Lexer::new(input.to_string());
+            let mut tokens = vec![];
+            loop {
+                match lexer.next_token() {
+                    Ok(token) if token.kind == TokenType::EOF => return Ok(tokens),
+                    Ok(token) => tokens.push(token.kind),
+                    Err(error) => return Err(error),
+                }
+            }
+        };
+
+        assert_eq!(tokens(".\u{79e}_"), Ok(vec![TokenType::ILLEGAL('.'), TokenType::ILLEGAL('\u{79e}'), TokenType::IDENT("_".to_string())]));
+        assert_eq!(tokens("\"é\" ü"), Ok(vec![TokenType::STRING("é".to_string()), TokenType::ILLEGAL('ü')]));
+        assert_eq!(tokens("'\\\0\\"), Err(EvaluatorError::unfinished_string("\\".to_string(), 1, 1)));
+        assert_eq!(tokens("99999999999999999999"), Err(EvaluatorError::integer_too_large("99999999999999999999".to_string(), 1, 1)));
+    }
+
     #[test]
     fn inline_addition() {
         let input = "5 + 6 * 7 - 8 / 9;";
@@ -455,4 +641,42 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn columns() {
+        let input = "let ab = 12 == \"s\";\n  !=fn(x)";
+        let expected = vec![
+            (TokenType::LET, 1, 1),
+            (TokenType::IDENT("ab".to_string()), 1, 5),
+            (TokenType::ASSIGN, 1, 8),
+            (TokenType::INT(12), 1, 10),
+            (TokenType::EQ, 1, 13),
+            (TokenType::STRING("s".to_string()), 1, 16),
+            (TokenType::SEMICOLON, 1, 19),
+            (TokenType::NOT_EQ, 2, 3),
+            (TokenType::FUNCTION, 2, 5),
+            (TokenType::LPAREN, 2, 7),
+            (TokenType::IDENT("x".to_string()), 2, 8),
+            (TokenType::RPAREN, 2, 9),
+            (TokenType::EOF, 2, 10),
+        ];
+
+        let mut lexer = Lexer::new(input.to_string());
+        for (kind, line, column) in expected {
+            assert_eq!(lexer.next_token().unwrap(), Token::new(kind, line, column));
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let mut lexer = Lexer::new("let x".to_string());
+        let tokens = vec![lexer.next_token().unwrap(), lexer.next_token().unwrap()];
+
+        let json = serde_json::to_value(&tokens).unwrap();
+        assert_eq!(json, serde_json::json!([
+            { "kind": "LET", "line": 1, "column": 1 },
+            { "kind": { "IDENT": "x" }, "line": 1, "column": 5 },
+        ]));
+        assert_eq!(serde_json::from_value::<Vec<Token>>(json).unwrap(), tokens);
+    }
+}
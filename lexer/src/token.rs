@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 use crate::precedence::Precedence;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
 pub enum TokenType {
     ILLEGAL(char),
@@ -49,6 +51,12 @@ pub enum TokenType {
     IF,
     ELSE,
     RETURN,
+    IMPORT,
+    EXPORT,
+    TRY,
+    CATCH,
+    FINALLY,
+    THROW,
 }
 
 impl Display for TokenType {
@@ -87,6 +95,12 @@ impl Display for TokenType {
             TokenType::IF => write!(f, "IF"),
             TokenType::ELSE => write!(f, "ELSE"),
             TokenType::RETURN => write!(f, "RETURN"),
+            TokenType::IMPORT => write!(f, "IMPORT"),
+            TokenType::EXPORT => write!(f, "EXPORT"),
+            TokenType::TRY => write!(f, "TRY"),
+            TokenType::CATCH => write!(f, "CATCH"),
+            TokenType::FINALLY => write!(f, "FINALLY"),
+            TokenType::THROW => write!(f, "THROW"),
         };
     }
 }
@@ -113,10 +127,12 @@ impl Into<Precedence> for TokenType {
 }
 
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenType,
+    /// Line of the first character of the token
     pub line: u32,
+    /// Column of the first character of the token
     pub column: u32,
 }
 
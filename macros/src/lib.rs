@@ -0,0 +1,92 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, FieldsNamed};
+
+/// Converts a struct to an `ObjectType::Hash` keyed by its field names
+#[proc_macro_derive(IntoObject)]
+pub fn derive_into_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match named_fields(&input) {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let pairs = fields.named.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let key = ident.to_string();
+        quote! {
+            (
+                ::environment::object::ObjectType::String(#key.to_string()),
+                ::environment::object::IntoObject::into_object(self.#ident)?,
+            )
+        }
+    });
+
+    let expanded = quote! {
+        impl #impl_generics ::environment::object::IntoObject for #name #ty_generics #where_clause {
+            fn into_object(self) -> Result<::environment::object::ObjectType, ::environment::object::EvaluatorError> {
+                Ok(::environment::object::ObjectType::Hash(vec![#(#pairs),*]))
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+/// Builds a struct back from an `ObjectType::Hash` keyed by its field names
+#[proc_macro_derive(FromObject)]
+pub fn derive_from_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match named_fields(&input) {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    let values = fields.named.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let key = ident.to_string();
+        quote! {
+            #ident: match ::environment::object::hash_get(&pairs, #key) {
+                Some(value) => <#ty as ::environment::object::FromObject>::from_object(value)?,
+                // Missing keys are only accepted for fields that can be built from `null`, such as `Option`
+                None => <#ty as ::environment::object::FromObject>::from_object(::environment::object::ObjectType::Null)
+                    .map_err(|_| ::environment::object::EvaluatorError::no_such_key(#key.to_string()))?,
+            }
+        }
+    });
+
+    let expected = name.to_string();
+    let expanded = quote! {
+        impl #impl_generics ::environment::object::FromObject for #name #ty_generics #where_clause {
+            fn from_object(object: ::environment::object::ObjectType) -> Result<Self, ::environment::object::EvaluatorError> {
+                let pairs = match object {
+                    ::environment::object::ObjectType::Hash(pairs) => pairs,
+                    other => return Err(::environment::object::EvaluatorError::cannot_convert_object(#expected, other.to_string().as_str())),
+                };
+
+                Ok(Self {
+                    #(#values),*
+                })
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+fn named_fields(input: &DeriveInput) -> Result<&FieldsNamed, syn::Error> {
+    if let Data::Struct(data) = &input.data {
+        if let Fields::Named(fields) = &data.fields {
+            return Ok(fields);
+        }
+    }
+
+    Err(syn::Error::new_spanned(&input.ident, "Only structs with named fields can be converted to a hash"))
+}
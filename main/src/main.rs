@@ -28,10 +28,114 @@ struct Args {
     /// (default: false)
     #[arg(short = 'p', long = "print")]
     print: bool,
+
+    /// (Optional) Does not load the standard library prelude
+    /// (default: false)
+    #[arg(long = "no-prelude")]
+    no_prelude: bool,
+
+    /// (Optional) Maximum number of nested function calls
+    /// (default: 1000)
+    #[arg(long = "max-depth", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), value_name = "DEPTH")]
+    max_depth: Option<usize>,
+
+    /// (Optional) Maximum number of evaluated expressions
+    /// (default: unlimited)
+    #[arg(long = "max-steps", value_parser = clap::value_parser!(u64).range(1..), value_name = "STEPS")]
+    max_steps: Option<u64>,
+
+    /// (Optional) Maximum length of strings, arrays and hashes
+    /// (default: unlimited)
+    #[arg(long = "max-size", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), value_name = "SIZE")]
+    max_size: Option<usize>,
+
+    /// (Optional) Stops the evaluation after the given number of milliseconds
+    /// (default: no timeout)
+    #[arg(short = 't', long = "timeout", value_name = "MS")]
+    timeout: Option<u64>,
+
+    /// (Optional) Directory searched for imported modules, can be repeated
+    /// (default: only the directory of the importing file)
+    #[arg(short = 'I', long = "module-path", value_name = "DIR")]
+    module_paths: Vec<String>,
+
+    /// (Optional) Name of the REPL session to resume, its history and definitions are saved on the way
+    /// (default: default)
+    #[arg(long = "session", value_name = "NAME")]
+    session: Option<String>,
+
+    /// (Optional) Directory the REPL sessions are saved in
+    /// (default: $XDG_DATA_HOME/monkey/sessions)
+    #[arg(long = "session-dir", value_name = "DIR")]
+    session_dir: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
-enum Commands {}
+enum Commands {
+    /// Runs a file step by step, with breakpoints
+    Debug {
+        /// File to debug
+        file: String,
+    },
+    /// Serves the Debug Adapter Protocol over stdio, for editors
+    Dap,
+    /// Serves the Language Server Protocol over stdio, for editors
+    Lsp,
+    /// Runs code for notebook frontends, reading one JSON request per line on stdin and answering on stdout
+    Kernel,
+    /// Runs the `test_` functions of the `*_test.monkey` files
+    Test {
+        /// Test files, or directories searched for them
+        /// (default: the current directory)
+        paths: Vec<String>,
+    },
+    /// Prints the tokens of a file with their line and column
+    Tokens {
+        /// File to read
+        file: String,
+
+        /// (Optional) Prints the tokens as JSON
+        /// (default: false)
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Prints the program parsed from a file as a tree
+    Ast {
+        /// File to parse
+        file: String,
+
+        /// (Optional) Prints the program as JSON
+        /// (default: false)
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Reports unknown identifiers, wrong calls to builtins and unused or unreachable code without running the files
+    Check {
+        /// Files to check
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Formats files in place, or standard input to standard output
+    Fmt {
+        /// Files to format
+        files: Vec<String>,
+
+        /// (Optional) Only reports the files that are not formatted, and fails if there are any
+        /// (default: false)
+        #[arg(long = "check")]
+        check: bool,
+
+        /// (Optional) Number of spaces per indentation level
+        /// (default: 4)
+        #[arg(long = "indent", value_name = "WIDTH")]
+        indent_width: Option<usize>,
+
+        /// (Optional) Line width lists are broken to fit in
+        /// (default: 100)
+        #[arg(long = "max-width", value_name = "WIDTH")]
+        max_width: Option<usize>,
+    },
+}
 
 fn main() -> Result<(), anyhow::Error> {
     std::env::set_var("RUST_LOG", "info");
@@ -40,21 +144,82 @@ fn main() -> Result<(), anyhow::Error> {
 
     let args = Args::parse();
 
+    // Deep recursion needs more stack than the main thread has
+    evaluator::evaluator::with_evaluation_stack(move || run(args))
+}
+
+fn run(args: Args) -> Result<(), anyhow::Error> {
+    if args.stop_on_error {
+        flags::STOP_AT_FIRST_ERROR.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    if args.print {
+        flags::PRINT_EVALUATED_RESULT.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    if args.no_prelude {
+        flags::NO_PRELUDE.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    *flags::MAX_CALL_DEPTH.lock().unwrap() = args.max_depth;
+
+    *flags::MAX_STEPS.lock().unwrap() = args.max_steps;
+
+    *flags::MAX_COLLECTION_SIZE.lock().unwrap() = args.max_size;
+
+    if let Some(timeout) = args.timeout {
+        flags::TIMEOUT_MS.store(timeout, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    flags::MODULE_PATHS.lock().unwrap().extend(args.module_paths);
+
+    if let Some(session) = args.session {
+        *flags::SESSION_NAME.lock().unwrap() = session;
+    }
+    *flags::SESSION_DIR.lock().unwrap() = args.session_dir;
+
     match args.command {
         Some(command) => match command {
-            _ => {
-                error!("Not implemented yet");
+            Commands::Debug { file } => {
+                if !std::path::Path::new(&file).is_file() {
+                    error!("File {} not found", file);
+                    return Ok(());
+                }
+                repl::debugger::start(&file)?;
             }
-        },
-        None => {
-            if args.stop_on_error {
-                flags::STOP_AT_FIRST_ERROR.store(true, std::sync::atomic::Ordering::Relaxed);
+            Commands::Dap => repl::dap::start()?,
+            Commands::Lsp => repl::lsp::start()?,
+            Commands::Kernel => repl::kernel::start()?,
+            Commands::Test { mut paths } => {
+                if paths.is_empty() {
+                    paths.push(".".to_string());
+                }
+                if !repl::testing::start(&paths)? {
+                    std::process::exit(1);
+                }
             }
-
-            if args.print {
-                flags::PRINT_EVALUATED_RESULT.store(true, std::sync::atomic::Ordering::Relaxed);
+            Commands::Tokens { file, json } => repl::dump::tokens(&file, json)?,
+            Commands::Ast { file, json } => repl::dump::ast(&file, json)?,
+            Commands::Check { files } => {
+                if !repl::check::start(&files)? {
+                    std::process::exit(1);
+                }
             }
+            Commands::Fmt { files, check, indent_width, max_width } => {
+                let mut options = formatter::formatter::FormatOptions::default();
+                if let Some(indent_width) = indent_width {
+                    options.indent_width = indent_width;
+                }
+                if let Some(max_width) = max_width {
+                    options.max_width = max_width;
+                }
 
+                if !repl::fmt::start(&files, check, &options)? {
+                    std::process::exit(1);
+                }
+            }
+        },
+        None => {
             if args.expression.is_some() {
                 let input = args.expression.unwrap();
                 info!("Executing inline input: {}", input);
@@ -65,9 +230,8 @@ fn main() -> Result<(), anyhow::Error> {
                 let file = args.file.unwrap();
                 info!("Executing file: {}", file);
 
-                let content = std::fs::read_to_string(&file);
-                return if content.is_ok() {
-                    repl::interpreter::execute_program(content.unwrap())?;
+                return if std::path::Path::new(&file).is_file() {
+                    repl::interpreter::execute_file(&file)?;
                     Ok(())
                 } else {
                     error!("File {} not found", file);
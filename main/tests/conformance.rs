@@ -0,0 +1,32 @@
+use std::path::{Path, PathBuf};
+
+use conformance::conformance::{cases, check, ProcessBackend, Verdict};
+
+/// Runs every program under `examples/` and `tests/cases/` and compares it to its expected file.
+/// `cargo test --test conformance -- --bless` writes the expected files instead.
+fn main() -> Result<(), anyhow::Error> {
+    let bless = std::env::args().any(|argument| argument == "--bless");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let roots: Vec<PathBuf> = vec![root.join("examples"), root.join("tests/cases")];
+    let backend = ProcessBackend::new(env!("CARGO_BIN_EXE_interpreter"), &["-f"]);
+
+    let (mut passed, mut failed) = (0, 0);
+    for case in cases(&roots)? {
+        let name = case.strip_prefix(&root).unwrap_or(&case).display().to_string();
+        match check(&backend, &case, bless)? {
+            Verdict::Passed => passed += 1,
+            Verdict::Blessed => println!("blessed {}", name),
+            Verdict::Failed(lines) => {
+                println!("FAILED  {}", name);
+                lines.iter().for_each(|line| println!("    {}", line));
+                failed += 1;
+            }
+        }
+    }
+
+    println!("conformance: {} passed, {} failed", passed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+/// Longest wait for a response, a kernel that stops answering fails the test instead of hanging it
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Talks to `interpreter kernel` like a notebook frontend would
+struct Client {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    responses: Receiver<String>,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_interpreter"))
+            .arg("kernel")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("the kernel starts");
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        // Responses are read on another thread so that waiting for them can time out
+        let (sender, responses) = channel();
+        std::thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Client { child, stdin, responses }
+    }
+
+    fn send(&mut self, request: Value) {
+        let stdin = self.stdin.as_mut().unwrap();
+        writeln!(stdin, "{}", request).unwrap();
+        stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let line = self.responses.recv_timeout(TIMEOUT).expect("a response before the timeout");
+        serde_json::from_str(&line).unwrap_or_else(|_| panic!("a JSON response, got {:?}", line))
+    }
+
+    /// Sends `request` and returns its response, the `busy` status of an `execute` is skipped
+    fn request(&mut self, request: Value) -> Value {
+        self.send(request);
+        loop {
+            let response = self.receive();
+            if response["status"] != "busy" {
+                return response;
+            }
+        }
+    }
+
+    /// Closes the input and waits for the kernel to exit, returns whether it succeeded
+    fn close(&mut self) -> bool {
+        self.stdin.take();
+        let start = Instant::now();
+        while start.elapsed() < TIMEOUT {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return status.success();
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the kernel did not exit before the timeout");
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+#[test]
+fn test_execute_keeps_bindings_and_captures_output() {
+    let mut client = Client::start();
+
+    let response = client.request(json!({ "id": 1, "type": "execute", "code": "let double = fn(x) { x * 2 }; println(\"hello\"); double(21)" }));
+    assert_eq!(response, json!({ "id": 1, "status": "ok", "result": "42", "type": "Integer", "output": "hello\n", "error_output": "" }));
+
+    let response = client.request(json!({ "id": 2, "type": "execute", "code": "print(double(1), \" \"); eprint(3); [double(2)]" }));
+    assert_eq!(response, json!({ "id": 2, "status": "ok", "result": "[4]", "type": "Array", "output": "2 ", "error_output": "3" }));
+
+    let response = client.request(json!({ "id": 3, "type": "execute", "code": "println(1); let f = fn() { [][1] }; f()" }));
+    assert_eq!(response["status"], "error");
+    assert_eq!(response["output"], "1\n");
+    assert_eq!(response["error"]["kind"], "IndexOutOfBounds");
+    assert!(response["error"]["backtrace"].as_str().unwrap().contains("at f"), "{}", response);
+
+    let response = client.request(json!({ "id": 4, "type": "execute", "code": "let = 1" }));
+    assert_eq!(response["status"], "error");
+    assert_eq!(response["error"]["kind"], "UnexpectedToken");
+
+    // Sessions do not share their bindings
+    let response = client.request(json!({ "id": 5, "type": "execute", "session": "other", "code": "double" }));
+    assert_eq!(response["error"]["kind"], "UnknownIdentifier");
+
+    let response = client.request(json!({ "id": 6, "type": "shutdown" }));
+    assert_eq!(response, json!({ "id": 6, "status": "ok" }));
+    assert!(client.close());
+}
+
+#[test]
+fn test_complete_and_inspect() {
+    let mut client = Client::start();
+    client.request(json!({ "id": 1, "type": "execute", "code": "let length = 3;" }));
+
+    let response = client.request(json!({ "id": 2, "type": "complete", "code": "1 + le", "cursor": 6 }));
+    assert_eq!(response, json!({ "id": 2, "status": "ok", "matches": ["len", "length", "let"], "cursor_start": 4, "cursor_end": 6 }));
+
+    let response = client.request(json!({ "id": 3, "type": "inspect", "code": "length + 1", "cursor": 2 }));
+    assert_eq!(response, json!({ "id": 3, "status": "ok", "found": true, "name": "length", "type": "Integer", "value": "3" }));
+
+    let response = client.request(json!({ "id": 4, "type": "inspect", "code": "push(a, 1)", "cursor": 4 }));
+    assert_eq!(response["signature"], "push(array, value)");
+
+    let response = client.request(json!({ "id": 5, "type": "inspect", "code": "nothing" }));
+    assert_eq!(response["found"], false);
+
+    let response = client.request(json!({ "id": 6, "type": "unknown" }));
+    assert_eq!(response["error"]["kind"], "InvalidRequest");
+    let response = client.request(json!("not a request"));
+    assert_eq!(response["error"]["kind"], "InvalidRequest");
+}
+
+#[test]
+fn test_interrupt_stops_the_running_execution() {
+    let mut client = Client::start();
+    let forever = "let forever = fn(n) { forever(n + 1) }; forever(0)";
+
+    // The `busy` status tells that the evaluation started
+    client.send(json!({ "id": 1, "type": "execute", "code": forever }));
+    assert_eq!(client.receive(), json!({ "id": 1, "status": "busy" }));
+    let response = client.request(json!({ "id": 2, "type": "interrupt" }));
+    assert_eq!(response, json!({ "id": 2, "status": "ok" }));
+
+    let response = client.receive();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["error"]["kind"], "Interrupted");
+
+    // An interrupt sent before the evaluation started is not lost, whether it was waiting or loading its session
+    client.send(json!({ "id": 3, "type": "execute", "session": "other", "code": forever }));
+    client.send(json!({ "id": 4, "type": "interrupt" }));
+    let mut responses = [client.receive(), client.receive(), client.receive()];
+    responses.sort_by_key(|response| response["id"].as_i64());
+    assert_eq!(responses[0], json!({ "id": 3, "status": "busy" }));
+    assert_eq!(responses[1]["error"]["kind"], "Interrupted");
+    assert_eq!(responses[2], json!({ "id": 4, "status": "ok" }));
+
+    // The session is still usable, the input closing stops the kernel
+    let response = client.request(json!({ "id": 5, "type": "execute", "code": "1 + 1" }));
+    assert_eq!(response["result"], "2");
+    assert!(client.close());
+}
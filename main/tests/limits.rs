@@ -0,0 +1,21 @@
+use std::process::Command;
+
+/// The prelude is loaded outside of the limits given to the program
+#[test]
+fn test_small_step_limit_still_runs_the_program() {
+    let output = Command::new(env!("CARGO_BIN_EXE_interpreter")).args(["--max-steps", "20", "-e", "1"]).output().unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("StepLimitExceeded"));
+}
+
+/// A limit of 0 is not taken for the default
+#[test]
+fn test_zero_limits_are_rejected() {
+    for flag in ["--max-depth", "--max-steps", "--max-size"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_interpreter")).args([flag, "0", "-e", "1"]).output().unwrap();
+
+        assert!(!output.status.success(), "{}", flag);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("0 is not in 1.."), "{}", flag);
+    }
+}
@@ -1 +1,4 @@
-pub mod parser;
\ No newline at end of file
+pub mod parser;
+
+#[cfg(test)]
+mod round_trip;
@@ -4,6 +4,7 @@ use log::{debug};
 
 use ast::expression::Expression;
 use ast::expression::Expression::{BooleanLiteral, IntegerLiteral, StringLiteral};
+use ast::location::{Location, MAIN_SOURCE};
 use ast::program::Program;
 use ast::statement::{BlockStatement, Statement};
 use error::EvaluatorError;
@@ -12,11 +13,18 @@ use lexer::lexer::Lexer;
 use lexer::precedence::Precedence;
 use lexer::token::{Token, TokenType};
 
+/// Deepest expression the parser accepts, deeper ones would overflow the stack of the tools walking the tree
+pub const MAX_NESTING: usize = 256;
+
 #[derive(Debug)]
 pub struct Parser {
     pub lexer: Lexer,
     pub cur_token: Token,
     pub peek_token: Token,
+    /// Source recorded in the locations of the parsed statements
+    pub source: u32,
+    /// Nesting of the expression being parsed
+    depth: usize,
 }
 
 impl Parser {
@@ -25,6 +33,8 @@ impl Parser {
             lexer,
             cur_token: Token::default(),
             peek_token: Token::default(),
+            source: MAIN_SOURCE,
+            depth: 0,
         };
 
         // Read two tokens so cur_token and peek_token are defined
@@ -54,6 +64,10 @@ impl Parser {
                                        self.cur_token.column)
     }
 
+    fn location(&self) -> Location {
+        return Location::in_source(self.cur_token.line, self.cur_token.column, self.source);
+    }
+
     fn peek_precedence(&self) -> Precedence {
         self.peek_token.to_precedence()
     }
@@ -105,18 +119,22 @@ impl Parser {
         match &self.cur_token.kind {
             TokenType::LET => self.parse_let_statement(),
             TokenType::RETURN => self.parse_return_statement(),
+            TokenType::THROW => self.parse_throw_statement(),
+            TokenType::IMPORT => self.parse_import_statement(),
+            TokenType::EXPORT => self.parse_export_statement(),
             TokenType::SEMICOLON => Ok(Statement::EmptyStatement),
             _ => self.parse_expression_statement(),
         }
     }
 
     fn parse_let_statement(&mut self) -> Result<Statement, EvaluatorError> {
+        let location = self.location();
         if !matches!(&self.peek_token.kind, TokenType::IDENT(_)) {
             return Err(self.expected_error_peek("IDENT".to_string()));
         }
         self.next_token()?; // (peek) Skip past the LET
 
-        let identifier = self.parse_indent().unwrap().to_string();
+        let identifier = self.parse_indent()?.to_string();
 
 
         if !matches!(self.peek_token.kind, TokenType::ASSIGN) {
@@ -137,10 +155,12 @@ impl Parser {
         Ok(Statement::LetStatement {
             identifier,
             value: value.unwrap(),
+            location,
         })
     }
 
     fn parse_return_statement(&mut self) -> Result<Statement, EvaluatorError> {
+        let location = self.location();
         self.next_token()?; // (peek) Skip past the RETURN
 
         let value = self.parse_expression(&Precedence::LOWEST);
@@ -155,10 +175,87 @@ impl Parser {
 
         Ok(Statement::ReturnStatement {
             value: value.unwrap(),
+            location,
+        })
+    }
+
+    fn parse_throw_statement(&mut self) -> Result<Statement, EvaluatorError> {
+        let location = self.location();
+        self.next_token()?; // (peek) Skip past the THROW
+
+        let value = self.parse_expression(&Precedence::LOWEST)?;
+
+        if !matches!(self.peek_token.kind, TokenType::SEMICOLON) {
+            return Err(self.expected_error_peek(TokenType::SEMICOLON.to_string()));
+        }
+
+        Ok(Statement::ThrowStatement {
+            value,
+            location,
+        })
+    }
+
+    fn parse_import_statement(&mut self) -> Result<Statement, EvaluatorError> {
+        let location = self.location();
+        let mut names = None;
+
+        if matches!(&self.peek_token.kind, TokenType::LBRACE) {
+            self.next_token()?; // (peek) Skip past the IMPORT
+            let mut identifiers = vec![];
+
+            while !matches!(&self.peek_token.kind, TokenType::RBRACE) {
+                self.next_token()?; // (curr) Skip past the LBRACE or COMMA
+                if let TokenType::IDENT(ident) = &self.cur_token.kind {
+                    identifiers.push(ident.clone());
+                } else {
+                    return Err(self.expected_error_curr("identifier".to_string()));
+                }
+
+                if !matches!(&self.peek_token.kind, TokenType::COMMA | TokenType::RBRACE) {
+                    return Err(self.expected_error_peek(",".to_string()));
+                }
+                if matches!(&self.peek_token.kind, TokenType::COMMA) {
+                    self.next_token()?; // (peek) Skip past the identifier
+                }
+            }
+            self.next_token()?; // (peek) Skip past the last identifier
+
+            // `from` is not reserved, it can still name a binding
+            if !matches!(&self.peek_token.kind, TokenType::IDENT(ident) if ident == "from") {
+                return Err(self.expected_error_peek("from".to_string()));
+            }
+            self.next_token()?; // (peek) Skip past the RBRACE
+            names = Some(identifiers);
+        }
+
+        let path = match &self.peek_token.kind {
+            TokenType::STRING(path) => path.clone(),
+            _ => return Err(self.expected_error_peek("STRING".to_string())),
+        };
+        self.next_token()?; // (peek) Skip past the IMPORT or FROM
+
+        Ok(Statement::ImportStatement {
+            path,
+            names,
+            location,
+        })
+    }
+
+    fn parse_export_statement(&mut self) -> Result<Statement, EvaluatorError> {
+        if !matches!(&self.peek_token.kind, TokenType::LET) {
+            return Err(self.expected_error_peek(TokenType::LET.to_string()));
+        }
+        self.next_token()?; // (peek) Skip past the EXPORT
+
+        let statement = self.parse_let_statement()?;
+
+        Ok(Statement::ExportStatement {
+            statement: Box::new(statement),
         })
     }
 
     fn parse_expression_statement(&mut self) -> Result<Statement, EvaluatorError> {
+        let location = self.location();
         let exp = self.parse_expression(&Precedence::LOWEST);
 
         if exp.is_err() {
@@ -169,11 +266,13 @@ impl Parser {
             self.next_token()?; // (cur_token) Skip past the SEMICOLON
         }
 
-        Ok(Statement::ExpressionStatement(exp.unwrap()))
+        Ok(Statement::ExpressionStatement(exp.unwrap(), location))
     }
 
     fn parse_block_statement(&mut self) -> Result<BlockStatement, EvaluatorError> {
         let mut statements: BlockStatement = vec![];
+        // Blocks are only parsed within an expression, which restores the nesting
+        self.enter_nesting()?;
 
         self.next_token()?; // (cur_token) Skip past the LBRACE
 
@@ -193,6 +292,24 @@ impl Parser {
     // Expressions
 
     fn parse_expression(&mut self, precedence: &Precedence) -> Result<Expression, EvaluatorError> {
+        let depth = self.depth;
+        let expression = self.parse_nested_expression(precedence);
+        self.depth = depth;
+        expression
+    }
+
+    /// Counts a level of nesting, for each sub-expression and block. The operands of a chain like `a + b + c`
+    /// are not nested in each other, they do not count.
+    fn enter_nesting(&mut self) -> Result<(), EvaluatorError> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(EvaluatorError::nesting_too_deep(MAX_NESTING, self.cur_token.line, self.cur_token.column));
+        }
+        Ok(())
+    }
+
+    fn parse_nested_expression(&mut self, precedence: &Precedence) -> Result<Expression, EvaluatorError> {
+        self.enter_nesting()?;
         let left_expression = match &self.cur_token.kind {
             TokenType::INT(_) => self.parse_int_literal(),
             TokenType::STRING(_) => self.parse_string_literal(),
@@ -201,6 +318,7 @@ impl Parser {
             TokenType::TRUE | TokenType::FALSE => self.parse_boolean_literal(),
             TokenType::LPAREN => self.parse_grouped_expression(),
             TokenType::IF => self.parse_if_expression(),
+            TokenType::TRY => self.parse_try_expression(),
             TokenType::FUNCTION => self.parse_function_literal(),
             TokenType::LBRACKET => self.parse_array_literal(),
             TokenType::LBRACE => self.parse_hash_literal(),
@@ -336,6 +454,65 @@ impl Parser {
         })
     }
 
+    fn parse_try_expression(&mut self) -> Result<Expression, EvaluatorError> {
+        if !matches!(&self.peek_token.kind, TokenType::LBRACE) {
+            return Err(self.expected_error_peek("{".to_string()));
+        }
+        self.next_token()?; // (peek) Skip past the TRY
+
+        let body = self.parse_block_statement()?;
+
+        let mut catch = None;
+        if matches!(&self.peek_token.kind, TokenType::CATCH) {
+            self.next_token()?; // (peek) Skip past the RBRACE
+
+            if !matches!(&self.peek_token.kind, TokenType::LPAREN) {
+                return Err(self.expected_error_peek("(".to_string()));
+            }
+            self.next_token()?; // (peek) Skip past the CATCH
+
+            let parameter = match &self.peek_token.kind {
+                TokenType::IDENT(identifier) => identifier.clone(),
+                _ => return Err(self.expected_error_peek("IDENT".to_string())),
+            };
+            self.next_token()?; // (peek) Skip past the LPAREN
+
+            if !matches!(&self.peek_token.kind, TokenType::RPAREN) {
+                return Err(self.expected_error_peek(")".to_string()));
+            }
+            self.next_token()?; // (peek) Skip past the identifier
+
+            if !matches!(&self.peek_token.kind, TokenType::LBRACE) {
+                return Err(self.expected_error_peek("{".to_string()));
+            }
+            self.next_token()?; // (peek) Skip past the RPAREN
+
+            catch = Some((parameter, self.parse_block_statement()?));
+        }
+
+        let mut finally = None;
+        if matches!(&self.peek_token.kind, TokenType::FINALLY) {
+            self.next_token()?; // (peek) Skip past the RBRACE
+
+            if !matches!(&self.peek_token.kind, TokenType::LBRACE) {
+                return Err(self.expected_error_peek("{".to_string()));
+            }
+            self.next_token()?; // (peek) Skip past the FINALLY
+
+            finally = Some(self.parse_block_statement()?);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.expected_error_peek(TokenType::CATCH.to_string()));
+        }
+
+        Ok(Expression::TryExpression {
+            body,
+            catch,
+            finally,
+        })
+    }
+
     fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, EvaluatorError> {
         let operator = self.cur_token.kind.to_string();
 
@@ -531,7 +708,7 @@ mod tests {
 
     fn asset_let_statement(statement: &Statement, ident: &str, exp: &Expression) {
         match &statement {
-            Statement::LetStatement { identifier, value } => {
+            Statement::LetStatement { identifier, value, .. } => {
                 assert_eq!(identifier, ident);
                 assert_eq!(value, exp);
             }
@@ -541,7 +718,7 @@ mod tests {
 
     fn asset_return_statement(statement: &Statement, exp: &Expression) {
         match &statement {
-            Statement::ReturnStatement { value } => {
+            Statement::ReturnStatement { value, .. } => {
                 assert_eq!(value, exp);
             }
             _ => assert!(false, "Expected ReturnStatement, got {:?}", statement),
@@ -550,7 +727,7 @@ mod tests {
 
     fn asset_expression_statement(statement: &Statement, exp: &Expression) {
         match statement {
-            Statement::ExpressionStatement(data) => {
+            Statement::ExpressionStatement(data, _) => {
                 assert_eq!(data, exp);
             }
             _ => assert!(false, "Expected ExpressionStatement, got {:?}", statement),
@@ -559,7 +736,7 @@ mod tests {
 
     fn asset_prefix_expression(statement: &Statement, op: &str, exp: &Expression) {
         match statement {
-            Statement::ExpressionStatement(data) => {
+            Statement::ExpressionStatement(data, _) => {
                 match data {
                     PrefixExpression { operator, right } => {
                         assert_eq!(operator, op);
@@ -574,7 +751,7 @@ mod tests {
 
     fn assert_infix_expression(statement: &Statement, left: &Expression, op: &str, right: &Expression) {
         match statement {
-            Statement::ExpressionStatement(data) => {
+            Statement::ExpressionStatement(data, _) => {
                 match data {
                     InfixExpression { operator, left: l, right: r } => {
                         assert_eq!(operator, op);
@@ -821,9 +998,9 @@ if (x < 3 * y) { x + 1; } else { y }
 
         assert_eq!(program.statements.len(), 3);
 
-        assert_eq!(&program.statements[0].to_string(), "if (x < y) { x; }");
-        assert_eq!(&program.statements[1].to_string(), "if (x < (2 * y)) { (x + 1); }");
-        assert_eq!(&program.statements[2].to_string(), "if (x < (3 * y)) { (x + 1); } else { y; }");
+        assert_eq!(&program.statements[0].to_string(), "if (x < y) { x; };");
+        assert_eq!(&program.statements[1].to_string(), "if (x < (2 * y)) { (x + 1); };");
+        assert_eq!(&program.statements[2].to_string(), "if (x < (3 * y)) { (x + 1); } else { y; };");
     }
 
     #[test]
@@ -847,8 +1024,8 @@ fn(x, y) { x + y; }
 
         assert_eq!(program.statements.len(), 2);
 
-        assert_eq!(&program.statements[0].to_string(), "fn() { (x + y); }");
-        assert_eq!(&program.statements[1].to_string(), "fn(x, y) { (x + y); }");
+        assert_eq!(&program.statements[0].to_string(), "fn() { (x + y); };");
+        assert_eq!(&program.statements[1].to_string(), "fn(x, y) { (x + y); };");
     }
 
     #[test]
@@ -954,6 +1131,117 @@ add(a + b + c * d / f + g);
         assert_eq!(&program.statements[1].to_string(), "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])));");
     }
 
+    #[test]
+    fn test_modules() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let input = r#"
+        import "lib.monkey";
+        import { map, reduce } from "./functional.monkey"
+        import {} from "empty.monkey";
+        export let a = 1;
+"#;
+
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer).unwrap();
+        let program = parser.parse_program();
+
+        assert!(program.is_ok());
+
+        let program = program.unwrap();
+
+        assert_eq!(program.statements.len(), 4);
+
+        assert_eq!(program.statements[0], Statement::ImportStatement { path: "lib.monkey".to_string(), names: None, location: Location::new(2, 9) });
+        assert_eq!(&program.statements[1].to_string(), r#"import { map, reduce } from "./functional.monkey";"#);
+        assert_eq!(&program.statements[2].to_string(), r#"import {  } from "empty.monkey";"#);
+        assert_eq!(&program.statements[3].to_string(), "export let a = 1;");
+
+        let program = Parser::new(Lexer::new("import { from } from \"lib\"; let to = from;".to_string())).unwrap().parse_program().unwrap();
+        assert_eq!(&program.statements[0].to_string(), r#"import { from } from "lib";"#);
+
+        let errors = vec![
+            ("import a;", EvaluatorError::expected_token("STRING", "IDENT: a", 1, 8)),
+            ("import { a b } from \"lib\";", EvaluatorError::expected_token(",", "IDENT: b", 1, 12)),
+            ("import { a } \"lib\";", EvaluatorError::expected_token("from", "STRING: lib", 1, 14)),
+            ("export 5;", EvaluatorError::expected_token("LET", "INT: 5", 1, 8)),
+        ];
+
+        for (input, error) in errors {
+            let mut parser = Parser::new(Lexer::new(input.to_string())).unwrap();
+            assert_eq!(parser.parse_program().err().unwrap()[0], error);
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        // A function literal and its body are two levels
+        let functions = |depth: usize| format!("{}{}", "fn() { ".repeat(depth), "}".repeat(depth));
+        // Chains are not nested, however long they are
+        let chain = format!("1{}", " + 1".repeat(300));
+        let concatenation = format!("\"a\"{}", " + \"b\"".repeat(260));
+
+        for input in [nested(MAX_NESTING - 1), format!("[{}]", nested(MAX_NESTING - 2)), functions(MAX_NESTING / 2), chain, concatenation] {
+            assert!(Parser::new(Lexer::new(input.clone())).unwrap().parse_program().is_ok(), "{}", input);
+        }
+
+        let errors = vec![
+            (nested(MAX_NESTING), EvaluatorError::nesting_too_deep(MAX_NESTING, 1, 257)),
+            (functions(MAX_NESTING / 2 + 1), EvaluatorError::nesting_too_deep(MAX_NESTING, 1, 897)),
+        ];
+
+        for (input, error) in errors {
+            let mut parser = Parser::new(Lexer::new(input.to_string())).unwrap();
+            assert_eq!(parser.parse_program().err().unwrap()[0], error);
+        }
+    }
+
+    #[test]
+    fn test_exceptions() {
+        std::env::set_var("RUST_LOG", "trace");
+        let _ = env_logger::try_init();
+
+        let tests = vec![
+            (r#"throw "error";"#, r#"throw "error";"#),
+            ("try { f(); } catch (e) { e }", "try { f(); } catch (e) { e; };"),
+            ("try { f(); } finally { g() }", "try { f(); } finally { g(); };"),
+            ("try { f(); } catch (e) { 1 } finally { 2 }", "try { f(); } catch (e) { 1; } finally { 2; };"),
+            ("let a = try { 1 } catch (e) { 2 };", "let a = try { 1; } catch (e) { 2; };"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input.to_string())).unwrap();
+            assert_eq!(parser.parse_program().unwrap().to_string(), expected);
+        }
+
+        let errors = vec![
+            ("try { 1 }", EvaluatorError::expected_token("CATCH", "EOF", 1, 10)),
+            ("try { 1 } catch { 2 }", EvaluatorError::expected_token("(", "{", 1, 17)),
+            ("try { 1 } catch (1) { 2 }", EvaluatorError::expected_token("IDENT", "INT: 1", 1, 18)),
+            ("throw 1", EvaluatorError::expected_token(";", "EOF", 1, 8)),
+        ];
+
+        for (input, error) in errors {
+            let mut parser = Parser::new(Lexer::new(input.to_string())).unwrap();
+            assert_eq!(parser.parse_program().err().unwrap()[0], error, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_locations() {
+        let input = "let a = 1;\n  a + 1;\n\nreturn a;";
+
+        let mut parser = Parser::new(Lexer::new(input.to_string())).unwrap();
+        let program = parser.parse_program().unwrap();
+        let locations = program.statements.iter()
+            .map(|statement| statement.location().map(|location| (location.line, location.column)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(locations, vec![Some((1, 1)), Some((2, 3)), Some((4, 1))]);
+    }
+
     #[test]
     fn test_hash_literal() {
         std::env::set_var("RUST_LOG", "trace");
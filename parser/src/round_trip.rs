@@ -0,0 +1,104 @@
+//! Property test: printing a tree with `Display` and parsing it again gives back the same tree
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+
+use ast::expression::Expression;
+use ast::location::Location;
+use ast::statement::{without_locations, BlockStatement, Statement};
+use lexer::lexer::Lexer;
+
+use crate::parser::Parser;
+
+const KEYWORDS: [&str; 14] = ["fn", "let", "true", "false", "if", "else", "return", "import", "export", "from", "try", "catch", "finally", "throw"];
+const PREFIX_OPERATORS: [&str; 3] = ["!", "-", "+"];
+const INFIX_OPERATORS: [&str; 10] = ["+", "-", "*", "/", "<", "<=", ">", ">=", "==", "!="];
+
+fn identifier() -> impl Strategy<Value=String> {
+    "[a-zA-Z_][a-zA-Z0-9_]{0,5}".prop_filter("keywords are not identifiers", |name| !KEYWORDS.contains(&name.as_str()))
+}
+
+/// Any text the lexer can read in a string literal, which ends at a NUL character
+fn string() -> impl Strategy<Value=String> {
+    let character = prop_oneof![
+        any::<char>().prop_filter("NUL ends the input", |ch| *ch != '\0'),
+        prop::sample::select(vec!['"', '\'', '\\', '\n', '\t', '\r', 'n']),
+    ];
+    vec(character, 0..6).prop_map(|characters| characters.into_iter().collect())
+}
+
+fn statement(expression: BoxedStrategy<Expression>) -> impl Strategy<Value=Statement> {
+    prop_oneof![
+        3 => expression.clone().prop_map(|value| Statement::ExpressionStatement(value, Location::default())),
+        1 => (identifier(), expression.clone()).prop_map(|(identifier, value)| Statement::LetStatement { identifier, value, location: Location::default() }),
+        1 => expression.clone().prop_map(|value| Statement::ReturnStatement { value, location: Location::default() }),
+        1 => expression.prop_map(|value| Statement::ThrowStatement { value, location: Location::default() }),
+    ]
+}
+
+fn block(expression: BoxedStrategy<Expression>) -> impl Strategy<Value=BlockStatement> {
+    vec(statement(expression), 0..3)
+}
+
+/// Trees the parser can produce: no `null` literal, no grouping node and no negative integer literal
+fn expression() -> impl Strategy<Value=Expression> {
+    let leaf = prop_oneof![
+        (0..=i64::MAX).prop_map(Expression::IntegerLiteral),
+        string().prop_map(Expression::StringLiteral),
+        any::<bool>().prop_map(Expression::BooleanLiteral),
+        identifier().prop_map(Expression::Identifier),
+    ];
+
+    leaf.prop_recursive(4, 32, 3, |inner| {
+        let inner = inner.boxed();
+        prop_oneof![
+            vec(inner.clone(), 0..3).prop_map(Expression::ArrayLiteral),
+            vec((inner.clone(), inner.clone()), 0..3).prop_map(Expression::HashLiteral),
+            (prop::sample::select(PREFIX_OPERATORS.to_vec()), inner.clone()).prop_map(|(operator, right)| {
+                Expression::PrefixExpression { operator: operator.to_string(), right: Box::new(right) }
+            }),
+            (inner.clone(), prop::sample::select(INFIX_OPERATORS.to_vec()), inner.clone()).prop_map(|(left, operator, right)| {
+                Expression::InfixExpression { left: Box::new(left), operator: operator.to_string(), right: Box::new(right) }
+            }),
+            (inner.clone(), block(inner.clone()), option::of(block(inner.clone()))).prop_map(|(condition, consequence, alternative)| {
+                Expression::IfExpression { condition: Box::new(condition), consequence, alternative }
+            }),
+            (vec(identifier(), 0..3), block(inner.clone())).prop_map(|(parameters, body)| {
+                Expression::FunctionLiteral { parameters: parameters.into_iter().map(Expression::Identifier).collect(), body }
+            }),
+            (inner.clone(), vec(inner.clone(), 0..3)).prop_map(|(function, arguments)| {
+                Expression::CallExpression { function: Box::new(function), arguments }
+            }),
+            (inner.clone(), inner.clone()).prop_map(|(left, index)| Expression::IndexExpression { left: Box::new(left), index: Box::new(index) }),
+            (block(inner.clone()), option::of((identifier(), block(inner.clone()))), option::of(block(inner)))
+                .prop_filter("a try has a catch or a finally", |(_, catch, finally)| catch.is_some() || finally.is_some())
+                .prop_map(|(body, catch, finally)| Expression::TryExpression { body, catch, finally }),
+        ]
+    })
+}
+
+/// Top level statements, which can also be imports and exports
+fn program() -> impl Strategy<Value=Vec<Statement>> {
+    let top_level = prop_oneof![
+        4 => statement(expression().boxed()),
+        1 => (string(), option::of(vec(identifier(), 0..3))).prop_map(|(path, names)| Statement::ImportStatement { path, names, location: Location::default() }),
+        1 => (identifier(), expression()).prop_map(|(identifier, value)| Statement::ExportStatement {
+            statement: Box::new(Statement::LetStatement { identifier, value, location: Location::default() }),
+        }),
+    ];
+    vec(top_level, 1..4)
+}
+
+fn print(statements: &[Statement]) -> String {
+    statements.iter().map(|statement| statement.to_string()).collect()
+}
+
+proptest! {
+    #[test]
+    fn test_print_then_parse(statements in program()) {
+        let source = print(&statements);
+        let parsed = Parser::new(Lexer::new(source.clone())).and_then(|mut parser| parser.parse_program().map_err(|errors| errors[0].clone()));
+        prop_assert_eq!(parsed.map(|program| without_locations(&program.statements)), Ok(statements), "{}", source);
+    }
+}
@@ -0,0 +1,278 @@
+use lexer::lexer::Lexer;
+use lexer::token::{Token, TokenType};
+
+/// Characters of a token on a line, columns start at 1 and `end` is excluded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Span {
+    pub(crate) line: u32,
+    pub(crate) start: u32,
+    pub(crate) end: u32,
+}
+
+impl Span {
+    fn contains(&self, line: u32, column: u32) -> bool {
+        self.line == line && self.start <= column && column <= self.end
+    }
+
+    fn position(&self) -> (u32, u32) {
+        (self.line, self.start)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SymbolKind {
+    Let,
+    Parameter,
+    Import,
+    Catch,
+}
+
+/// A name bound by `let`, a function parameter, an `import` or a `catch`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Symbol {
+    pub(crate) name: String,
+    pub(crate) kind: SymbolKind,
+    pub(crate) span: Span,
+    /// Parameters of the function literal a `let` binds
+    pub(crate) parameters: Option<Vec<String>>,
+    scope: usize,
+}
+
+/// An identifier used in an expression, with the symbol it refers to when it is bound in the file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Reference {
+    pub(crate) name: String,
+    pub(crate) span: Span,
+    pub(crate) symbol: Option<usize>,
+    scope: usize,
+}
+
+/// Braces of a block, a function body or a hash literal, the first scope is the whole file
+#[derive(Debug, Clone, PartialEq)]
+struct Scope {
+    parent: Option<usize>,
+    start: (u32, u32),
+    end: (u32, u32),
+}
+
+/// Bindings and their uses in a source, found from its tokens so it also works on programs that do not parse
+#[derive(Debug, Default)]
+pub(crate) struct Analysis {
+    pub(crate) symbols: Vec<Symbol>,
+    pub(crate) references: Vec<Reference>,
+    scopes: Vec<Scope>,
+}
+
+/// Span of an identifier, from its first column to the column following it
+fn span(token: &Token, name: &str) -> Span {
+    Span { line: token.line, start: token.column, end: token.column + name.len() as u32 }
+}
+
+fn tokens(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut tokens = vec![];
+    // A lexer error stops the analysis, the tokens read before are kept
+    while let Ok(token) = lexer.next_token() {
+        if token.kind == TokenType::EOF {
+            break;
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+impl Analysis {
+    pub(crate) fn new(source: &str) -> Self {
+        let tokens = tokens(source);
+        let mut analysis = Self {
+            scopes: vec![Scope { parent: None, start: (0, 0), end: (u32::MAX, u32::MAX) }],
+            ..Self::default()
+        };
+
+        let mut stack = vec![0];
+        // Parameters declared by the `fn` or `catch` whose body starts at the next brace
+        let mut pending: Vec<(String, Span, SymbolKind)> = vec![];
+        let mut index = 0;
+
+        let identifier = |index: usize| match tokens.get(index).map(|token| &token.kind) {
+            Some(TokenType::IDENT(name)) => Some(name.clone()),
+            _ => None,
+        };
+        let is = |index: usize, kind: TokenType| tokens.get(index).map(|token| token.kind == kind).unwrap_or(false);
+
+        while index < tokens.len() {
+            let token = &tokens[index];
+            let scope = *stack.last().unwrap();
+
+            match &token.kind {
+                TokenType::FUNCTION | TokenType::CATCH if is(index + 1, TokenType::LPAREN) => {
+                    let kind = if token.kind == TokenType::FUNCTION { SymbolKind::Parameter } else { SymbolKind::Catch };
+                    index += 2;
+                    while index < tokens.len() && !is(index, TokenType::RPAREN) {
+                        if let Some(name) = identifier(index) {
+                            pending.push((name.clone(), span(&tokens[index], &name), kind));
+                        }
+                        index += 1;
+                    }
+                }
+                TokenType::LET => {
+                    if let Some(name) = identifier(index + 1) {
+                        let parameters = if is(index + 2, TokenType::ASSIGN) && is(index + 3, TokenType::FUNCTION) && is(index + 4, TokenType::LPAREN) {
+                            let closing = (index + 5..tokens.len()).find(|i| is(*i, TokenType::RPAREN)).unwrap_or(tokens.len());
+                            Some((index + 5..closing).filter_map(identifier).collect())
+                        } else {
+                            None
+                        };
+                        analysis.symbols.push(Symbol { name: name.clone(), kind: SymbolKind::Let, span: span(&tokens[index + 1], &name), parameters, scope });
+                        index += 1;
+                    }
+                }
+                TokenType::IMPORT if is(index + 1, TokenType::LBRACE) => {
+                    index += 2;
+                    while index < tokens.len() && !is(index, TokenType::RBRACE) {
+                        if let Some(name) = identifier(index) {
+                            analysis.symbols.push(Symbol { name: name.clone(), kind: SymbolKind::Import, span: span(&tokens[index], &name), parameters: None, scope });
+                        }
+                        index += 1;
+                    }
+                    // `from` is an identifier token, not a reference
+                    if identifier(index + 1).as_deref() == Some("from") {
+                        index += 1;
+                    }
+                }
+                TokenType::LBRACE => {
+                    analysis.scopes.push(Scope { parent: Some(scope), start: (token.line, token.column), end: (u32::MAX, u32::MAX) });
+                    let inner = analysis.scopes.len() - 1;
+                    stack.push(inner);
+                    for (name, span, kind) in pending.drain(..) {
+                        analysis.symbols.push(Symbol { name, kind, span, parameters: None, scope: inner });
+                    }
+                }
+                TokenType::RBRACE if stack.len() > 1 => {
+                    let closed = stack.pop().unwrap();
+                    analysis.scopes[closed].end = (token.line, token.column);
+                }
+                TokenType::IDENT(name) => {
+                    analysis.references.push(Reference { name: name.clone(), span: span(token, name), symbol: None, scope });
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+
+        for reference in 0..analysis.references.len() {
+            analysis.references[reference].symbol = analysis.resolve(&analysis.references[reference]);
+        }
+        analysis
+    }
+
+    fn scope_chain(&self, scope: usize) -> Vec<usize> {
+        let mut chain = vec![scope];
+        while let Some(parent) = self.scopes[*chain.last().unwrap()].parent {
+            chain.push(parent);
+        }
+        chain
+    }
+
+    /// The innermost binding made before the use, or one made later since functions can call the ones declared after them
+    fn resolve(&self, reference: &Reference) -> Option<usize> {
+        let chain = self.scope_chain(reference.scope);
+        let candidates = |scope: usize| self.symbols.iter().enumerate()
+            .filter(move |(_, symbol)| symbol.scope == scope && symbol.name == reference.name);
+
+        for scope in &chain {
+            let before = candidates(*scope).rfind(|(_, symbol)| symbol.span.position() < reference.span.position());
+            if let Some((index, _)) = before {
+                return Some(index);
+            }
+        }
+        for scope in &chain {
+            if let Some((index, _)) = candidates(*scope).next() {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Symbol declared or used at the position
+    pub(crate) fn symbol_at(&self, line: u32, column: u32) -> Option<usize> {
+        if let Some(index) = self.symbols.iter().position(|symbol| symbol.span.contains(line, column)) {
+            return Some(index);
+        }
+        self.reference_at(line, column).and_then(|reference| reference.symbol)
+    }
+
+    pub(crate) fn reference_at(&self, line: u32, column: u32) -> Option<&Reference> {
+        self.references.iter().find(|reference| reference.span.contains(line, column))
+    }
+
+    /// Uses of a symbol, in the order of the source
+    pub(crate) fn references_to(&self, symbol: usize) -> Vec<Span> {
+        self.references.iter().filter(|reference| reference.symbol == Some(symbol)).map(|reference| reference.span).collect()
+    }
+
+    /// Symbols usable at the position, an inner binding hides the outer ones with the same name
+    pub(crate) fn visible_at(&self, line: u32, column: u32) -> Vec<&Symbol> {
+        let scope = (0..self.scopes.len()).rev()
+            .find(|index| self.scopes[*index].start < (line, column) && (line, column) <= self.scopes[*index].end)
+            .unwrap_or(0);
+
+        let mut visible: Vec<&Symbol> = vec![];
+        for scope in self.scope_chain(scope) {
+            for symbol in self.symbols.iter().filter(|symbol| symbol.scope == scope && symbol.span.position() < (line, column)) {
+                if !visible.iter().any(|other| other.name == symbol.name) {
+                    visible.push(symbol);
+                }
+            }
+        }
+        visible
+    }
+
+    /// `let` bindings made outside of any block
+    pub(crate) fn top_level(&self) -> impl Iterator<Item=&Symbol> {
+        self.symbols.iter().filter(|symbol| symbol.scope == 0 && symbol.kind == SymbolKind::Let)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution() {
+        let analysis = Analysis::new("let x = 1;
+let f = fn(x, y) { x + y + g() };
+let g = fn() { x };
+try { f(x, 2) } catch (e) { e }");
+
+        let names = analysis.symbols.iter().map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.span.line, symbol.span.start)).collect::<Vec<_>>();
+        assert_eq!(names, vec![
+            ("x", SymbolKind::Let, 1, 5),
+            ("f", SymbolKind::Let, 2, 5),
+            ("x", SymbolKind::Parameter, 2, 12),
+            ("y", SymbolKind::Parameter, 2, 15),
+            ("g", SymbolKind::Let, 3, 5),
+            ("e", SymbolKind::Catch, 4, 24),
+        ]);
+        assert_eq!(analysis.symbols[1].parameters, Some(vec!["x".to_string(), "y".to_string()]));
+
+        // Parameters hide the outer `x`, `g` is called before it is declared
+        assert_eq!(analysis.symbol_at(2, 20), Some(2));
+        assert_eq!(analysis.symbol_at(2, 28), Some(4));
+        assert_eq!(analysis.references_to(0), vec![Span { line: 3, start: 16, end: 17 }, Span { line: 4, start: 9, end: 10 }]);
+        assert_eq!(analysis.references_to(5).len(), 1);
+
+        let visible = analysis.visible_at(2, 21).iter().map(|symbol| (symbol.name.as_str(), symbol.kind)).collect::<Vec<_>>();
+        assert_eq!(visible, vec![("x", SymbolKind::Parameter), ("y", SymbolKind::Parameter), ("f", SymbolKind::Let)]);
+        assert_eq!(analysis.top_level().map(|symbol| symbol.name.as_str()).collect::<Vec<_>>(), vec!["x", "f", "g"]);
+    }
+
+    #[test]
+    fn test_unfinished_source() {
+        let analysis = Analysis::new("import { sum } from \"lib\";\nlet total = sum([1, 2]);\nlet broken = fn(a) { a +");
+
+        assert_eq!(analysis.symbols.iter().map(|symbol| symbol.name.as_str()).collect::<Vec<_>>(), vec!["sum", "total", "broken", "a"]);
+        assert_eq!(analysis.symbol_at(2, 14), Some(0));
+        assert_eq!(analysis.symbol_at(3, 22), Some(3));
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+use checker::checker::{Checker, Severity};
+use evaluator::module::Modules;
+use evaluator::prelude::prelude_names;
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+/// Checks files without running them and prints what is found, one line per diagnostic.
+/// Returns whether the files parsed and no error was found, warnings do not fail the check.
+pub fn start(files: &[String]) -> Result<bool, anyhow::Error> {
+    let globals = if flags::NO_PRELUDE.load(Ordering::Relaxed) { vec![] } else { prelude_names() };
+    let search_paths: Vec<PathBuf> = flags::MODULE_PATHS.lock().unwrap().iter().map(PathBuf::from).collect();
+
+    let mut success = true;
+    for file in files {
+        let source = std::fs::read_to_string(file)?;
+        let program = match Parser::new(Lexer::new(source)).map_err(|error| vec![error]).and_then(|mut parser| parser.parse_program()) {
+            Ok(program) => program,
+            Err(errors) => {
+                for error in errors {
+                    println!("{}: error: {}", file, error);
+                }
+                success = false;
+                continue;
+            }
+        };
+
+        let mut modules = Modules::new(search_paths.clone());
+        modules.set_main_file(PathBuf::from(file));
+        let checker = Checker::new(globals.clone()).with_exports(|path| modules.exported_names(path));
+
+        for diagnostic in checker.check(&program) {
+            println!("{}:{}", file, diagnostic);
+            success &= diagnostic.severity() != Severity::Error;
+        }
+    }
+    Ok(success)
+}
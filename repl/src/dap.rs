@@ -0,0 +1,601 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use serde_json::{json, Value};
+
+use ast::location::MAIN_SOURCE;
+use ast::statement::Statement;
+use environment::environment::Environment;
+use environment::object::ObjectType;
+use error::EvaluatorError;
+use evaluator::backtrace::Backtrace;
+use evaluator::context::Context;
+use evaluator::evaluator::eval_with_context;
+use evaluator::hook::{eval_in_frame, Hook};
+use evaluator::output::{Buffer, Output};
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::debugger::{scopes, summary, Mode, Pause, Stepping};
+use crate::interpreter::{build_context, load_prelude};
+use crate::protocol::{read_message, write_message};
+
+/// The evaluation runs on a single thread
+const THREAD_ID: i64 = 1;
+
+/// Messages exchanged with the client
+struct Connection {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    seq: i64,
+    /// Set once the client asked to stop, nothing is sent afterward
+    disconnected: bool,
+    /// What the program printed and was not sent yet, the output of the server is the protocol
+    out: Buffer,
+    err: Buffer,
+}
+
+impl Connection {
+    /// Reads the next request, `None` once the client closed the input
+    fn read(&mut self) -> Result<Option<Value>, anyhow::Error> {
+        read_message(&mut self.input)
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), anyhow::Error> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> Result<(), anyhow::Error> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    fn respond_error(&mut self, request: &Value, message: &str) -> Result<(), anyhow::Error> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> Result<(), anyhow::Error> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn output(&mut self, category: &str, text: String) -> Result<(), anyhow::Error> {
+        self.event("output", json!({ "category": category, "output": text }))
+    }
+
+    /// Sends what the program printed since the last call as output events
+    fn flush_printed(&mut self) -> Result<(), anyhow::Error> {
+        for (category, text) in [("stdout", self.out.take()), ("stderr", self.err.take())] {
+            if !text.is_empty() {
+                self.output(category, text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lines of the breakpoints of a `setBreakpoints` request, whether they can pause `program` and the response body
+fn breakpoints(request: &Value, program: Option<&Path>) -> (Vec<u32>, bool, Value) {
+    let arguments = &request["arguments"];
+    let lines: Vec<u32> = arguments["breakpoints"].as_array()
+        .map(|breakpoints| breakpoints.iter().filter_map(|breakpoint| breakpoint["line"].as_u64()).map(|line| line as u32).collect())
+        .unwrap_or_default();
+
+    // Only the launched program can pause, modules and the prelude are stepped over
+    let verified = match (arguments["source"]["path"].as_str(), program) {
+        (Some(path), Some(program)) => canonical(Path::new(path)) == program,
+        _ => true,
+    };
+
+    let body = json!({
+        "breakpoints": lines.iter().map(|line| json!({ "verified": verified, "line": line })).collect::<Vec<Value>>(),
+    });
+    (lines, verified, body)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or(path.to_path_buf())
+}
+
+/// Hook pausing the program for the client, requests are read while it is paused
+struct Session {
+    connection: Rc<RefCell<Connection>>,
+    program: PathBuf,
+    stepping: Stepping,
+    stop_on_entry: bool,
+    /// Children of the values shown while paused, a `variablesReference` is an index in it plus one
+    references: Vec<Vec<(String, ObjectType)>>,
+}
+
+impl Session {
+    /// Reference to the children of `value`, 0 when it has none
+    fn reference(&mut self, value: &ObjectType) -> usize {
+        let children = match value {
+            ObjectType::Array(values) if !values.is_empty() => {
+                values.iter().enumerate().map(|(index, value)| (index.to_string(), value.clone())).collect()
+            }
+            ObjectType::Hash(pairs) if !pairs.is_empty() => {
+                pairs.iter().map(|(key, value)| (key.to_string(), value.clone())).collect()
+            }
+            _ => return 0,
+        };
+        self.scope(children)
+    }
+
+    fn scope(&mut self, bindings: Vec<(String, ObjectType)>) -> usize {
+        self.references.push(bindings);
+        self.references.len()
+    }
+
+    fn stack_trace(&self, context: &Context) -> Value {
+        let backtrace = Backtrace::new(context.call_stack(), context.location, &context.modules);
+        let frames = backtrace.frames.iter().enumerate().map(|(id, frame)| {
+            let source = match &frame.source {
+                None => json!({ "name": self.program.file_name().map(|name| name.to_string_lossy()), "path": self.program }),
+                Some(name) if Path::new(name).is_file() => json!({ "name": name, "path": name }),
+                Some(name) => json!({ "name": name }),
+            };
+            json!({
+                "id": id,
+                "name": frame.function,
+                "source": source,
+                "line": frame.location.line,
+                "column": frame.location.column,
+            })
+        }).collect::<Vec<Value>>();
+
+        json!({ "stackFrames": frames, "totalFrames": backtrace.frames.len() })
+    }
+
+    /// Only the innermost frame has its environment, its scopes go from the local bindings to the globals
+    fn scopes(&mut self, context: &Context, environment: &Environment, frame: u64) -> Value {
+        if frame != 0 {
+            return json!({ "scopes": [] });
+        }
+
+        let levels = scopes(context, environment);
+        let count = levels.len();
+        let scopes = levels.into_iter().enumerate().map(|(index, bindings)| {
+            let name = match index {
+                0 => "Locals".to_string(),
+                _ if index == count - 1 => "Globals".to_string(),
+                _ => format!("Scope {}", index),
+            };
+            json!({ "name": name, "variablesReference": self.scope(bindings), "expensive": false })
+        }).collect::<Vec<Value>>();
+
+        json!({ "scopes": scopes })
+    }
+
+    fn variables(&mut self, reference: usize) -> Value {
+        let children = match reference.checked_sub(1).and_then(|index| self.references.get(index)) {
+            Some(children) => children.clone(),
+            None => vec![],
+        };
+
+        let variables = children.iter().map(|(name, value)| json!({
+            "name": name,
+            "value": summary(value),
+            "variablesReference": self.reference(value),
+        })).collect::<Vec<Value>>();
+
+        json!({ "variables": variables })
+    }
+
+    /// Answers the requests of the client until one resumes the program
+    fn pause(&mut self, context: &Context, environment: &Environment, reason: &str) -> Result<(), anyhow::Error> {
+        let depth = context.depth();
+        self.stepping.paused(&context.location, depth);
+        self.references.clear();
+
+        let connection = self.connection.clone();
+        let mut connection = connection.borrow_mut();
+        connection.flush_printed()?;
+        connection.event("stopped", json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }))?;
+
+        loop {
+            let request = match connection.read()? {
+                Some(request) => request,
+                None => {
+                    connection.disconnected = true;
+                    return Err(anyhow!("The client closed the connection"));
+                }
+            };
+            let arguments = &request["arguments"];
+
+            let resume = match request["command"].as_str().unwrap_or_default() {
+                "continue" => Some(Mode::Continue),
+                "next" => Some(Mode::Next(depth)),
+                "stepIn" => Some(Mode::Step),
+                "stepOut" => Some(Mode::Out(depth)),
+                "disconnect" | "terminate" => {
+                    connection.respond(&request, json!({}))?;
+                    connection.disconnected = true;
+                    return Err(anyhow!("The client stopped the program"));
+                }
+                "threads" => {
+                    connection.respond(&request, json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }))?;
+                    None
+                }
+                "stackTrace" => {
+                    connection.respond(&request, self.stack_trace(context))?;
+                    None
+                }
+                "scopes" => {
+                    let body = self.scopes(context, environment, arguments["frameId"].as_u64().unwrap_or_default());
+                    connection.respond(&request, body)?;
+                    None
+                }
+                "variables" => {
+                    let body = self.variables(arguments["variablesReference"].as_u64().unwrap_or_default() as usize);
+                    connection.respond(&request, body)?;
+                    None
+                }
+                "evaluate" => {
+                    let result = eval_in_frame(context, environment, arguments["expression"].as_str().unwrap_or_default());
+                    connection.flush_printed()?;
+                    match result {
+                        Ok(value) => {
+                            let body = json!({ "result": summary(&value), "variablesReference": self.reference(&value) });
+                            connection.respond(&request, body)?;
+                        }
+                        Err(error) => connection.respond_error(&request, &error.to_string())?,
+                    }
+                    None
+                }
+                "setBreakpoints" => {
+                    let (lines, verified, body) = breakpoints(&request, Some(&self.program));
+                    if verified {
+                        self.stepping.breakpoints = lines.into_iter().collect();
+                    }
+                    connection.respond(&request, body)?;
+                    None
+                }
+                "pause" => {
+                    connection.respond(&request, json!({}))?;
+                    None
+                }
+                command => {
+                    connection.respond_error(&request, &format!("Unsupported request `{}` while paused", command))?;
+                    None
+                }
+            };
+
+            if let Some(mode) = resume {
+                self.stepping.mode = mode;
+                connection.respond(&request, json!({ "allThreadsContinued": true }))?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Hook for Session {
+    fn before_statement(&mut self, context: &Context, environment: &Environment, statement: &Statement) -> Result<(), EvaluatorError> {
+        let location = match statement.location() {
+            Some(location) if location.source == MAIN_SOURCE => location,
+            _ => return Ok(()),
+        };
+
+        let reason = if self.stop_on_entry {
+            self.stop_on_entry = false;
+            "entry"
+        } else {
+            match self.stepping.should_pause(&location, context.depth()) {
+                Some(Pause::Breakpoint) => "breakpoint",
+                Some(Pause::Step) => "step",
+                None => return Ok(()),
+            }
+        };
+
+        // Protocol errors stop the program, the server reports them once the evaluation returned
+        self.pause(context, environment, reason).map_err(|_| EvaluatorError::interrupted())
+    }
+}
+
+/// Program to launch, read from the arguments of the `launch` request
+struct Launch {
+    program: PathBuf,
+    stop_on_entry: bool,
+}
+
+/// Serves one debugging session over stdio
+pub fn start() -> Result<(), anyhow::Error> {
+    serve(Box::new(std::io::stdin().lock()), Box::new(std::io::stdout()))
+}
+
+/// Answers the configuration requests, runs the launched program then waits for the client to disconnect
+pub fn serve(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Result<(), anyhow::Error> {
+    let connection = Rc::new(RefCell::new(Connection { input, output, seq: 0, disconnected: false, out: Buffer::new(), err: Buffer::new() }));
+    let mut breakpoints_by_path: HashMap<PathBuf, Vec<u32>> = HashMap::new();
+    let mut launch = None;
+    let mut configured = false;
+
+    while launch.is_none() || !configured {
+        let mut connection = connection.borrow_mut();
+        let request = match connection.read()? {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+
+        match request["command"].as_str().unwrap_or_default() {
+            "initialize" => {
+                connection.respond(&request, json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                }))?;
+                connection.event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let arguments = &request["arguments"];
+                match arguments["program"].as_str() {
+                    Some(program) if Path::new(program).is_file() => {
+                        launch = Some(Launch {
+                            program: canonical(Path::new(program)),
+                            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+                        });
+                        connection.respond(&request, json!({}))?;
+                    }
+                    Some(program) => connection.respond_error(&request, &format!("File {} not found", program))?,
+                    None => connection.respond_error(&request, "Missing `program` argument")?,
+                }
+            }
+            "setBreakpoints" => {
+                let (lines, _, body) = breakpoints(&request, None);
+                if let Some(path) = request["arguments"]["source"]["path"].as_str() {
+                    breakpoints_by_path.insert(canonical(Path::new(path)), lines);
+                }
+                connection.respond(&request, body)?;
+            }
+            "configurationDone" => {
+                configured = true;
+                connection.respond(&request, json!({}))?;
+            }
+            "setExceptionBreakpoints" => connection.respond(&request, json!({}))?,
+            "threads" => connection.respond(&request, json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }))?,
+            "disconnect" | "terminate" => return connection.respond(&request, json!({})),
+            command => connection.respond_error(&request, &format!("Unsupported request `{}` before launch", command))?,
+        }
+    }
+
+    let launch = launch.unwrap();
+    let mut stepping = Stepping::new(Mode::Continue);
+    stepping.breakpoints = breakpoints_by_path.remove(&launch.program).unwrap_or_default().into_iter().collect();
+
+    let session = Session {
+        connection: connection.clone(),
+        program: launch.program.clone(),
+        stepping,
+        stop_on_entry: launch.stop_on_entry,
+        references: vec![],
+    };
+    let printed = {
+        let connection = connection.borrow();
+        Output::new(connection.out.clone(), connection.err.clone())
+    };
+    let mut context = build_context().with_hook(Box::new(session)).with_output(printed);
+    context.modules.set_main_file(launch.program.clone());
+
+    let result = run(&launch.program, &mut context);
+
+    let mut connection = connection.borrow_mut();
+    if connection.disconnected {
+        return Ok(());
+    }
+
+    connection.flush_printed()?;
+    let exit_code = match result {
+        Ok(value) => {
+            connection.output("console", format!("{}\n", value))?;
+            0
+        }
+        Err(message) => {
+            connection.output("stderr", format!("{}\n", message))?;
+            1
+        }
+    };
+    connection.event("terminated", json!({}))?;
+    connection.event("exited", json!({ "exitCode": exit_code }))?;
+
+    while let Some(request) = connection.read()? {
+        match request["command"].as_str().unwrap_or_default() {
+            "disconnect" | "terminate" => return connection.respond(&request, json!({})),
+            "threads" => connection.respond(&request, json!({ "threads": [] }))?,
+            _ => connection.respond_error(&request, "The program has ended")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Evaluates the launched program, errors are returned with their backtrace
+fn run(program: &Path, context: &mut Context) -> Result<ObjectType, String> {
+    let input = std::fs::read_to_string(program).map_err(|e| e.to_string())?;
+    let mut parser = Parser::new(Lexer::new(input)).map_err(|e| e.to_string())?;
+    let program = parser.parse_program().map_err(|errors| {
+        errors.iter().map(|e| format!("Error: {:?}", e)).collect::<Vec<String>>().join("\n")
+    })?;
+
+    let mut environment = Environment::new();
+    load_prelude(context, &mut environment).map_err(|e| format!("Error: {:?}", e))?;
+
+    eval_with_context(&program, &mut environment, context).map_err(|error| {
+        match context.backtrace(&error) {
+            Some(backtrace) => format!("Error: {:?}\nBacktrace:\n{}", error, backtrace),
+            None => format!("Error: {:?}", error),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::protocol::{frame, unframe, SharedOutput};
+
+    use super::*;
+
+    const PROGRAM: &str = "let f = fn(x) {
+  let y = [x, x * 2];
+  y[1] + 1
+};
+let a = f(1);
+let b = f(a);
+b";
+
+    /// Sends the requests in order and returns the messages of the server
+    fn session(name: &str, requests: &[(&str, Value)]) -> Vec<Value> {
+        session_of(name, PROGRAM, requests)
+    }
+
+    fn session_of(name: &str, source: &str, requests: &[(&str, Value)]) -> Vec<Value> {
+        let program = std::env::temp_dir().join(format!("monkey-dap-{}-{}.monkey", name, std::process::id()));
+        std::fs::write(&program, source).unwrap();
+
+        let requests = requests.iter().enumerate().map(|(seq, (command, arguments))| {
+            let mut arguments = arguments.clone();
+            if *command == "launch" || *command == "setBreakpoints" {
+                arguments["program"] = json!(program);
+                arguments["source"] = json!({ "path": program });
+            }
+            json!({ "seq": seq + 1, "type": "request", "command": command, "arguments": arguments })
+        }).collect::<Vec<Value>>();
+
+        let output = SharedOutput::default();
+        serve(Box::new(Cursor::new(frame(&requests))), Box::new(output.clone())).unwrap();
+
+        let messages = unframe(&output.0.borrow());
+        messages
+    }
+
+    fn response(messages: &[Value], seq: u64) -> &Value {
+        messages.iter().find(|message| message["type"] == "response" && message["request_seq"] == seq).unwrap()
+    }
+
+    /// Reasons and lines of the pauses, read from the top frame of the following `stackTrace` responses
+    fn stops(messages: &[Value]) -> Vec<(String, u64)> {
+        let reasons = messages.iter()
+            .filter(|message| message["event"] == "stopped")
+            .map(|message| message["body"]["reason"].as_str().unwrap().to_string());
+        let lines = messages.iter()
+            .filter(|message| message["command"] == "stackTrace")
+            .map(|message| message["body"]["stackFrames"][0]["line"].as_u64().unwrap());
+        reasons.zip(lines).collect()
+    }
+
+    #[test]
+    fn test_session() {
+        let messages = session("session", &[
+            ("initialize", json!({})),
+            ("launch", json!({})),
+            ("setBreakpoints", json!({ "breakpoints": [{ "line": 3 }] })),
+            ("configurationDone", json!({})),
+            ("stackTrace", json!({ "threadId": 1 })),
+            ("scopes", json!({ "frameId": 0 })),
+            ("variables", json!({ "variablesReference": 1 })),
+            ("variables", json!({ "variablesReference": 3 })),
+            ("evaluate", json!({ "expression": "x + y[1]" })),
+            ("evaluate", json!({ "expression": "z" })),
+            ("next", json!({})),
+            ("stackTrace", json!({ "threadId": 1 })),
+            ("stepIn", json!({})),
+            ("stackTrace", json!({ "threadId": 1 })),
+            ("continue", json!({})),
+            ("stackTrace", json!({ "threadId": 1 })),
+            ("continue", json!({})),
+            ("disconnect", json!({})),
+        ]);
+
+        assert_eq!(response(&messages, 1)["body"]["supportsConfigurationDoneRequest"], true);
+        assert_eq!(response(&messages, 3)["body"]["breakpoints"][0], json!({ "verified": true, "line": 3 }));
+        assert_eq!(stops(&messages), vec![
+            ("breakpoint".to_string(), 3),
+            ("step".to_string(), 6),
+            ("step".to_string(), 2),
+            ("breakpoint".to_string(), 3),
+        ]);
+
+        let frames = &response(&messages, 5)["body"]["stackFrames"];
+        assert_eq!(frames[0]["name"], "f");
+        assert_eq!(frames[1]["name"], "<main>");
+        assert_eq!(frames[1]["line"], 5);
+
+        let scopes = &response(&messages, 6)["body"]["scopes"];
+        assert_eq!(scopes[0]["name"], "Locals");
+        assert_eq!(scopes[0]["variablesReference"], 1);
+
+        let variables = &response(&messages, 7)["body"]["variables"];
+        assert_eq!(variables[0], json!({ "name": "x", "value": "1", "variablesReference": 0 }));
+        assert_eq!(variables[1], json!({ "name": "y", "value": "[1, 2]", "variablesReference": 3 }));
+        assert_eq!(response(&messages, 8)["body"]["variables"][1]["value"], "2");
+
+        assert_eq!(response(&messages, 9)["body"]["result"], "3");
+        assert_eq!(response(&messages, 10)["success"], false);
+
+        let events = messages.iter().filter(|message| message["type"] == "event").map(|message| message["event"].as_str().unwrap()).collect::<Vec<&str>>();
+        assert_eq!(&events[events.len() - 3..], &["output", "terminated", "exited"]);
+        assert!(messages.iter().any(|message| message["event"] == "output" && message["body"]["output"] == "7\n"));
+        assert_eq!(response(&messages, 18)["success"], true);
+    }
+
+    #[test]
+    fn test_stop_on_entry() {
+        let messages = session("entry", &[
+            ("initialize", json!({})),
+            ("launch", json!({ "stopOnEntry": true })),
+            ("configurationDone", json!({})),
+            ("stackTrace", json!({ "threadId": 1 })),
+            ("evaluate", json!({ "expression": "eprintln(\"hi\")" })),
+            ("disconnect", json!({})),
+        ]);
+
+        assert_eq!(stops(&messages), vec![("entry".to_string(), 1)]);
+        // What the program prints is sent as output events, stdout carries the protocol
+        let printed = messages.iter().position(|message| message["event"] == "output").unwrap();
+        assert_eq!(messages[printed]["body"], json!({ "category": "stderr", "output": "hi\n" }));
+        assert_eq!(messages[printed + 1]["request_seq"], 5);
+        assert!(messages.iter().all(|message| message["event"] != "terminated"));
+    }
+
+    #[test]
+    fn test_printing_program() {
+        let messages = session_of("printing", "println(1);\neprint(\"e\");\nlet a = 2;\na", &[
+            ("initialize", json!({})),
+            ("launch", json!({})),
+            ("setBreakpoints", json!({ "breakpoints": [{ "line": 3 }] })),
+            ("configurationDone", json!({})),
+            ("continue", json!({})),
+            ("disconnect", json!({})),
+        ]);
+
+        // Every message is a protocol message, what was printed before the pause is sent before it
+        let events = messages.iter().filter(|message| message["type"] == "event")
+            .map(|message| (message["event"].as_str().unwrap(), message["body"]["category"].as_str().unwrap_or_default(), message["body"]["output"].as_str().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![
+            ("initialized", "", ""),
+            ("output", "stdout", "1\n"),
+            ("output", "stderr", "e"),
+            ("stopped", "", ""),
+            ("output", "console", "2\n"),
+            ("terminated", "", ""),
+            ("exited", "", ""),
+        ]);
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use ast::location::{Location, MAIN_SOURCE};
+use ast::statement::Statement;
+use environment::environment::Environment;
+use environment::object::ObjectType;
+use error::EvaluatorError;
+use evaluator::backtrace::Backtrace;
+use evaluator::context::Context;
+use evaluator::hook::{eval_in_frame, Hook};
+
+use crate::interpreter::{build_context, execute};
+
+const HELP: &str = "\
+Commands:
+  s, step           run until the next statement, entering calls
+  n, next           run until the next statement of this function, stepping over calls
+  o, out            run until the current function returns
+  c, continue       run until the next breakpoint
+  b, break <line>   add a breakpoint
+  d, delete <line>  remove a breakpoint
+  breakpoints       list the breakpoints
+  l, list           show the source around the current line
+  e, env            show the bindings of each scope, the innermost first
+  p, print <expr>   evaluate an expression in the current scope
+  bt, backtrace     show the call stack
+  q, quit           stop the program
+  h, help           show this help
+An empty line repeats the last command.";
+
+/// Number of lines shown on each side of the current line by `list`
+const LIST_CONTEXT: u32 = 3;
+
+/// When the program pauses next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Mode {
+    /// At the next statement
+    Step,
+    /// At the next statement at most this deep in the call stack
+    Next(usize),
+    /// At the next statement less deep in the call stack
+    Out(usize),
+    /// At the next breakpoint
+    Continue,
+}
+
+/// Why the program paused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Pause {
+    Step,
+    Breakpoint,
+}
+
+/// Breakpoints and stepping state shared by the debugger frontends
+#[derive(Debug)]
+pub(crate) struct Stepping {
+    pub(crate) mode: Mode,
+    pub(crate) breakpoints: BTreeSet<u32>,
+    /// Line and depth of the last pause, a breakpoint does not pause again before the line changes
+    last_stop: Option<(u32, usize)>,
+}
+
+impl Stepping {
+    pub(crate) fn new(mode: Mode) -> Self {
+        return Self {
+            mode,
+            breakpoints: BTreeSet::new(),
+            last_stop: None,
+        };
+    }
+
+    /// Whether the statement at `location`, evaluated `depth` calls deep, pauses the program
+    pub(crate) fn should_pause(&mut self, location: &Location, depth: usize) -> Option<Pause> {
+        if matches!(self.last_stop, Some((line, _)) if line != location.line) {
+            self.last_stop = None;
+        }
+
+        let stepped = match self.mode {
+            Mode::Step => true,
+            Mode::Next(max_depth) => depth <= max_depth,
+            Mode::Out(max_depth) => depth < max_depth,
+            Mode::Continue => false,
+        };
+        let on_breakpoint = self.breakpoints.contains(&location.line) && self.last_stop != Some((location.line, depth));
+
+        if on_breakpoint {
+            return Some(Pause::Breakpoint);
+        }
+        if stepped {
+            return Some(Pause::Step);
+        }
+        None
+    }
+
+    pub(crate) fn paused(&mut self, location: &Location, depth: usize) {
+        self.last_stop = Some((location.line, depth));
+    }
+}
+
+/// Hook pausing the program on breakpoints and steps to read commands.
+/// It only pauses in the debugged file, calls to modules and to the prelude are stepped over.
+pub struct Debugger {
+    source: Vec<String>,
+    stepping: Stepping,
+    last_command: String,
+    input: Box<dyn Iterator<Item=String>>,
+    output: Box<dyn Write>,
+}
+
+impl Debugger {
+    /// Pauses at the first statement of `source`, commands are read from `input`
+    pub fn new(source: &str, input: Box<dyn Iterator<Item=String>>, output: Box<dyn Write>) -> Self {
+        return Self {
+            source: source.lines().map(|line| line.to_string()).collect(),
+            stepping: Stepping::new(Mode::Step),
+            last_command: String::new(),
+            input,
+            output,
+        };
+    }
+
+    fn show_line(&mut self, line: u32) -> std::io::Result<()> {
+        let text = self.source.get(line as usize - 1).map(|text| text.as_str()).unwrap_or("");
+        writeln!(self.output, "{:>4} | {}", line, text)
+    }
+
+    fn list(&mut self, current: u32) -> std::io::Result<()> {
+        let first = current.saturating_sub(LIST_CONTEXT).max(1);
+        let last = (current + LIST_CONTEXT).min(self.source.len() as u32);
+        for line in first..=last {
+            let marker = if line == current { ">" } else if self.stepping.breakpoints.contains(&line) { "*" } else { " " };
+            let text = self.source[line as usize - 1].clone();
+            writeln!(self.output, "{} {:>4} | {}", marker, line, text)?;
+        }
+        Ok(())
+    }
+
+    fn show_environment(&mut self, context: &Context, environment: &Environment) -> std::io::Result<()> {
+        for (index, bindings) in scopes(context, environment).iter().enumerate() {
+            writeln!(self.output, "scope {}:", index)?;
+            for (name, value) in bindings {
+                writeln!(self.output, "  {} = {}", name, summary(value))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads commands until one resumes the program
+    fn pause(&mut self, context: &Context, environment: &Environment) -> Result<(), EvaluatorError> {
+        let location = context.location;
+        let depth = context.depth();
+        self.stepping.paused(&location, depth);
+        self.show_line(location.line).map_err(io_error)?;
+
+        loop {
+            write!(self.output, "(debug) ").map_err(io_error)?;
+            self.output.flush().map_err(io_error)?;
+
+            let line = match self.input.next() {
+                Some(line) => line.trim().to_string(),
+                None => return Err(EvaluatorError::interrupted()),
+            };
+            let line = if line.is_empty() { self.last_command.clone() } else { line };
+            self.last_command = line.clone();
+
+            let (command, argument) = match line.split_once(' ') {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (line.as_str(), ""),
+            };
+
+            match command {
+                "s" | "step" => self.stepping.mode = Mode::Step,
+                "n" | "next" => self.stepping.mode = Mode::Next(depth),
+                "o" | "out" => self.stepping.mode = Mode::Out(depth),
+                "c" | "continue" => self.stepping.mode = Mode::Continue,
+                "q" | "quit" => return Err(EvaluatorError::interrupted()),
+                _ => {
+                    self.run_command(context, environment, command, argument).map_err(io_error)?;
+                    continue;
+                }
+            }
+
+            return Ok(());
+        }
+    }
+
+    /// Runs a command that does not resume the program
+    fn run_command(&mut self, context: &Context, environment: &Environment, command: &str, argument: &str) -> std::io::Result<()> {
+        match command {
+            "b" | "break" | "d" | "delete" => {
+                match argument.parse::<u32>() {
+                    Ok(line) if line >= 1 && line as usize <= self.source.len() => {
+                        if command.starts_with('b') {
+                            self.stepping.breakpoints.insert(line);
+                            writeln!(self.output, "Breakpoint at line {}", line)
+                        } else if self.stepping.breakpoints.remove(&line) {
+                            writeln!(self.output, "Removed breakpoint at line {}", line)
+                        } else {
+                            writeln!(self.output, "No breakpoint at line {}", line)
+                        }
+                    }
+                    _ => writeln!(self.output, "Expected a line between 1 and {}", self.source.len()),
+                }
+            }
+            "breakpoints" => {
+                let lines = self.stepping.breakpoints.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+                writeln!(self.output, "Breakpoints: {}", if lines.is_empty() { "none".to_string() } else { lines.join(", ") })
+            }
+            "l" | "list" => self.list(context.location.line),
+            "e" | "env" => self.show_environment(context, environment),
+            "p" | "print" => match eval_in_frame(context, environment, argument) {
+                Ok(value) => writeln!(self.output, "{}", value),
+                Err(error) => writeln!(self.output, "Error: {}", error),
+            },
+            "bt" | "backtrace" => {
+                let backtrace = Backtrace::new(context.call_stack(), context.location, &context.modules);
+                writeln!(self.output, "{}", backtrace)
+            }
+            "h" | "help" => writeln!(self.output, "{}", HELP),
+            _ => writeln!(self.output, "Unknown command `{}`, type `help` for the list of commands", command),
+        }
+    }
+}
+
+impl Hook for Debugger {
+    fn before_statement(&mut self, context: &Context, environment: &Environment, statement: &Statement) -> Result<(), EvaluatorError> {
+        let location = match statement.location() {
+            Some(location) if location.source == MAIN_SOURCE => location,
+            _ => return Ok(()),
+        };
+
+        if self.stepping.should_pause(&location, context.depth()).is_some() {
+            return self.pause(context, environment);
+        }
+        Ok(())
+    }
+}
+
+/// Bindings of each scope of `environment` sorted by name, the innermost first.
+/// Calls copy the bindings of the function environment, these copies are only shown in the scope they come from.
+/// The outermost scope is not shown when it is the prelude of `context`.
+pub(crate) fn scopes(context: &Context, environment: &Environment) -> Vec<Vec<(String, ObjectType)>> {
+    let mut levels = vec![];
+    let mut scope = Some(environment);
+    while let Some(environment) = scope {
+        levels.push(environment.store());
+        scope = environment.outer();
+    }
+    let shown = if context.modules.prelude.is_empty() { levels.len() } else { levels.len() - 1 };
+
+    let mut scopes = vec![];
+    for (index, store) in levels.iter().enumerate().take(shown) {
+        let mut bindings = store.iter()
+            .filter(|(name, value)| {
+                !levels[index + 1..].iter().any(|outer| matches!(outer.get(*name), Some(outer) if outer.to_string() == value.to_string()))
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        bindings.sort_by(|(left, _), (right, _)| left.cmp(right));
+        scopes.push(bindings);
+    }
+    scopes
+}
+
+/// Functions are shown by their parameters, their body can be long
+pub(crate) fn summary(value: &ObjectType) -> String {
+    match value {
+        ObjectType::Function { parameters, .. } => {
+            format!("fn({})", parameters.iter().map(|parameter| parameter.to_string()).collect::<Vec<String>>().join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn io_error(error: std::io::Error) -> EvaluatorError {
+    EvaluatorError::io_error(error.to_string())
+}
+
+/// Runs `file` under the debugger, reading commands from the standard input
+pub fn start(file: &str) -> Result<(), anyhow::Error> {
+    let input = std::fs::read_to_string(file)?;
+    let stdin_lines = Box::new(std::io::stdin().lines().map_while(Result::ok));
+    let debugger = Debugger::new(&input, stdin_lines, Box::new(std::io::stdout()));
+    println!("Debugging {}, type `help` for the list of commands", file);
+
+    let mut context = build_context().with_hook(Box::new(debugger));
+    context.modules.set_main_file(PathBuf::from(file));
+
+    execute(input, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use lexer::lexer::Lexer;
+    use parser::parser::Parser;
+
+    use crate::protocol::SharedOutput;
+
+    use super::*;
+
+    fn debug(source: &str, commands: &[&str]) -> (Result<ObjectType, EvaluatorError>, String) {
+        debug_in(source, commands, false)
+    }
+
+    fn debug_in(source: &str, commands: &[&str], prelude: bool) -> (Result<ObjectType, EvaluatorError>, String) {
+        let output = SharedOutput::default();
+        let commands = commands.iter().map(|command| command.to_string()).collect::<Vec<String>>();
+        let debugger = Debugger::new(source, Box::new(commands.into_iter()), Box::new(output.clone()));
+
+        let program = Parser::new(Lexer::new(source.to_string())).unwrap().parse_program().unwrap();
+        let mut context = Context::default();
+        let mut environment = Environment::new();
+        if prelude {
+            evaluator::prelude::load_prelude(&mut context, &mut environment).unwrap();
+        }
+        let mut context = context.with_hook(Box::new(debugger));
+        let result = evaluator::evaluator::eval_with_context(&program, &mut environment, &mut context);
+
+        let output = String::from_utf8(output.0.borrow().clone()).unwrap();
+        (result, output)
+    }
+
+    /// Lines the program paused on
+    fn stops(output: &str) -> Vec<u32> {
+        output.lines()
+            .filter_map(|line| line.trim_start_matches("(debug) ").split_once(" | "))
+            .filter_map(|(line, _)| line.trim().parse().ok())
+            .collect()
+    }
+
+    const PROGRAM: &str = "let f = fn(x) {
+  let y = x * 2;
+  y + 1
+};
+let a = f(1);
+let b = f(a);
+b";
+
+    #[test]
+    fn test_stepping() {
+        let (result, output) = debug(PROGRAM, &["s", "s", "s", "s", "", "c"]);
+        assert_eq!(result, Ok(ObjectType::Integer(7)));
+        assert_eq!(stops(&output), vec![1, 5, 2, 3, 6, 2]);
+
+        let (_, output) = debug(PROGRAM, &["n", "n", "n", "n"]);
+        assert_eq!(stops(&output), vec![1, 5, 6, 7]);
+
+        let (_, output) = debug(PROGRAM, &["s", "s", "o", "c"]);
+        assert_eq!(stops(&output), vec![1, 5, 2, 6]);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let (result, output) = debug(PROGRAM, &["b 3", "c", "c", "d 3", "c"]);
+        assert_eq!(result, Ok(ObjectType::Integer(7)));
+        assert_eq!(stops(&output), vec![1, 3, 3]);
+
+        let (result, _) = debug(PROGRAM, &["b 2", "c", "q"]);
+        assert_eq!(result, Err(EvaluatorError::interrupted()));
+    }
+
+    #[test]
+    fn test_inspection() {
+        let (_, output) = debug(PROGRAM, &["b 3", "c", "c", "p x + y", "env", "bt", "c"]);
+        assert!(output.contains("(debug) 9\n"), "{}", output);
+        assert!(output.contains("scope 0:\n  x = 3\n  y = 6\n"), "{}", output);
+        assert!(output.contains("f = fn(x)"), "{}", output);
+        assert!(output.contains("    at f (line 3, column"), "{}", output);
+        assert!(output.contains("    at <main> (line 6, column"), "{}", output);
+
+        // The prelude is the outermost scope, it is not shown
+        let (_, output) = debug_in(PROGRAM, &["b 3", "c", "env", "c"], true);
+        assert!(output.contains("scope 0:\n  x = 1\n  y = 2\nscope 1:\n  f = fn(x)\n(debug) "), "{}", output);
+        assert!(!output.contains("scope 2:"), "{}", output);
+    }
+
+    struct ClosedTerminal;
+
+    impl Write for ClosedTerminal {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_terminal_error() {
+        let debugger = Debugger::new(PROGRAM, Box::new(std::iter::empty()), Box::new(ClosedTerminal));
+        let program = Parser::new(Lexer::new(PROGRAM.to_string())).unwrap().parse_program().unwrap();
+        let mut context = Context::default().with_hook(Box::new(debugger));
+
+        let result = evaluator::evaluator::eval_with_context(&program, &mut Environment::new(), &mut context);
+        assert_eq!(result, Err(EvaluatorError::io_error("closed".to_string())));
+        assert!(!result.unwrap_err().is_catchable());
+    }
+}
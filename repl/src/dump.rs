@@ -0,0 +1,60 @@
+use std::io::Write;
+
+use ast::program::Program;
+use ast::tree::tree;
+use error::EvaluatorError;
+use lexer::lexer::Lexer;
+use lexer::token::{Token, TokenType};
+use parser::parser::Parser;
+
+/// Tokens of `source` up to the end, without the `EOF` token
+pub(crate) fn read_tokens(source: &str) -> Result<Vec<Token>, EvaluatorError> {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_token()?;
+        if token.kind == TokenType::EOF {
+            break;
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Parses `source`, the parse errors are reported together, one per line
+pub(crate) fn parse(source: &str) -> Result<Program, anyhow::Error> {
+    let mut parser = Parser::new(Lexer::new(source.to_string()))?;
+    parser.parse_program().map_err(|errors| {
+        let messages = errors.iter().map(|error| error.to_string()).collect::<Vec<String>>();
+        anyhow::anyhow!(messages.join("\n"))
+    })
+}
+
+/// Prints the tokens of a file, one per line with its line and column, or as a JSON array
+pub fn tokens(file: &str, json: bool) -> Result<(), anyhow::Error> {
+    let tokens = read_tokens(&std::fs::read_to_string(file)?)?;
+
+    // Written without `println!` so a closed pipe is an error instead of a panic
+    let mut stdout = std::io::stdout().lock();
+    if json {
+        writeln!(stdout, "{}", serde_json::to_string_pretty(&tokens)?)?;
+        return Ok(());
+    }
+    for token in tokens {
+        writeln!(stdout, "{}:{} {}", token.line, token.column, token.kind)?;
+    }
+    Ok(())
+}
+
+/// Prints the program parsed from a file as an indented tree, or as JSON
+pub fn ast(file: &str, json: bool) -> Result<(), anyhow::Error> {
+    let program = parse(&std::fs::read_to_string(file)?)?;
+
+    let mut stdout = std::io::stdout().lock();
+    if json {
+        writeln!(stdout, "{}", serde_json::to_string_pretty(&program)?)?;
+        return Ok(());
+    }
+    write!(stdout, "{}", tree(&program))?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::io::{Read, Write};
+
+use log::{error, info};
+
+use formatter::formatter::{format, FormatOptions};
+
+/// Formats files in place, or standard input to standard output when no file is given.
+/// With `check` nothing is written and the files that are not formatted are reported.
+/// Returns whether every input was formatted and parsed.
+pub fn start(files: &[String], check: bool, options: &FormatOptions) -> Result<bool, anyhow::Error> {
+    if files.is_empty() {
+        let mut source = String::new();
+        std::io::stdin().read_to_string(&mut source)?;
+
+        return match format_source("<stdin>", &source, options) {
+            Some(formatted) if check => Ok(formatted == source),
+            Some(formatted) => {
+                std::io::stdout().write_all(formatted.as_bytes())?;
+                Ok(true)
+            }
+            None => Ok(false),
+        };
+    }
+
+    let mut success = true;
+    for file in files {
+        let source = std::fs::read_to_string(file)?;
+        let Some(formatted) = format_source(file, &source, options) else {
+            success = false;
+            continue;
+        };
+
+        if formatted == source {
+            continue;
+        }
+        if check {
+            info!("{} is not formatted", file);
+            success = false;
+        } else {
+            std::fs::write(file, formatted)?;
+            info!("Formatted {}", file);
+        }
+    }
+    Ok(success)
+}
+
+fn format_source(name: &str, source: &str, options: &FormatOptions) -> Option<String> {
+    match format(source, options) {
+        Ok(formatted) => Some(formatted),
+        Err(errors) => {
+            for error in errors {
+                error!("{}: {:?}", name, error);
+            }
+            None
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::borrow::Cow;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::{Hint, Hinter};
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+
+use error::EvaluatorError;
+use evaluator::builtins::{builtin_names, get_builtin, get_builtin_signature};
+use lexer::lexer::Lexer;
+use lexer::token::TokenType;
+
+use crate::lsp::KEYWORDS;
+use crate::repl::COMMANDS;
+
+// ANSI colours of the highlighted tokens
+const KEYWORD: &str = "\x1b[35m";
+const LITERAL: &str = "\x1b[33m";
+const STRING: &str = "\x1b[32m";
+const BUILTIN: &str = "\x1b[36m";
+const ILLEGAL: &str = "\x1b[31m";
+const COMMENT: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// Completes, highlights, hints and validates the lines typed in the REPL with the lexer of the language
+#[derive(Debug, Default)]
+pub(crate) struct ReplHelper {
+    /// Names bound in the session
+    pub(crate) names: Vec<String>,
+    /// Lines of the statement being continued, the edited line follows them
+    pub(crate) pending: String,
+}
+
+impl ReplHelper {
+    fn is_command(&self, line: &str) -> bool {
+        self.pending.is_empty() && line.starts_with(':')
+    }
+
+    /// Start of the word before `pos` and the names it is the beginning of, sorted
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        if self.is_command(line) {
+            if line[..pos].contains(char::is_whitespace) {
+                return (pos, vec![]);
+            }
+            let commands = COMMANDS.iter().filter(|command| command.starts_with(&line[..pos])).map(|command| command.to_string());
+            return (0, commands.collect());
+        }
+
+        complete_name(&self.names, line, pos)
+    }
+
+    fn hint_at(&self, line: &str, pos: usize) -> Option<ReplHint> {
+        // In the middle of the line the hint would hide what follows
+        if pos < line.len() {
+            return None;
+        }
+
+        let (start, candidates) = self.candidates(line, pos);
+        if let [name] = candidates.as_slice() {
+            let rest = &name[pos - start..];
+            if !rest.is_empty() {
+                return Some(ReplHint { display: rest.to_string(), completion: Some(rest.to_string()) });
+            }
+        }
+
+        // The parameters of the builtin being called, they cannot be accepted
+        let callee = line[..pos].strip_suffix('(')?;
+        let name = &callee[word_start(callee, callee.len())..];
+        let (signature, _) = get_builtin_signature(name)?;
+        Some(ReplHint { display: signature[name.len() + 1..].to_string(), completion: None })
+    }
+}
+
+/// Start of the identifier before `pos` and the keywords, builtins and `names` it is the beginning of, sorted
+pub(crate) fn complete_name(names: &[String], line: &str, pos: usize) -> (usize, Vec<String>) {
+    let start = word_start(line, pos);
+    let word = &line[start..pos];
+    if word.is_empty() || word.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return (start, vec![]);
+    }
+    let mut candidates = KEYWORDS.iter().copied().chain(builtin_names()).map(str::to_string)
+        .chain(names.iter().cloned())
+        .filter(|name| name.starts_with(word))
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+/// Start of the identifier ending at `pos`
+pub(crate) fn word_start(line: &str, pos: usize) -> usize {
+    let mut start = pos;
+    for (index, ch) in line[..pos].char_indices().rev() {
+        if !(ch.is_ascii_alphanumeric() || ch == '_') {
+            break;
+        }
+        start = index;
+    }
+    start
+}
+
+fn colour(kind: &TokenType) -> Option<&'static str> {
+    match kind {
+        TokenType::FUNCTION | TokenType::LET | TokenType::IF | TokenType::ELSE | TokenType::RETURN | TokenType::IMPORT
+        | TokenType::EXPORT | TokenType::TRY | TokenType::CATCH | TokenType::FINALLY | TokenType::THROW => Some(KEYWORD),
+        TokenType::INT(_) | TokenType::TRUE | TokenType::FALSE => Some(LITERAL),
+        TokenType::STRING(_) => Some(STRING),
+        TokenType::IDENT(name) if get_builtin(name).is_some() => Some(BUILTIN),
+        TokenType::ILLEGAL(_) => Some(ILLEGAL),
+        _ => None,
+    }
+}
+
+fn paint(output: &mut String, text: &str, colour: Option<&str>) {
+    match colour {
+        Some(colour) if !text.is_empty() => output.push_str(&format!("{}{}{}", colour, text, RESET)),
+        _ => output.push_str(text),
+    }
+}
+
+/// Copies the whitespace and the comments of `source` from `start` to `output`, returns where the next token starts
+fn skip_comments(source: &str, mut start: usize, output: &mut String) -> usize {
+    loop {
+        let rest = &source[start..];
+        let token = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+        output.push_str(&rest[..rest.len() - token.len()]);
+        start += rest.len() - token.len();
+        if !token.starts_with("//") {
+            return start;
+        }
+
+        let comment = &token[..token.find('\n').unwrap_or(token.len())];
+        paint(output, comment, Some(COMMENT));
+        start += comment.len();
+    }
+}
+
+/// `source` with its tokens and comments coloured by category, the text after a lexer error is left as is
+fn highlight(source: &str) -> String {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut output = String::new();
+    let mut end = 0;
+    loop {
+        let token = lexer.next_token();
+        let start = skip_comments(source, end, &mut output);
+        end = lexer.position().clamp(start, source.len());
+        let text = &source[start..end];
+        match &token {
+            Ok(token) if token.kind != TokenType::EOF => {
+                paint(&mut output, text, colour(&token.kind));
+                continue;
+            }
+            Ok(_) => paint(&mut output, text, None),
+            Err(EvaluatorError::UnfinishedString { .. }) => paint(&mut output, text, Some(STRING)),
+            Err(_) => paint(&mut output, text, Some(ILLEGAL)),
+        }
+        output.push_str(&source[end..]);
+        return output;
+    }
+}
+
+/// Message for the first closing bracket of `source` that does not close the last opened one
+fn unbalanced(source: &str) -> Option<String> {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut open: Vec<char> = vec![];
+    while let Ok(token) = lexer.next_token() {
+        let closing = match token.kind {
+            TokenType::EOF => break,
+            TokenType::LPAREN => ')',
+            TokenType::LBRACE => '}',
+            TokenType::LBRACKET => ']',
+            TokenType::RPAREN | TokenType::RBRACE | TokenType::RBRACKET => {
+                let found = token.kind.to_string();
+                match open.pop() {
+                    Some(expected) if found == expected.to_string() => continue,
+                    Some(expected) => return Some(format!(" -- expected `{}`, found `{}`", expected, found)),
+                    None => return Some(format!(" -- `{}` closes nothing", found)),
+                }
+            }
+            _ => continue,
+        };
+        open.push(closing);
+    }
+    None
+}
+
+/// Shown after the cursor, only the rest of a name can be accepted
+#[derive(Debug, PartialEq)]
+pub(crate) struct ReplHint {
+    display: String,
+    completion: Option<String>,
+}
+
+impl Hint for ReplHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.completion.as_deref()
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, names) = self.candidates(line, pos);
+        Ok((start, names.into_iter().map(|name| Pair { display: name.clone(), replacement: name }).collect()))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = ReplHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<ReplHint> {
+        self.hint_at(line, pos)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if self.is_command(line) {
+            return Cow::Borrowed(line);
+        }
+        Cow::Owned(highlight(line))
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{}{}{}", COMMENT, hint, RESET))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let input = ctx.input();
+        if self.is_command(input) {
+            return Ok(ValidationResult::Valid(None));
+        }
+
+        let source = format!("{}\n{}", self.pending, input);
+        Ok(match unbalanced(&source) {
+            Some(message) => ValidationResult::Invalid(Some(message)),
+            None => ValidationResult::Valid(None),
+        })
+    }
+}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("let x = len(\"a\") + 1; // one"),
+            "\x1b[35mlet\x1b[0m x = \x1b[36mlen\x1b[0m(\x1b[32m\"a\"\x1b[0m) + \x1b[33m1\x1b[0m; \x1b[90m// one\x1b[0m",
+        );
+        assert_eq!(highlight("  if (é) {"), "  \x1b[35mif\x1b[0m (\x1b[31mé\x1b[0m) {");
+        assert_eq!(highlight("f(\"open"), "f(\x1b[32m\"open\x1b[0m");
+        assert_eq!(highlight(""), "");
+    }
+
+    #[test]
+    fn test_completion_and_hints() {
+        let helper = ReplHelper { names: vec!["lengthy".to_string(), "counter".to_string()], pending: String::new() };
+
+        assert_eq!(helper.candidates("let a = le", 10), (8, vec!["len".to_string(), "lengthy".to_string(), "let".to_string()]));
+        assert_eq!(helper.candidates("cou + 1", 3), (0, vec!["counter".to_string()]));
+        assert_eq!(helper.candidates("12", 2), (0, vec![]));
+        assert_eq!(helper.candidates(":ty", 3), (0, vec![":type".to_string()]));
+        assert_eq!(helper.candidates(":type le", 8), (8, vec![]));
+
+        assert_eq!(helper.hint_at("cou", 3), Some(ReplHint { display: "nter".to_string(), completion: Some("nter".to_string()) }));
+        assert_eq!(helper.hint_at("push(", 5), Some(ReplHint { display: "array, value)".to_string(), completion: None }));
+        assert_eq!(helper.hint_at("le", 2), None);
+        assert_eq!(helper.hint_at("cou", 1), None);
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        assert_eq!(unbalanced("f([1, 2], { \"a\": 1 })"), None);
+        assert_eq!(unbalanced("f(\n[1,"), None);
+        assert_eq!(unbalanced("\"(\" + \")\")"), Some(" -- `)` closes nothing".to_string()));
+        assert_eq!(unbalanced("f([1, 2)"), Some(" -- expected `]`, found `)`".to_string()));
+    }
+}
@@ -1,11 +1,68 @@
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
 use log::{debug, error};
 
 use environment::environment::Environment;
-use evaluator::evaluator::eval;
+use error::EvaluatorError;
+use evaluator::context::{Context, Limits};
+use evaluator::evaluator::eval_with_context;
+use evaluator::module::Modules;
+use evaluator::prelude;
 use lexer::lexer::Lexer;
 use parser::parser::{Parser};
 
+/// Builds an evaluation context with the limits and module paths given on the command line
+pub fn build_context() -> Context {
+    let mut limits = Limits::default();
+
+    if let Some(max_depth) = *flags::MAX_CALL_DEPTH.lock().unwrap() {
+        limits.max_depth = Some(max_depth);
+    }
+    if let Some(max_steps) = *flags::MAX_STEPS.lock().unwrap() {
+        limits.max_steps = Some(max_steps);
+    }
+    if let Some(max_collection_size) = *flags::MAX_COLLECTION_SIZE.lock().unwrap() {
+        limits.max_collection_size = Some(max_collection_size);
+    }
+
+    let mut context = Context::new(limits);
+    let module_paths = flags::MODULE_PATHS.lock().unwrap().iter().map(PathBuf::from).collect();
+    context.modules = Modules::new(module_paths);
+
+    let timeout = flags::TIMEOUT_MS.load(Ordering::Relaxed);
+    if timeout > 0 {
+        return context.with_timeout(Duration::from_millis(timeout));
+    }
+
+    context
+}
+
+/// Binds the prelude into `environment` unless it was disabled with `--no-prelude`
+pub fn load_prelude(context: &mut Context, environment: &mut Environment) -> Result<(), EvaluatorError> {
+    if flags::NO_PRELUDE.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    prelude::load_prelude(context, environment)
+}
+
 pub fn execute_program(input: String) -> Result<(), anyhow::Error> {
+    execute(input, build_context())
+}
+
+/// Executes a file, the modules it imports are resolved relative to it
+pub fn execute_file(file: &str) -> Result<(), anyhow::Error> {
+    let input = std::fs::read_to_string(file)?;
+    let mut context = build_context();
+    context.modules.set_main_file(PathBuf::from(file));
+
+    execute(input, context)
+}
+
+/// Evaluates the program in `input` and prints its value to the output of `context`, errors are logged
+pub fn execute(input: String, mut context: Context) -> Result<(), anyhow::Error> {
     debug!("Executing program: {}", input);
     let lexer = Lexer::new(input);
     let mut parser = Parser::new(lexer)?;
@@ -26,14 +83,22 @@ pub fn execute_program(input: String) -> Result<(), anyhow::Error> {
     }
 
     let program = program.unwrap();
-    let evaluated = eval(&program, &mut environment);
-    if evaluated.is_err() {
-        error!("Error: {:?}", evaluated.err().unwrap());
+    if let Err(err) = load_prelude(&mut context, &mut environment) {
+        error!("Error: {:?}", err);
+        return Ok(());
+    }
+
+    let evaluated = eval_with_context(&program, &mut environment, &mut context);
+    if let Err(err) = &evaluated {
+        error!("Error: {:?}", err);
+        if let Some(backtrace) = context.backtrace(err) {
+            error!("Backtrace:\n{}", backtrace);
+        }
         return Ok(());
     }
 
     let evaluated = evaluated.unwrap();
-    println!("{}", evaluated);
+    context.output.print(&format!("{}\n", evaluated))?;
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use evaluator::builtins::get_builtin_signature;
+use evaluator::context::{InterruptHandle, STACK_SIZE};
+use evaluator::output::Buffer;
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::debugger::summary;
+use crate::helper::{complete_name, word_start};
+use crate::repl::{EvaluationError, Session};
+
+/// Session used by the requests without a `session`
+const DEFAULT_SESSION: &str = "default";
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Writes a response on its own line
+fn send(output: &Output, response: Value) -> Result<(), anyhow::Error> {
+    let mut output = output.lock().unwrap();
+    writeln!(output, "{}", response)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn error_response(id: &Value, kind: &str, message: &str) -> Value {
+    json!({ "id": id, "status": "error", "error": { "kind": kind, "message": message, "backtrace": null } })
+}
+
+/// Byte offset of the `cursor` of a request, counted in characters from the start of `code`, the end by default
+fn cursor(request: &Value, code: &str) -> usize {
+    match request["cursor"].as_u64() {
+        Some(cursor) => code.char_indices().nth(cursor as usize).map(|(index, _)| index).unwrap_or(code.len()),
+        None => code.len(),
+    }
+}
+
+/// Requests given to the worker and not answered yet, an `interrupt` stops all of them
+#[derive(Clone, Default)]
+struct Outstanding {
+    count: Arc<Mutex<usize>>,
+    interrupt: InterruptHandle,
+}
+
+impl Outstanding {
+    fn add(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    /// Called before the response is sent, the requests sent after it are not interrupted
+    fn done(&self) {
+        let mut count = self.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.interrupt.reset();
+        }
+    }
+
+    /// Does nothing while the worker is idle, the next request would be stopped
+    fn interrupt(&self) {
+        let count = self.count.lock().unwrap();
+        if *count > 0 {
+            self.interrupt.interrupt();
+        }
+    }
+}
+
+/// Session with the buffers its inputs print to, emptied after each `execute`
+struct KernelSession {
+    session: Session,
+    out: Buffer,
+    err: Buffer,
+}
+
+/// Sessions of the client, evaluated one request at a time
+struct Kernel {
+    sessions: HashMap<String, KernelSession>,
+    interrupt: InterruptHandle,
+}
+
+impl Kernel {
+    fn kernel_session(&mut self, request: &Value) -> &mut KernelSession {
+        let name = request["session"].as_str().unwrap_or(DEFAULT_SESSION).to_string();
+        let interrupt = self.interrupt.clone();
+        self.sessions.entry(name).or_insert_with(|| {
+            let (out, err) = (Buffer::new(), Buffer::new());
+            let session = Session::new(interrupt).with_output(evaluator::output::Output::new(out.clone(), err.clone()));
+            KernelSession { session, out, err }
+        })
+    }
+
+    fn session(&mut self, request: &Value) -> &mut Session {
+        &mut self.kernel_session(request).session
+    }
+
+    fn handle(&mut self, request: &Value) -> Value {
+        let id = &request["id"];
+        let code = request["code"].as_str().unwrap_or_default();
+        match request["type"].as_str().unwrap_or_default() {
+            "execute" => self.execute(id, code, request),
+            "complete" => {
+                let (start, matches) = complete_name(&self.session(request).names(), code, cursor(request, code));
+                json!({
+                    "id": id,
+                    "status": "ok",
+                    "matches": matches,
+                    "cursor_start": code[..start].chars().count(),
+                    "cursor_end": code[..cursor(request, code)].chars().count(),
+                })
+            }
+            "inspect" => self.inspect(id, code, request),
+            other => error_response(id, "InvalidRequest", &format!("Unknown request type {:?}", other)),
+        }
+    }
+
+    /// Evaluates the code in the session, what it prints is returned apart from its value
+    fn execute(&mut self, id: &Value, code: &str, request: &Value) -> Value {
+        let program = match Parser::new(Lexer::new(code.to_string())).map_err(|error| vec![error]).and_then(|mut parser| parser.parse_program()) {
+            Ok(program) => program,
+            Err(errors) => {
+                let message = errors.iter().map(|error| error.to_string()).collect::<Vec<_>>().join("\n");
+                let mut response = error_response(id, errors[0].kind(), &message);
+                response["output"] = json!("");
+                response["error_output"] = json!("");
+                return response;
+            }
+        };
+
+        let KernelSession { session, out, err } = self.kernel_session(request);
+        let result = session.evaluate(&program, code, None);
+        let (output, error_output) = (out.take(), err.take());
+        match result {
+            Ok(value) => json!({
+                "id": id,
+                "status": "ok",
+                "result": value.to_string(),
+                "type": value.type_name(),
+                "output": output,
+                "error_output": error_output,
+            }),
+            Err(err) => {
+                let (kind, message, backtrace) = match err.downcast_ref::<EvaluationError>() {
+                    Some(evaluation) => (evaluation.error.kind(), evaluation.error.to_string(), evaluation.backtrace.clone()),
+                    None => ("Error", err.to_string(), None),
+                };
+                json!({
+                    "id": id,
+                    "status": "error",
+                    "error": { "kind": kind, "message": message, "backtrace": backtrace },
+                    "output": output,
+                    "error_output": error_output,
+                })
+            }
+        }
+    }
+
+    /// Describes the name under the cursor: its value when it is bound, its signature when it is a builtin
+    fn inspect(&mut self, id: &Value, code: &str, request: &Value) -> Value {
+        let position = cursor(request, code);
+        let end = position + code[position..].find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_')).unwrap_or(code.len() - position);
+        let name = &code[word_start(code, position)..end];
+
+        let mut response = json!({ "id": id, "status": "ok", "found": false, "name": name });
+        if let Some(value) = self.session(request).get(name) {
+            response["found"] = json!(true);
+            response["type"] = json!(value.type_name());
+            response["value"] = json!(summary(value));
+        }
+        if let Some((signature, description)) = get_builtin_signature(name) {
+            response["found"] = json!(true);
+            response["type"] = json!("Builtin");
+            response["signature"] = json!(signature);
+            response["description"] = json!(description);
+        }
+        response
+    }
+}
+
+pub fn start() -> Result<(), anyhow::Error> {
+    serve(std::io::stdin().lock(), Box::new(std::io::stdout()))
+}
+
+/// Answers the requests read from `input`, one JSON object per line, until `shutdown` or the end of the input.
+/// `interrupt` is answered right away and stops the running `execute` and those waiting for it, the other requests are answered in order.
+/// Every request has a `type` and an `id` given back in its response:
+/// - `execute` evaluates `code`, the response has its `result` and `type`, or an `error`,
+///   and what it printed as `output` and `error_output`
+/// - `complete` gives the names that `matches` the word before the `cursor` of `code`, from `cursor_start` to `cursor_end`
+/// - `inspect` describes the name at the `cursor` of `code`: its `type` and `value`, or the `signature` of a builtin
+/// - `interrupt` stops the running and waiting `execute`, `shutdown` stops the kernel
+///
+/// `execute` is first answered with a `busy` status once it starts running.
+///
+/// `execute`, `complete` and `inspect` take an optional `session`, each session has its own bindings.
+pub fn serve(input: impl BufRead, output: Box<dyn Write + Send>) -> Result<(), anyhow::Error> {
+    let output: Output = Arc::new(Mutex::new(output));
+    let outstanding = Outstanding::default();
+    let (requests, received) = channel::<Value>();
+
+    let worker_output = output.clone();
+    let worker_outstanding = outstanding.clone();
+    // Evaluations need the stack the interpreter gives them
+    let worker = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(move || -> Result<(), anyhow::Error> {
+        let mut kernel = Kernel { sessions: HashMap::new(), interrupt: worker_outstanding.interrupt.clone() };
+        for request in received {
+            if request["type"] == "shutdown" {
+                return send(&worker_output, json!({ "id": request["id"], "status": "ok" }));
+            }
+            if request["type"] == "execute" {
+                send(&worker_output, json!({ "id": request["id"], "status": "busy" }))?;
+            }
+            let response = kernel.handle(&request);
+            worker_outstanding.done();
+            send(&worker_output, response)?;
+        }
+        Ok(())
+    })?;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                send(&output, error_response(&Value::Null, "InvalidRequest", &err.to_string()))?;
+                continue;
+            }
+        };
+
+        match request["type"].as_str() {
+            Some("interrupt") => {
+                outstanding.interrupt();
+                send(&output, json!({ "id": request["id"], "status": "ok" }))?;
+            }
+            // The worker answers `shutdown` once the requests before it are answered
+            Some("shutdown") => {
+                let _ = requests.send(request);
+                break;
+            }
+            // The worker stops once it failed to write, there is nobody left to answer
+            _ => {
+                outstanding.add();
+                if requests.send(request).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    drop(requests);
+    worker.join().map_err(|_| anyhow::anyhow!("Kernel thread panicked"))?
+}
@@ -1,2 +1,13 @@
 pub mod repl;
-pub mod interpreter;
\ No newline at end of file
+pub mod interpreter;
+pub mod debugger;
+pub mod dap;
+pub mod lsp;
+pub mod fmt;
+pub mod check;
+pub mod dump;
+pub mod testing;
+pub mod kernel;
+mod protocol;
+mod analysis;
+mod helper;
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+
+use environment::environment::Environment;
+use error::EvaluatorError;
+use evaluator::builtins::{builtin_names, get_builtin_signature};
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::analysis::{Analysis, Span, Symbol, SymbolKind};
+use crate::debugger::summary;
+use crate::interpreter::{build_context, load_prelude};
+use crate::protocol::{read_message, write_message};
+
+pub(crate) const KEYWORDS: [&str; 14] = [
+    "fn", "let", "true", "false", "if", "else", "return", "import", "export", "from", "try", "catch", "finally", "throw",
+];
+
+// Kinds of the protocol
+const DIAGNOSTIC_ERROR: u32 = 1;
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_VARIABLE: u32 = 6;
+const COMPLETION_KEYWORD: u32 = 14;
+const SYMBOL_FUNCTION: u32 = 12;
+const SYMBOL_VARIABLE: u32 = 13;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Range of the protocol, its lines and characters start at 0
+fn range(span: &Span) -> Value {
+    json!({
+        "start": { "line": span.line - 1, "character": span.start - 1 },
+        "end": { "line": span.line - 1, "character": span.end - 1 },
+    })
+}
+
+/// Line and column of a position of the protocol, in the convention of the lexer
+fn position(params: &Value) -> (u32, u32) {
+    let position = &params["position"];
+    (position["line"].as_u64().unwrap_or_default() as u32 + 1, position["character"].as_u64().unwrap_or_default() as u32 + 1)
+}
+
+/// Errors of the parser, the ones without a position are shown on the first line
+fn diagnostics(source: &str) -> Vec<Value> {
+    let errors = match Parser::new(Lexer::new(source.to_string())) {
+        Ok(mut parser) => parser.parse_program().err().unwrap_or_default(),
+        Err(error) => vec![error],
+    };
+
+    errors.iter().map(|error| {
+        let (line, column) = match error {
+            EvaluatorError::UnexpectedToken { line, column, .. }
+            | EvaluatorError::UnfinishedString { line, column, .. }
+            | EvaluatorError::IntegerTooLarge { line, column, .. }
+            | EvaluatorError::NestingTooDeep { line, column, .. } => (*line, *column),
+            _ => (1, 1),
+        };
+        let span = Span { line: line.max(1), start: column.max(1), end: column.max(1) + 1 };
+        json!({ "range": range(&span), "severity": DIAGNOSTIC_ERROR, "source": "monkey", "message": error.to_string() })
+    }).collect()
+}
+
+/// How a symbol is shown on hover and in completions
+fn detail(symbol: &Symbol) -> String {
+    match (&symbol.kind, &symbol.parameters) {
+        (SymbolKind::Let, Some(parameters)) => format!("let {} = fn({})", symbol.name, parameters.join(", ")),
+        (SymbolKind::Let, None) => format!("let {}", symbol.name),
+        (SymbolKind::Parameter, _) => format!("{} (parameter)", symbol.name),
+        (SymbolKind::Import, _) => format!("import {{ {} }}", symbol.name),
+        (SymbolKind::Catch, _) => format!("catch ({})", symbol.name),
+    }
+}
+
+fn markdown(signature: &str, description: &str) -> Value {
+    let value = if description.is_empty() {
+        format!("```monkey\n{}\n```", signature)
+    } else {
+        format!("```monkey\n{}\n```\n{}", signature, description)
+    };
+    json!({ "contents": { "kind": "markdown", "value": value } })
+}
+
+struct Server {
+    output: Box<dyn Write>,
+    documents: HashMap<String, String>,
+    /// Names and representations of the prelude functions, available in every file
+    prelude: Vec<(String, String)>,
+}
+
+impl Server {
+    fn send(&mut self, message: Value) -> Result<(), anyhow::Error> {
+        write_message(&mut self.output, &message)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> Result<(), anyhow::Error> {
+        let diagnostics = self.documents.get(uri).map(|source| diagnostics(source)).unwrap_or_default();
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    fn analysis(&self, params: &Value) -> (String, Analysis) {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        let analysis = self.documents.get(&uri).map(|source| Analysis::new(source)).unwrap_or_default();
+        (uri, analysis)
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let (uri, analysis) = self.analysis(params);
+        let (line, column) = position(params);
+        match analysis.symbol_at(line, column) {
+            Some(symbol) => json!({ "uri": uri, "range": range(&analysis.symbols[symbol].span) }),
+            None => Value::Null,
+        }
+    }
+
+    fn references(&self, params: &Value) -> Value {
+        let (uri, analysis) = self.analysis(params);
+        let (line, column) = position(params);
+        let symbol = match analysis.symbol_at(line, column) {
+            Some(symbol) => symbol,
+            None => return Value::Null,
+        };
+
+        let mut spans = analysis.references_to(symbol);
+        if params["context"]["includeDeclaration"].as_bool().unwrap_or(true) {
+            spans.insert(0, analysis.symbols[symbol].span);
+        }
+        Value::Array(spans.iter().map(|span| json!({ "uri": uri, "range": range(span) })).collect())
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (_, analysis) = self.analysis(params);
+        let (line, column) = position(params);
+        if let Some(symbol) = analysis.symbol_at(line, column) {
+            return markdown(&detail(&analysis.symbols[symbol]), "");
+        }
+
+        let name = match analysis.reference_at(line, column) {
+            Some(reference) => reference.name.clone(),
+            None => return Value::Null,
+        };
+        if let Some((_, representation)) = self.prelude.iter().find(|(prelude, _)| prelude == &name) {
+            return markdown(&format!("let {} = {}", name, representation), "Defined in the prelude");
+        }
+        match get_builtin_signature(&name) {
+            Some((signature, description)) => markdown(signature, description),
+            None => Value::Null,
+        }
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let (_, analysis) = self.analysis(params);
+        let (line, column) = position(params);
+
+        let mut items = vec![];
+        for symbol in analysis.visible_at(line, column) {
+            let kind = if symbol.parameters.is_some() { COMPLETION_FUNCTION } else { COMPLETION_VARIABLE };
+            items.push(json!({ "label": symbol.name, "kind": kind, "detail": detail(symbol) }));
+        }
+        for (name, representation) in &self.prelude {
+            items.push(json!({ "label": name, "kind": COMPLETION_FUNCTION, "detail": representation }));
+        }
+        for name in builtin_names() {
+            let detail = get_builtin_signature(name).map(|(signature, _)| signature).unwrap_or(name);
+            items.push(json!({ "label": name, "kind": COMPLETION_FUNCTION, "detail": detail }));
+        }
+        for keyword in KEYWORDS {
+            items.push(json!({ "label": keyword, "kind": COMPLETION_KEYWORD }));
+        }
+        Value::Array(items)
+    }
+
+    fn document_symbols(&self, params: &Value) -> Value {
+        let (_, analysis) = self.analysis(params);
+        Value::Array(analysis.top_level().map(|symbol| {
+            let kind = if symbol.parameters.is_some() { SYMBOL_FUNCTION } else { SYMBOL_VARIABLE };
+            json!({ "name": symbol.name, "kind": kind, "range": range(&symbol.span), "selectionRange": range(&symbol.span) })
+        }).collect())
+    }
+
+    /// Handles a message, returns false once the client asked the server to exit
+    fn handle(&mut self, message: Value) -> Result<bool, anyhow::Error> {
+        let method = message["method"].as_str().unwrap_or_default().to_string();
+        let params = &message["params"];
+
+        let result = match method.as_str() {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "monkey", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Value::Null,
+            "exit" => return Ok(false),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+                // The whole document is sent on each change
+                let text = match params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+                    Some(change) => &change["text"],
+                    None => &params["textDocument"]["text"],
+                };
+                self.documents.insert(uri.clone(), text.as_str().unwrap_or_default().to_string());
+                self.publish_diagnostics(&uri)?;
+                return Ok(true);
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+                self.documents.remove(&uri);
+                self.publish_diagnostics(&uri)?;
+                return Ok(true);
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            _ => {
+                // Notifications without a handler are ignored
+                if message.get("id").is_some() && message.get("method").is_some() {
+                    self.send(json!({
+                        "jsonrpc": "2.0",
+                        "id": message["id"],
+                        "error": { "code": METHOD_NOT_FOUND, "message": format!("Unsupported method `{}`", method) },
+                    }))?;
+                }
+                return Ok(true);
+            }
+        };
+
+        self.send(json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }))?;
+        Ok(true)
+    }
+}
+
+/// Names of the prelude functions with their representation, empty with `--no-prelude`
+fn prelude() -> Vec<(String, String)> {
+    let mut context = build_context();
+    if load_prelude(&mut context, &mut Environment::new()).is_err() {
+        return vec![];
+    }
+    context.modules.prelude.iter().map(|(name, value)| (name.clone(), summary(value))).collect()
+}
+
+/// Serves the language server over stdio until the client exits
+pub fn start() -> Result<(), anyhow::Error> {
+    serve(Box::new(std::io::stdin().lock()), Box::new(std::io::stdout()))
+}
+
+pub fn serve(mut input: Box<dyn BufRead>, output: Box<dyn Write>) -> Result<(), anyhow::Error> {
+    let mut server = Server { output, documents: HashMap::new(), prelude: prelude() };
+
+    while let Some(message) = read_message(&mut input)? {
+        if !server.handle(message)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::protocol::{frame, unframe, SharedOutput};
+
+    use super::*;
+
+    const URI: &str = "file:///main.monkey";
+
+    const SOURCE: &str = "let double = fn(x) { x * 2 };
+let values = map([1, 2], double);
+len(values) + double(3)";
+
+    fn request(id: u64, method: &str, line: u32, character: u32) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+                "context": { "includeDeclaration": true },
+            },
+        })
+    }
+
+    fn serve_messages(messages: &[Value]) -> Vec<Value> {
+        let output = SharedOutput::default();
+        serve(Box::new(Cursor::new(frame(messages))), Box::new(output.clone())).unwrap();
+        let messages = unframe(&output.0.borrow());
+        messages
+    }
+
+    fn result(messages: &[Value], id: u64) -> &Value {
+        &messages.iter().find(|message| message["id"] == id).unwrap()["result"]
+    }
+
+    fn open(text: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "monkey", "version": 1, "text": text } },
+        })
+    }
+
+    #[test]
+    fn test_navigation() {
+        let messages = serve_messages(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            open(SOURCE),
+            request(2, "textDocument/definition", 2, 17),
+            request(3, "textDocument/references", 0, 5),
+            request(4, "textDocument/hover", 2, 1),
+            request(5, "textDocument/hover", 1, 14),
+            request(6, "textDocument/hover", 0, 16),
+            request(7, "textDocument/completion", 0, 22),
+            request(8, "textDocument/documentSymbol", 0, 0),
+            request(9, "textDocument/formatting", 0, 0),
+            json!({ "jsonrpc": "2.0", "id": 10, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+
+        assert_eq!(result(&messages, 1)["capabilities"]["definitionProvider"], true);
+        assert_eq!(messages[1]["params"]["diagnostics"], json!([]));
+
+        let definition = json!({ "uri": URI, "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 10 } } });
+        assert_eq!(result(&messages, 2), &definition);
+
+        let references = result(&messages, 3).as_array().unwrap();
+        let lines = references.iter().map(|reference| reference["range"]["start"]["line"].as_u64().unwrap()).collect::<Vec<u64>>();
+        assert_eq!(lines, vec![0, 1, 2]);
+
+        assert_eq!(result(&messages, 4)["contents"]["value"], "```monkey\nlen(value)\n```\nReturns the length of a string or an array");
+        assert!(result(&messages, 5)["contents"]["value"].as_str().unwrap().contains("let map = fn(arr, f)"));
+        assert_eq!(result(&messages, 6)["contents"]["value"], "```monkey\nx (parameter)\n```");
+
+        let labels = result(&messages, 7).as_array().unwrap().iter().map(|item| item["label"].as_str().unwrap()).collect::<Vec<&str>>();
+        assert_eq!(&labels[..2], &["x", "double"]);
+        assert!(labels.contains(&"filter") && labels.contains(&"json_parse") && labels.contains(&"return"));
+        assert!(!labels.contains(&"values"));
+
+        let symbols = result(&messages, 8).as_array().unwrap();
+        assert_eq!(symbols.iter().map(|symbol| (symbol["name"].as_str().unwrap(), symbol["kind"].as_u64().unwrap())).collect::<Vec<_>>(), vec![
+            ("double", SYMBOL_FUNCTION as u64),
+            ("values", SYMBOL_VARIABLE as u64),
+        ]);
+
+        let unsupported = messages.iter().find(|message| message["id"] == 9).unwrap();
+        assert_eq!(unsupported["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(result(&messages, 10), &Value::Null);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let messages = serve_messages(&[
+            open("let x = 1;\nlet = 2;"),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didChange",
+                "params": { "textDocument": { "uri": URI, "version": 2 }, "contentChanges": [{ "text": "let x = 1;" }] },
+            }),
+        ]);
+
+        let diagnostics = messages[0]["params"]["diagnostics"].as_array().unwrap();
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["character"], 4, "the `=` of `let = 2;`");
+        assert!(diagnostics[0]["message"].as_str().unwrap().contains("IDENT"), "{}", diagnostics[0]);
+        assert_eq!(messages[1]["params"]["diagnostics"], json!([]));
+    }
+}
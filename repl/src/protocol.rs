@@ -0,0 +1,73 @@
+use std::io::{BufRead, Write};
+
+use serde_json::Value;
+
+/// Reads the next JSON message preceded by its `Content-Length` header, `None` once the input is closed.
+/// This framing is shared by the Debug Adapter Protocol and the Language Server Protocol.
+pub(crate) fn read_message(input: &mut dyn BufRead) -> Result<Option<Value>, anyhow::Error> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let mut content = vec![0; length.unwrap()];
+    input.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)?))
+}
+
+pub(crate) fn write_message(output: &mut dyn Write, message: &Value) -> Result<(), anyhow::Error> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Frames `messages` the way a client sends them
+#[cfg(test)]
+pub(crate) fn frame(messages: &[Value]) -> Vec<u8> {
+    let mut output = vec![];
+    for message in messages {
+        write_message(&mut output, message).unwrap();
+    }
+    output
+}
+
+/// Messages written by a server
+#[cfg(test)]
+pub(crate) fn unframe(mut input: &[u8]) -> Vec<Value> {
+    let mut messages = vec![];
+    while let Some(message) = read_message(&mut input).unwrap() {
+        messages.push(message);
+    }
+    messages
+}
+
+/// Output kept readable after it was given to a server or a hook
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedOutput(pub(crate) std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,14 +1,47 @@
-use anyhow::Result;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
 use log::{error, info, warn};
-use rustyline::DefaultEditor;
+use rustyline::Editor;
 use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
 
+use ast::program::Program;
+use ast::statement::Statement;
+use ast::tree::tree;
 use environment::environment::Environment;
+use environment::object::ObjectType;
+use evaluator::context::InterruptHandle;
 use error::EvaluatorError;
-use evaluator::evaluator::eval;
+use evaluator::evaluator::eval_with_context;
+use evaluator::module::Modules;
+use evaluator::output::Output;
 use lexer::lexer::Lexer;
+use lexer::token::TokenType;
 use parser::parser::{Parser};
 
+use crate::debugger::summary;
+use crate::dump::{parse, read_tokens};
+use crate::helper::ReplHelper;
+use crate::interpreter::{build_context, load_prelude};
+
+/// Names of the commands listed by `:help`
+pub(crate) const COMMANDS: [&str; 9] = [":env", ":type", ":ast", ":tokens", ":load", ":save", ":reset", ":time", ":help"];
+
+const HELP: &str = "\
+Commands:
+  :env           show the bindings defined in the session
+  :type <expr>   show the type of the value of an expression, without changing the session
+  :ast <expr>    show the tree parsed from an expression
+  :tokens <expr> show the tokens of an expression
+  :load <file>   evaluate a file in the session
+  :save <file>   write the inputs evaluated without error to a file
+  :reset         start again from an empty session
+  :time <expr>   evaluate an expression and show how long it took
+  :help          show this help";
+
 fn build_caret(column: &u32, prompt_len: &u32) -> String {
     let mut caret = String::new();
     for _ in 0..(column + prompt_len - 1) {
@@ -19,24 +52,327 @@ fn build_caret(column: &u32, prompt_len: &u32) -> String {
     caret
 }
 
+/// Prompt of the lines continuing an incomplete statement
+const CONTINUATION_PROMPT: &str = ".. ";
+
+/// Whether more lines could complete `source`: a bracket or a string is left open,
+/// it ends with an operator, or the parser reached its end while expecting more.
+/// Input that cannot be completed, like an extra closing bracket, is not incomplete.
+fn is_incomplete(source: &str) -> bool {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut depth = 0;
+    let mut last = TokenType::EOF;
+    loop {
+        match lexer.next_token() {
+            Ok(token) if token.kind == TokenType::EOF => break,
+            Ok(token) => {
+                match token.kind {
+                    TokenType::LPAREN | TokenType::LBRACE | TokenType::LBRACKET => depth += 1,
+                    TokenType::RPAREN | TokenType::RBRACE | TokenType::RBRACKET => depth -= 1,
+                    _ => {}
+                }
+                if depth < 0 {
+                    return false;
+                }
+                last = token.kind;
+            }
+            Err(EvaluatorError::UnfinishedString { .. }) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    if depth > 0 {
+        return true;
+    }
+    if matches!(last, TokenType::ASSIGN | TokenType::PLUS | TokenType::MINUS | TokenType::BANG | TokenType::ASTERISK | TokenType::SLASH
+        | TokenType::LT | TokenType::GT | TokenType::LTE | TokenType::GTE | TokenType::EQ | TokenType::NOT_EQ
+        | TokenType::COMMA | TokenType::COLON) {
+        return true;
+    }
+
+    let errors = match Parser::new(Lexer::new(source.to_string())).map(|mut parser| parser.parse_program()) {
+        Ok(Err(errors)) => errors,
+        _ => return false,
+    };
+    // A missing `;` is a mistake, typing more would not make it valid
+    return matches!(errors.first(), Some(EvaluatorError::UnexpectedToken { expected, actual, .. }) if actual == "EOF" && expected != ";");
+}
+
+/// An error raised while evaluating an input, with the functions that were running
+#[derive(Debug)]
+pub(crate) struct EvaluationError {
+    pub(crate) error: EvaluatorError,
+    pub(crate) backtrace: Option<String>,
+}
+
+impl std::fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.backtrace {
+            Some(backtrace) => write!(f, "{}\nBacktrace:\n{}", self.error, backtrace),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for EvaluationError {}
+
+/// Bindings and inputs kept from one input of the REPL to the next
+pub(crate) struct Session {
+    /// Bindings of the session, the prelude ones are in its outer scope
+    environment: Environment,
+    /// Every input starts with the prelude bindings and sources
+    modules: Modules,
+    interrupt: InterruptHandle,
+    /// Where the inputs print
+    output: Output,
+    /// Inputs evaluated without error, written by `:save`
+    inputs: Vec<String>,
+    /// `let`, `import` and `export` statements of these inputs, replayed when the session is resumed
+    definitions: Vec<String>,
+    /// File the definitions are saved to after each input
+    definitions_file: Option<PathBuf>,
+}
+
+impl Session {
+    pub(crate) fn new(interrupt: InterruptHandle) -> Self {
+        let mut environment = Environment::new();
+        let mut context = build_context();
+        if let Err(err) = load_prelude(&mut context, &mut environment) {
+            error!("Could not load the prelude: {:}", err);
+        }
+
+        return Self {
+            environment,
+            modules: context.modules,
+            interrupt,
+            output: Output::stdio(),
+            inputs: vec![],
+            definitions: vec![],
+            definitions_file: None,
+        };
+    }
+
+    /// Writes what the inputs print to `output`
+    pub(crate) fn with_output(mut self, output: Output) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Replays the definitions saved in `file` one at a time, then saves the new ones to it.
+    /// A definition failing to replay, e.g. because its file is gone, is logged and not saved again.
+    pub(crate) fn resume(&mut self, file: PathBuf) -> Result<(), anyhow::Error> {
+        let saved = if file.is_file() { Some(std::fs::read_to_string(&file)?) } else { None };
+        self.definitions_file = Some(file);
+        if let Some(source) = saved {
+            for statement in parse(&source)?.statements {
+                let input = statement.to_string();
+                if let Err(err) = self.evaluate(&Program { statements: vec![statement] }, &input, None) {
+                    warn!("Could not replay `{}`: {:}", input, err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn save_definitions(&self) {
+        let Some(file) = &self.definitions_file else {
+            return;
+        };
+        let mut source = self.definitions.join("\n");
+        if !source.is_empty() {
+            source.push('\n');
+        }
+        if let Err(err) = std::fs::write(file, source) {
+            warn!("Could not save the session to {}: {:}", file.display(), err);
+        }
+    }
+
+    /// Names bound in the session, including the prelude ones
+    pub(crate) fn names(&self) -> Vec<String> {
+        let mut names = vec![];
+        let mut scope = Some(&self.environment);
+        while let Some(environment) = scope {
+            names.extend(environment.store().keys().cloned());
+            scope = environment.outer();
+        }
+        names
+    }
+
+    /// Value bound to `name` in the session
+    pub(crate) fn get(&self, name: &str) -> Option<&ObjectType> {
+        return self.environment.get(name);
+    }
+
+    /// Evaluates `program` parsed from `input` in the session, imports are resolved relative to `file`
+    pub(crate) fn evaluate(&mut self, program: &Program, input: &str, file: Option<&str>) -> Result<ObjectType, anyhow::Error> {
+        let mut environment = self.environment.clone();
+        let value = self.evaluate_in(program, &mut environment, file)?;
+        self.environment = environment;
+        self.inputs.push(input.to_string());
+
+        let definitions = program.statements.iter()
+            .filter(|statement| matches!(statement, Statement::LetStatement { .. } | Statement::ImportStatement { .. } | Statement::ExportStatement { .. }))
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>();
+        if !definitions.is_empty() {
+            self.definitions.push(definitions.join("\n"));
+            self.save_definitions();
+        }
+        Ok(value)
+    }
+
+    /// The interrupt is not reset here, the owner of the handle resets it once nothing is running
+    fn evaluate_in(&self, program: &Program, environment: &mut Environment, file: Option<&str>) -> Result<ObjectType, anyhow::Error> {
+        let mut context = build_context().with_interrupt(self.interrupt.clone()).with_output(self.output.clone());
+        context.modules = self.modules.clone();
+        if let Some(file) = file {
+            context.modules.set_main_file(PathBuf::from(file));
+        }
+
+        eval_with_context(program, environment, &mut context).map_err(|error| {
+            let backtrace = context.backtrace(&error).map(|backtrace| backtrace.to_string());
+            anyhow!(EvaluationError { error, backtrace })
+        })
+    }
+
+    /// Runs a `:command` line, what it shows is written to `output`
+    pub(crate) fn command(&mut self, line: &str, output: &mut dyn Write) -> Result<(), anyhow::Error> {
+        let line = line.trim();
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+        if argument.is_empty() && matches!(name, ":type" | ":ast" | ":tokens" | ":load" | ":save" | ":time") {
+            return Err(anyhow!("{} needs an argument, see :help", name));
+        }
+
+        match name {
+            ":env" => {
+                let mut bindings = self.environment.store().iter().collect::<Vec<_>>();
+                bindings.sort_by(|(left, _), (right, _)| left.cmp(right));
+                for (name, value) in bindings {
+                    writeln!(output, "{} = {}", name, summary(value))?;
+                }
+            }
+            ":type" => {
+                let mut environment = self.environment.clone();
+                let value = self.evaluate_in(&parse(argument)?, &mut environment, None)?;
+                writeln!(output, "{}", value.type_name())?;
+            }
+            ":ast" => write!(output, "{}", tree(&parse(argument)?))?,
+            ":tokens" => {
+                for token in read_tokens(argument)? {
+                    writeln!(output, "{}:{} {}", token.line, token.column, token.kind)?;
+                }
+            }
+            ":load" => {
+                let source = std::fs::read_to_string(argument)?;
+                self.evaluate(&parse(&source)?, &source, Some(argument))?;
+                writeln!(output, "Loaded {}", argument)?;
+            }
+            ":save" => {
+                let mut source = self.inputs.join("\n");
+                source.push('\n');
+                std::fs::write(argument, source)?;
+                writeln!(output, "Saved {} inputs to {}", self.inputs.len(), argument)?;
+            }
+            ":reset" => {
+                let definitions_file = self.definitions_file.take();
+                *self = Session::new(self.interrupt.clone()).with_output(self.output.clone());
+                self.definitions_file = definitions_file;
+                self.save_definitions();
+                writeln!(output, "Session reset")?;
+            }
+            ":time" => {
+                let program = parse(argument)?;
+                let start = Instant::now();
+                let value = self.evaluate(&program, argument, None)?;
+                let elapsed = start.elapsed();
+                writeln!(output, "{}", value)?;
+                writeln!(output, "Time: {:?}", elapsed)?;
+            }
+            ":help" => writeln!(output, "{}", HELP)?,
+            _ => return Err(anyhow!("Unknown command {}, see :help", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Directory of the sessions: `--session-dir`, else `sessions` in the XDG data directory of the interpreter
+fn sessions_directory() -> PathBuf {
+    if let Some(directory) = flags::SESSION_DIR.lock().unwrap().clone() {
+        return PathBuf::from(directory);
+    }
+    let data = match (std::env::var_os("XDG_DATA_HOME"), std::env::var_os("HOME")) {
+        (Some(data), _) if !data.is_empty() => PathBuf::from(data),
+        (_, Some(home)) => PathBuf::from(home).join(".local").join("share"),
+        _ => PathBuf::from("."),
+    };
+    return data.join("monkey").join("sessions");
+}
+
 pub fn start(prompt: &str) -> Result<(), anyhow::Error> {
-    let mut reader = DefaultEditor::new()?;
+    let mut reader: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    reader.set_helper(Some(ReplHelper::default()));
     let lexer = Lexer::default();
     let mut parser = Parser::new(lexer)?;
-    let mut environment = Environment::new();
     let print_evaluated_result = flags::PRINT_EVALUATED_RESULT.load(std::sync::atomic::Ordering::Relaxed);
 
-    if reader.load_history("history.txt").is_err() {
+    // CTRL-C while evaluating cancels the running expression, at the prompt it is handled by rustyline
+    let interrupt = InterruptHandle::new();
+    let handler_interrupt = interrupt.clone();
+    if let Err(err) = ctrlc::set_handler(move || handler_interrupt.interrupt()) {
+        warn!("Could not install the CTRL-C handler: {:}", err);
+    }
+
+    // Each session has its own history and definitions
+    let name = flags::SESSION_NAME.lock().unwrap().clone();
+    if name.is_empty() || !name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-') {
+        return Err(anyhow!("Invalid session name {:?}, only letters, digits, `_` and `-` are allowed", name));
+    }
+    let directory = sessions_directory().join(&name);
+    std::fs::create_dir_all(&directory)?;
+    let history = directory.join("history.txt");
+
+    let mut session = Session::new(interrupt.clone());
+    match session.resume(directory.join("session.monkey")) {
+        Ok(()) => info!("Session {} ({})", name, directory.display()),
+        Err(err) => error!("Could not resume the session {}: {:}", name, err),
+    }
+
+    if reader.load_history(&history).is_err() {
         info!("No previous history.");
     }
 
+    // Lines of the statement being typed, sent to the parser once it is complete
+    let mut lines: Vec<String> = vec![];
     loop {
-        let readline = reader.readline(prompt);
-        let prompt_len = &(prompt.len() as u32);
+        // A CTRL-C of the previous input must not stop the next one
+        interrupt.reset();
+        if let Some(helper) = reader.helper_mut() {
+            helper.names = session.names();
+            helper.pending = lines.join("\n");
+        }
+        let line_prompt = if lines.is_empty() { prompt } else { CONTINUATION_PROMPT };
+        let readline = reader.readline(line_prompt);
         match readline {
-            Ok(line) => {
+            Ok(line) if lines.is_empty() && line.trim_start().starts_with(':') => {
                 reader.add_history_entry(line.as_str())?;
-                if let Err(err) = parser.reset(line) {
+                if let Err(err) = session.command(&line, &mut std::io::stdout()) {
+                    error!("Error: {:}", err);
+                }
+            }
+            Ok(line) => {
+                lines.push(line);
+                let input = lines.join("\n");
+                if is_incomplete(&input) {
+                    continue;
+                }
+                let line_count = lines.len() as u32;
+                lines.clear();
+
+                reader.add_history_entry(input.as_str())?;
+                if let Err(err) = parser.reset(input.clone()) {
                     error!("Error: {:}", err);
                     continue;
                 }
@@ -48,9 +384,12 @@ pub fn start(prompt: &str) -> Result<(), anyhow::Error> {
                     let errors = program.err().unwrap();
                     for error in errors {
                         match &error {
-                            EvaluatorError::UnexpectedToken { column, .. }  | EvaluatorError::UnfinishedString { column, .. }=> {
-                                if is_first {
-                                    println!("{}", build_caret(column, prompt_len));
+                            EvaluatorError::UnexpectedToken { line, column, .. } | EvaluatorError::UnfinishedString { line, column, .. }
+                            | EvaluatorError::IntegerTooLarge { line, column, .. } | EvaluatorError::NestingTooDeep { line, column, .. } => {
+                                // The caret can only point in the last line, the one just above it
+                                if is_first && *line == line_count {
+                                    let prompt_len = if line_count == 1 { prompt.len() } else { CONTINUATION_PROMPT.len() };
+                                    println!("{}", build_caret(column, &(prompt_len as u32)));
                                 }
                                 error!("Error: {:}", error);
                             }
@@ -64,20 +403,20 @@ pub fn start(prompt: &str) -> Result<(), anyhow::Error> {
                 }
 
                 let program = program.unwrap();
-                let evaluated = eval(&program, &mut environment);
-                if evaluated.is_err() {
-                    error!("Error: {:}", evaluated.err().unwrap());
-                    continue;
-                }
-
-                let evaluated = evaluated.unwrap();
-                if print_evaluated_result {
-                    println!("{}", evaluated);
+                match session.evaluate(&program, &input, None) {
+                    Ok(evaluated) if print_evaluated_result => println!("{}", evaluated),
+                    Ok(_) => {}
+                    Err(err) => error!("Error: {:}", err),
                 }
             }
+            Err(ReadlineError::Interrupted) if !lines.is_empty() => {
+                // Drops the incomplete statement
+                lines.clear();
+                continue;
+            }
             Err(ReadlineError::Interrupted) => {
-                warn!("CTRL-C");
-                break;
+                info!("CTRL-C, use CTRL-D to exit");
+                continue;
             }
             Err(ReadlineError::Eof) => {
                 warn!("CTRL-D");
@@ -91,8 +430,138 @@ pub fn start(prompt: &str) -> Result<(), anyhow::Error> {
     }
 
 
-    reader.save_history("history.txt")?;
+    reader.save_history(&history)?;
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use evaluator::context::InterruptHandle;
+
+    use crate::dump::parse;
+
+    use super::{is_incomplete, Session};
+
+    /// Output of each command, run one after the other in the same session
+    fn run(session: &mut Session, commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|command| {
+            let mut output = vec![];
+            match session.command(command, &mut output) {
+                Ok(()) => String::from_utf8(output).unwrap(),
+                Err(err) => format!("error: {}", err),
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        let incomplete = vec![
+            "let add = fn(a, b) {",
+            "let add = fn(a, b) {\n    a + b",
+            "[1, 2,",
+            "{\"a\": 1",
+            "f(1,\n2",
+            "\"unterminated",
+            "let a = 1 +",
+            "let a =",
+            "a == // comment",
+            "if (a)",
+            "try { f() }",
+            "import { a } from",
+        ];
+        for input in incomplete {
+            assert!(is_incomplete(input), "{:?} is incomplete", input);
+        }
+
+        let complete = vec![
+            "",
+            "let a = 1;",
+            "let add = fn(a, b) {\n    a + b\n};",
+            "if (a) { 1 } else { 2 }",
+            "\"a\\\"b\"",
+            "1 + 2)",
+            "} {",
+            "let 1",
+            "a $",
+            "let a = 1",
+            "let f = fn(x) {\n    x\n}",
+        ];
+        for input in complete {
+            assert!(!is_incomplete(input), "{:?} is complete", input);
+        }
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut session = Session::new(InterruptHandle::new());
+        for input in ["let a = 1;", "let double = fn(x) { x * 2 };", "let map = 2;"] {
+            session.evaluate(&parse(input).unwrap(), input, None).unwrap();
+        }
+        assert!(session.evaluate(&parse("let b = c;").unwrap(), "let b = c;", None).is_err());
+
+        let output = run(&mut session, &[":env", ":type double(a)", ":type fn() {}", ":type let b = 1; b", ":env", ":ast a + 1", ":tokens a[0]"]);
+        assert_eq!(output[0], "a = 1\ndouble = fn(x)\nmap = 2\n");
+        assert_eq!(output[1], "Integer\n");
+        assert_eq!(output[2], "Function\n");
+        assert_eq!(output[3], "Integer\n");
+        assert_eq!(output[4], output[0], ":type does not change the session");
+        assert_eq!(output[5], ast::tree::tree(&parse("a + 1").unwrap()));
+        assert!(output[5].starts_with("Program\n  Expression @ 1:1\n"), "{}", output[5]);
+        assert_eq!(output[6], "1:1 IDENT: a\n1:2 [\n1:3 INT: 0\n1:4 ]\n");
+
+        let file = std::env::temp_dir().join(format!("monkey-repl-{}.monkey", std::process::id()));
+        let file = file.to_str().unwrap();
+        let output = run(&mut session, &[&format!(":save {}", file), ":reset", ":env", &format!(":load {}", file), ":env"]);
+        assert_eq!(output[0], format!("Saved 3 inputs to {}\n", file));
+        assert_eq!(std::fs::read_to_string(file).unwrap(), "let a = 1;\nlet double = fn(x) { x * 2 };\nlet map = 2;\n");
+        assert_eq!(output[2], "");
+        assert_eq!(output[3], format!("Loaded {}\n", file));
+        assert_eq!(output[4], "a = 1\ndouble = fn(x)\nmap = 2\n");
+        std::fs::remove_file(file).unwrap();
+
+        let output = run(&mut session, &[":time double(21)", ":env", ":type", ":load /missing.monkey", ":what", ":ast let"]);
+        assert!(output[0].starts_with("42\nTime: "), "{}", output[0]);
+        assert_eq!(output[1], "a = 1\ndouble = fn(x)\nmap = 2\n");
+        assert_eq!(output[2], "error: :type needs an argument, see :help");
+        assert!(output[3].starts_with("error: "));
+        assert_eq!(output[4], "error: Unknown command :what, see :help");
+        assert!(output[5].starts_with("error: Unexpected token"), "{}", output[5]);
+    }
+
+    #[test]
+    fn test_resume() {
+        let directory = std::env::temp_dir().join(format!("monkey-session-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file = directory.join("session.monkey");
+
+        let mut session = Session::new(InterruptHandle::new());
+        session.resume(file.clone()).unwrap();
+        for input in ["let a = 1; a + 1;", "let text = \"a\\\"b\"; len(text);", "1 / 0;", "let add = fn(x, y) {\n  x + y\n};"] {
+            let _ = session.evaluate(&parse(input).unwrap(), input, None);
+        }
+        assert!(session.evaluate(&parse("let b = missing;").unwrap(), "let b = missing;", None).is_err());
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "let a = 1;\nlet text = \"a\\\"b\";\nlet add = fn(x, y) { (x + y); };\n",
+        );
+
+        let mut resumed = Session::new(InterruptHandle::new());
+        resumed.resume(file.clone()).unwrap();
+        let output = run(&mut resumed, &[":env", ":reset", ":env"]);
+        assert_eq!(output[0], "a = 1\nadd = fn(x, y)\ntext = a\"b\n");
+        assert_eq!(output[2], "");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "");
+
+
+        // The definitions replayed after a failing one and the later ones are still saved
+        std::fs::write(&file, "let a = 1;\nlet b = missing;\nlet c = a + 1;\n").unwrap();
+        let mut resumed = Session::new(InterruptHandle::new());
+        resumed.resume(file.clone()).unwrap();
+        resumed.evaluate(&parse("let d = 3;").unwrap(), "let d = 3;", None).unwrap();
+        assert_eq!(run(&mut resumed, &[":env"])[0], "a = 1\nc = 2\nd = 3\n");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "let a = 1;\nlet c = (a + 1);\nlet d = 3;\n");
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+
+use ast::expression::Expression;
+use ast::location::Location;
+use ast::program::Program;
+use ast::statement::Statement;
+use environment::environment::Environment;
+use error::EvaluatorError;
+use evaluator::evaluator::eval_with_context;
+use lexer::lexer::Lexer;
+use parser::parser::Parser;
+
+use crate::interpreter::{build_context, load_prelude};
+
+/// Files holding tests end with this suffix
+const TEST_FILE_SUFFIX: &str = "_test.monkey";
+/// Test functions are bound by `let` at the top level with this prefix
+const TEST_PREFIX: &str = "test_";
+
+/// A failed test, with the backtrace of its error
+#[derive(Debug)]
+struct Failure {
+    error: EvaluatorError,
+    backtrace: Option<String>,
+}
+
+/// Adds the test files found under `path`, or `path` itself when it is a file
+fn discover(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), anyhow::Error> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect::<Result<Vec<PathBuf>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let is_test = entry.file_name().and_then(|name| name.to_str()).map(|name| name.ends_with(TEST_FILE_SUFFIX)).unwrap_or(false);
+        if entry.is_dir() {
+            discover(&entry, files)?;
+        } else if is_test {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Functions without parameters bound to a `test_` name at the top level, in the order of the file
+fn test_names(program: &Program) -> Vec<String> {
+    program.statements.iter()
+        .map(|statement| match statement {
+            Statement::ExportStatement { statement } => statement.as_ref(),
+            statement => statement,
+        })
+        .filter_map(|statement| match statement {
+            Statement::LetStatement { identifier, value: Expression::FunctionLiteral { parameters, .. }, .. }
+            if identifier.starts_with(TEST_PREFIX) && parameters.is_empty() => Some(identifier.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs a test in its own environment, where the prelude and the top level of its file were evaluated
+fn run_test(file: &Path, program: &Program, name: &str) -> Result<(), Failure> {
+    let mut context = build_context();
+    context.modules.set_main_file(file.to_path_buf());
+    let mut environment = Environment::new();
+
+    let call = Program {
+        statements: vec![Statement::ExpressionStatement(Expression::CallExpression {
+            function: Box::new(Expression::Identifier(name.to_string())),
+            arguments: vec![],
+        }, Location::default())],
+    };
+
+    let setup = load_prelude(&mut context, &mut environment).and_then(|_| eval_with_context(program, &mut environment, &mut context));
+    if let Err(error) = setup {
+        let backtrace = context.backtrace(&error).map(|backtrace| backtrace.to_string());
+        return Err(Failure { error, backtrace });
+    }
+
+    eval_with_context(&call, &mut environment, &mut context).map(|_| ()).map_err(|error| {
+        // The last frame is the call made by the runner, it is not in the file
+        let backtrace = context.backtrace(&error).map(|backtrace| {
+            let lines = backtrace.to_string().lines().map(|line| line.to_string()).collect::<Vec<String>>();
+            lines[..lines.len().saturating_sub(1)].join("\n")
+        });
+        Failure { error, backtrace }
+    })
+}
+
+/// Lines of `expected` and `actual`, the differing ones are marked with `-` and `+`
+fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let expected = expected.lines().collect::<Vec<&str>>();
+    let actual = actual.lines().collect::<Vec<&str>>();
+
+    let mut lines = vec![];
+    for index in 0..expected.len().max(actual.len()) {
+        match (expected.get(index), actual.get(index)) {
+            (Some(expected), Some(actual)) if expected == actual => lines.push(format!("  {}", expected)),
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    lines.push(format!("- {}", expected));
+                }
+                if let Some(actual) = actual {
+                    lines.push(format!("+ {}", actual));
+                }
+            }
+        }
+    }
+    lines
+}
+
+fn report(failure: &Failure) -> Vec<String> {
+    let mut lines = vec![failure.error.to_string()];
+    if let EvaluatorError::AssertionNotEqual { expected, actual } = &failure.error {
+        lines.extend(diff(expected, actual));
+    }
+    if let Some(backtrace) = &failure.backtrace {
+        lines.extend(backtrace.lines().map(|line| line.trim().to_string()));
+    }
+    lines
+}
+
+/// Name of a test and how it ended
+type TestResult = (String, Result<(), Failure>);
+
+/// Runs the tests of a file, `Err` when it does not parse
+fn run_file(file: &Path) -> Result<Vec<TestResult>, anyhow::Error> {
+    let source = std::fs::read_to_string(file)?;
+    let program = Parser::new(Lexer::new(source))?.parse_program().map_err(|errors| {
+        let messages = errors.iter().map(|error| error.to_string()).collect::<Vec<String>>();
+        anyhow::anyhow!(messages.join("\n"))
+    })?;
+
+    Ok(test_names(&program).into_iter().map(|name| {
+        let result = run_test(file, &program, &name);
+        (name, result)
+    }).collect())
+}
+
+/// Runs the tests of the `*_test.monkey` files found under `paths` and prints their results.
+/// Returns whether every test passed.
+pub fn start(paths: &[String]) -> Result<bool, anyhow::Error> {
+    let mut files = vec![];
+    for path in paths {
+        discover(Path::new(path), &mut files)?;
+    }
+
+    let (mut passed, mut failed) = (0, 0);
+    for file in files {
+        println!("{}", file.display());
+        let results = match run_file(&file) {
+            Ok(results) => results,
+            Err(error) => {
+                println!("    error: {}", error);
+                failed += 1;
+                continue;
+            }
+        };
+
+        for (name, result) in results {
+            match result {
+                Ok(()) => {
+                    println!("    ok      {}", name);
+                    passed += 1;
+                }
+                Err(failure) => {
+                    println!("    FAILED  {}", name);
+                    report(&failure).iter().for_each(|line| println!("        {}", line));
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner() {
+        let directory = std::env::temp_dir().join(format!("monkey-tests-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("nested")).unwrap();
+        std::fs::write(directory.join("nested/math_test.monkey"), "let counter = [];
+let helper = fn(x) { x * 2 };
+let test_double = fn() { assert_eq(helper(2), 4); };
+let test_isolated = fn() { assert_eq(push(counter, 1), [1]); };
+let test_isolated_again = fn() { assert_eq(push(counter, 1), [1]); };
+let test_wrong = fn() {
+    assert_eq(helper(2), 5);
+};
+let test_error = fn() { assert_error(fn() { helper(\"a\") }, \"TypeMismatch\") };
+let test_with_parameter = fn(x) { assert(false) };
+").unwrap();
+        std::fs::write(directory.join("helpers.monkey"), "let test_not_a_test_file = fn() { assert(false) };").unwrap();
+
+        let mut files = vec![];
+        discover(&directory, &mut files).unwrap();
+        assert_eq!(files, vec![directory.join("nested/math_test.monkey")]);
+
+        let results = run_file(&files[0]).unwrap();
+        let outcomes = results.iter().map(|(name, result)| (name.as_str(), result.is_ok())).collect::<Vec<_>>();
+        assert_eq!(outcomes, vec![
+            ("test_double", true),
+            ("test_isolated", true),
+            ("test_isolated_again", true),
+            ("test_wrong", false),
+            ("test_error", true),
+        ]);
+
+        let failure = results[3].1.as_ref().unwrap_err();
+        let lines = report(failure);
+        assert_eq!(lines[..3], ["Assertion failed: expected 5, got 4", "- 5", "+ 4"]);
+        assert_eq!(lines.len(), 4, "{:?}", lines);
+        assert!(lines[3].starts_with("at test_wrong (line 7"), "{:?}", lines);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc", "a\nx"), vec!["  a", "- b", "+ x", "- c"]);
+    }
+}